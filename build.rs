@@ -0,0 +1,11 @@
+use vergen::EmitBuilder;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // outside a git checkout (e.g. a crates.io tarball) vergen falls back to placeholder values
+    EmitBuilder::builder()
+        .git_sha(true)
+        .rustc_semver()
+        .cargo_target_triple()
+        .emit()?;
+    Ok(())
+}
@@ -3,7 +3,7 @@ use std::str::FromStr;
 
 use clap::Parser;
 
-use super::verify_file;
+use super::{verify_file, verify_input, verify_output_template};
 
 #[derive(Debug, Parser)]
 pub enum Base64SubCommand {
@@ -11,25 +11,46 @@ pub enum Base64SubCommand {
     Encode(Base64EncodeOpts),
     #[command(name = "decode")]
     Decode(Base64DecodeOpts),
+    #[command(name = "transcode", about = "Convert between base64 variants")]
+    Transcode(Base64TranscodeOpts),
+    #[command(name = "split", about = "Encode a file as numbered base64 chunks")]
+    Split(Base64SplitOpts),
+    #[command(name = "join", about = "Reassemble numbered base64 chunks")]
+    Join(Base64JoinOpts),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Base64Format {
     Standard,
     UrlSafe,
+    /// Decode-only: the first of url-safe, standard and their padded/unpadded forms that fits
+    Auto,
 }
 
 fn parse_base64_format(format: &str) -> Result<Base64Format, anyhow::Error> {
+    match format.parse()? {
+        Base64Format::Auto => Err(anyhow::anyhow!("auto only applies when decoding")),
+        format => Ok(format),
+    }
+}
+
+fn parse_decode_format(format: &str) -> Result<Base64Format, anyhow::Error> {
     format.parse()
 }
 
+fn parse_alphabet(spec: &str) -> Result<String, anyhow::Error> {
+    crate::CustomAlphabet::parse(spec)?;
+    Ok(spec.into())
+}
+
 impl FromStr for Base64Format {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "standard" => Ok(Base64Format::Standard),
-            "urlsafe" => Ok(Base64Format::UrlSafe),
+            "urlsafe" | "url_safe" => Ok(Base64Format::UrlSafe),
+            "auto" => Ok(Base64Format::Auto),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
@@ -40,6 +61,7 @@ impl From<Base64Format> for &'static str {
         match value {
             Base64Format::Standard => "standard",
             Base64Format::UrlSafe => "urlsafe",
+            Base64Format::Auto => "auto",
         }
     }
 }
@@ -50,18 +72,144 @@ impl fmt::Display for Base64Format {
     }
 }
 
+/// How `decode --as` prints the decoded bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeAs {
+    Utf8,
+    Hex,
+    Rust,
+    C,
+}
+
+fn parse_decode_as(format: &str) -> Result<DecodeAs, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for DecodeAs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(DecodeAs::Utf8),
+            "hex" => Ok(DecodeAs::Hex),
+            "rust" => Ok(DecodeAs::Rust),
+            "c" => Ok(DecodeAs::C),
+            _ => Err(anyhow::anyhow!("Invalid output format")),
+        }
+    }
+}
+
+impl From<DecodeAs> for &'static str {
+    fn from(value: DecodeAs) -> Self {
+        match value {
+            DecodeAs::Utf8 => "utf8",
+            DecodeAs::Hex => "hex",
+            DecodeAs::Rust => "rust",
+            DecodeAs::C => "c",
+        }
+    }
+}
+
+impl fmt::Display for DecodeAs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
 #[derive(Debug, Parser)]
 pub struct Base64EncodeOpts {
+    /// A file, `-` for stdin, or a glob pattern encoding every matching file
+    #[arg(short,long,value_parser=verify_input,default_value="-")]
+    pub input: String,
+    /// Use this string as the input instead of reading --input
+    #[arg(long, conflicts_with = "input")]
+    pub text: Option<String>,
+    #[arg(long,value_parser=parse_base64_format,default_value="standard")]
+    pub format: Base64Format,
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// 64 distinct characters plus an optional pad character; overrides --format
+    #[arg(long, value_parser = parse_alphabet)]
+    pub alphabet: Option<String>,
+    /// Encode only the first N bytes of the input
+    #[arg(long)]
+    pub limit: Option<u64>,
+    /// Write each input's encoding to its own file, e.g. `{dir}/{name}.b64`
+    #[arg(long, value_parser = verify_output_template,
+        conflicts_with_all = ["output", "text", "alphabet"])]
+    pub output_template: Option<String>,
+    /// Append a `crc32:<hex>` line computed over the encoded output
+    #[arg(long, default_value_t = false, conflicts_with_all = ["alphabet", "output_template"])]
+    pub with_checksum: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct Base64SplitOpts {
     #[arg(short,long,value_parser=verify_file,default_value="-")]
     pub input: String,
     #[arg(long,value_parser=parse_base64_format,default_value="standard")]
     pub format: Base64Format,
+    #[arg(long, default_value_t = 8192, value_parser = clap::value_parser!(u32).range(1..))]
+    pub chunk_bytes: u32,
 }
 
 #[derive(Debug, Parser)]
-pub struct Base64DecodeOpts {
+pub struct Base64JoinOpts {
     #[arg(short,long,value_parser=verify_file,default_value="-")]
     pub input: String,
     #[arg(long,value_parser=parse_base64_format,default_value="standard")]
     pub format: Base64Format,
+    #[arg(short, long)]
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct Base64DecodeOpts {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    /// Use this string as the input instead of reading --input
+    #[arg(long, conflicts_with = "input")]
+    pub text: Option<String>,
+    #[arg(long,value_parser=parse_decode_format,default_value="standard")]
+    pub format: Base64Format,
+    #[arg(long)]
+    pub head: Option<usize>,
+    #[arg(long, default_value_t = false, conflicts_with = "head")]
+    pub check: bool,
+    /// Drop this many decoded bytes and write the rest raw to stdout
+    #[arg(long, conflicts_with_all = ["head", "check"])]
+    pub skip: Option<usize>,
+    /// Write at most this many decoded bytes raw to stdout
+    #[arg(long, conflicts_with_all = ["head", "check"])]
+    pub take: Option<usize>,
+    /// Print an xxd-style dump of the decoded bytes
+    #[arg(long, default_value_t = false, conflicts_with_all = ["head", "check"])]
+    pub hexdump: bool,
+    /// Write the decoded bytes to this file instead of stdout
+    #[arg(short, long, conflicts_with_all = ["head", "check", "hexdump", "skip", "take"])]
+    pub output: Option<String>,
+    /// Print the decoded bytes as utf8 text, hex, or a Rust or C string literal
+    #[arg(long = "as", default_value = "utf8", value_parser = parse_decode_as,
+        conflicts_with_all = ["check", "hexdump", "skip", "take", "output"])]
+    pub as_format: DecodeAs,
+    /// 64 distinct characters plus an optional pad character; overrides --format
+    #[arg(long, value_parser = parse_alphabet,
+        conflicts_with_all = ["check", "hexdump", "skip", "take", "as_format"])]
+    pub alphabet: Option<String>,
+    /// Replace invalid UTF-8 with U+FFFD instead of failing
+    #[arg(long, default_value_t = false, conflicts_with_all = ["check", "hexdump", "skip", "take", "output"])]
+    pub lossy: bool,
+    /// Check and drop a trailing `crc32:<hex>` line written by `encode --with-checksum`
+    #[arg(long, default_value_t = false, conflicts_with = "alphabet")]
+    pub with_checksum: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct Base64TranscodeOpts {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    #[arg(long,value_parser=parse_decode_format)]
+    pub from: Base64Format,
+    #[arg(long,value_parser=parse_base64_format)]
+    pub to: Base64Format,
 }
@@ -0,0 +1,50 @@
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+pub struct BenchOpt {
+    /// Amount of random data to process, e.g. 4096, 10MiB or 1GB
+    #[arg(long, default_value = "10MiB", value_parser = parse_size)]
+    pub size: usize,
+}
+
+fn parse_size(size: &str) -> Result<usize, &'static str> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: usize = number
+        .parse()
+        .map_err(|_| "size must start with a number")?;
+    let unit: usize = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000 * 1000,
+        "gb" => 1000 * 1000 * 1000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => return Err("size unit must be one of B, KB, MB, GB, KiB, MiB or GiB"),
+    };
+    match number.checked_mul(unit) {
+        Some(0) => Err("size must be greater than zero"),
+        Some(size) => Ok(size),
+        None => Err("size is too large"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("10MiB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1GB"), Ok(1_000_000_000));
+        assert_eq!(parse_size("2 kb"), Ok(2000));
+        assert!(parse_size("MiB").is_err());
+        assert!(parse_size("10XB").is_err());
+        assert!(parse_size("0").is_err());
+    }
+}
@@ -0,0 +1,11 @@
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct CompareOpt {
+    #[arg(long,value_parser=verify_file)]
+    pub a: String,
+    #[arg(long,value_parser=verify_file)]
+    pub b: String,
+}
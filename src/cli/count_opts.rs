@@ -0,0 +1,15 @@
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct CountOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    #[arg(short, long, default_value_t = false)]
+    pub lines: bool,
+    #[arg(short, long, default_value_t = false)]
+    pub words: bool,
+    #[arg(short = 'c', long, default_value_t = false)]
+    pub bytes: bool,
+}
@@ -1,14 +1,22 @@
 use core::fmt;
-use std::str::FromStr;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use clap::{arg, Parser};
 
-use super::verify_file;
+use super::{verify_file, verify_output_template};
 
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     Json,
     Yaml,
+    Csv,
+    Msgpack,
+    Cbor,
+    Ini,
+    Ndjson,
 }
 
 impl From<OutputFormat> for &'static str {
@@ -16,6 +24,11 @@ impl From<OutputFormat> for &'static str {
         match value {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Msgpack => "msgpack",
+            OutputFormat::Cbor => "cbor",
+            OutputFormat::Ini => "ini",
+            OutputFormat::Ndjson => "ndjson",
         }
     }
 }
@@ -26,31 +39,431 @@ impl FromStr for OutputFormat {
         match s {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
+            "csv" => Ok(OutputFormat::Csv),
+            "msgpack" => Ok(OutputFormat::Msgpack),
+            "cbor" => Ok(OutputFormat::Cbor),
+            "ini" => Ok(OutputFormat::Ini),
+            "ndjson" | "jsonl" => Ok(OutputFormat::Ndjson),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
 }
 
+impl OutputFormat {
+    /// The format implied by the extension, looking through a trailing `.gz`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let path = match path.extension()?.to_str()? {
+            "gz" => Path::new(path.file_stem()?),
+            _ => path,
+        };
+        match path.extension()?.to_str()? {
+            "json" => Some(OutputFormat::Json),
+            "yaml" | "yml" => Some(OutputFormat::Yaml),
+            "csv" => Some(OutputFormat::Csv),
+            "msgpack" => Some(OutputFormat::Msgpack),
+            "cbor" => Some(OutputFormat::Cbor),
+            "ini" => Some(OutputFormat::Ini),
+            "ndjson" | "jsonl" => Some(OutputFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, OutputFormat::Msgpack | OutputFormat::Cbor)
+    }
+}
+
 impl fmt::Display for OutputFormat {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", Into::<&str>::into(*self))
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnError {
+    Abort,
+    Skip,
+}
+
+impl From<OnError> for &'static str {
+    fn from(value: OnError) -> Self {
+        match value {
+            OnError::Abort => "abort",
+            OnError::Skip => "skip",
+        }
+    }
+}
+
+impl FromStr for OnError {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(OnError::Abort),
+            "skip" => Ok(OnError::Skip),
+            _ => Err(anyhow::anyhow!("Invalid on-error mode")),
+        }
+    }
+}
+
+impl fmt::Display for OnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    Utf8,
+    Latin1,
+    Windows1252,
+}
+
+impl From<InputEncoding> for &'static str {
+    fn from(value: InputEncoding) -> Self {
+        match value {
+            InputEncoding::Utf8 => "utf8",
+            InputEncoding::Latin1 => "latin1",
+            InputEncoding::Windows1252 => "windows-1252",
+        }
+    }
+}
+
+impl FromStr for InputEncoding {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" | "utf-8" => Ok(InputEncoding::Utf8),
+            "latin1" | "iso-8859-1" => Ok(InputEncoding::Latin1),
+            "windows-1252" | "cp1252" => Ok(InputEncoding::Windows1252),
+            _ => Err(anyhow::anyhow!("Invalid input encoding")),
+        }
+    }
+}
+
+impl fmt::Display for InputEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+/// How `--header-case` rewrites column names; `--rename`d columns are left alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderCase {
+    None,
+    Lower,
+    Snake,
+    Camel,
+}
+
+impl From<HeaderCase> for &'static str {
+    fn from(value: HeaderCase) -> Self {
+        match value {
+            HeaderCase::None => "none",
+            HeaderCase::Lower => "lower",
+            HeaderCase::Snake => "snake",
+            HeaderCase::Camel => "camel",
+        }
+    }
+}
+
+impl FromStr for HeaderCase {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(HeaderCase::None),
+            "lower" => Ok(HeaderCase::Lower),
+            "snake" => Ok(HeaderCase::Snake),
+            "camel" => Ok(HeaderCase::Camel),
+            _ => Err(anyhow::anyhow!("Invalid header case")),
+        }
+    }
+}
+
+impl fmt::Display for HeaderCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+/// What `--dedupe-keys` does with repeated header names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupeKeys {
+    Error,
+    Suffix,
+}
+
+impl From<DedupeKeys> for &'static str {
+    fn from(value: DedupeKeys) -> Self {
+        match value {
+            DedupeKeys::Error => "error",
+            DedupeKeys::Suffix => "suffix",
+        }
+    }
+}
+
+impl FromStr for DedupeKeys {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(DedupeKeys::Error),
+            "suffix" => Ok(DedupeKeys::Suffix),
+            _ => Err(anyhow::anyhow!("Invalid dedupe-keys mode")),
+        }
+    }
+}
+
+impl fmt::Display for DedupeKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+/// When csv output quotes a field (`--quote-style`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvQuoteStyle {
+    Necessary,
+    Always,
+    Never,
+    NonNumeric,
+}
+
+impl From<CsvQuoteStyle> for &'static str {
+    fn from(value: CsvQuoteStyle) -> Self {
+        match value {
+            CsvQuoteStyle::Necessary => "necessary",
+            CsvQuoteStyle::Always => "always",
+            CsvQuoteStyle::Never => "never",
+            CsvQuoteStyle::NonNumeric => "non-numeric",
+        }
+    }
+}
+
+impl FromStr for CsvQuoteStyle {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "necessary" => Ok(CsvQuoteStyle::Necessary),
+            "always" => Ok(CsvQuoteStyle::Always),
+            "never" => Ok(CsvQuoteStyle::Never),
+            "non-numeric" => Ok(CsvQuoteStyle::NonNumeric),
+            _ => Err(anyhow::anyhow!("Invalid quote style")),
+        }
+    }
+}
+
+impl fmt::Display for CsvQuoteStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
 #[derive(Debug, Parser)]
 pub struct CsvOpts {
     #[arg(short,long,value_parser=verify_file)]
     pub input: String,
     #[arg(short, long)]
     pub output: Option<String>,
-    #[arg(long,default_value="json",value_parser=parse_format)]
-    pub format: OutputFormat,
-    #[arg(short, long, default_value = ",")]
-    pub delimiter: char,
+    #[arg(long,value_parser=parse_format)]
+    pub format: Option<OutputFormat>,
+    #[arg(short, long, default_value = ",", value_parser = parse_delimiter)]
+    pub delimiter: u8,
+    #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+    pub output_delimiter: u8,
+    /// Quote csv output fields: necessary, always, never or non-numeric
+    #[arg(long, default_value = "necessary", value_parser = parse_quote_style)]
+    pub quote_style: CsvQuoteStyle,
     #[arg(long, default_value_t = true)]
     pub header: bool,
+    #[arg(long, default_value_t = false)]
+    pub trim: bool,
+    /// Append to the output file instead of replacing it (csv output only)
+    #[arg(long, default_value_t = false)]
+    pub append: bool,
+    #[arg(long, value_parser = parse_comment)]
+    pub comment: Option<u8>,
+    /// Accept rows shorter than the header, filling the missing trailing fields with "";
+    /// rows longer than the header are still an error
+    #[arg(long, default_value_t = false)]
+    pub flexible: bool,
+    #[arg(long, default_value = "abort", value_parser = parse_on_error)]
+    pub on_error: OnError,
+    /// With --on-error skip, write each skipped line, its line number and the error to this csv
+    #[arg(long)]
+    pub error_report: Option<PathBuf>,
+    /// Periodically overwrite this file with `{"rows_done": N}`, for tailing in CI
+    #[arg(long)]
+    pub progress_file: Option<PathBuf>,
+    /// Rows between --progress-file updates
+    #[arg(long, default_value_t = 10_000, requires = "progress_file", value_parser = clap::value_parser!(u64).range(1..))]
+    pub progress_every: u64,
+    #[arg(long, default_value = "utf8", value_parser = parse_input_encoding)]
+    pub input_encoding: InputEncoding,
+    #[arg(long)]
+    pub null_value: Vec<String>,
+    #[arg(long, default_value_t = false)]
+    pub empty_as_null: bool,
+    /// Embed cells holding a JSON object or array as nested values instead of strings
+    #[arg(long, default_value_t = false)]
+    pub parse_json_cells: bool,
+    #[arg(long)]
+    pub value_counts: Option<String>,
+    /// Rank the candidate delimiters on the first lines of the input and exit without converting
+    #[arg(long, default_value_t = false, conflicts_with = "value_counts")]
+    pub detect: bool,
+    #[arg(long)]
+    pub explode: Option<String>,
+    #[arg(long, default_value_t = ';', requires = "explode")]
+    pub sub_delimiter: char,
+    /// Keep only these columns, in that order
+    #[arg(long, value_delimiter = ',', conflicts_with = "columns_file")]
+    pub columns: Vec<String>,
+    /// Keep only the columns named in this file, one per line, in that order
+    #[arg(long, value_parser = verify_file)]
+    pub columns_file: Option<String>,
+    /// Move these columns to the front, keeping the rest in header order
+    #[arg(long, value_delimiter = ',')]
+    pub reorder: Vec<String>,
+    /// Rename a column in the output as OLD=NEW; repeatable
+    #[arg(long, value_parser = parse_rename)]
+    pub rename: Vec<(String, String)>,
+    #[arg(long, default_value = "none", value_parser = parse_header_case)]
+    pub header_case: HeaderCase,
+    /// Repeated header names: error, or suffix the later ones as `name_2`, `name_3`, ...
+    #[arg(long, default_value = "error", value_parser = parse_dedupe_keys)]
+    pub dedupe_keys: DedupeKeys,
+    /// JSON list of `{"name": .., "type": "string|int|float|bool"}` the columns must match
+    #[arg(long, value_parser = verify_file)]
+    pub schema: Option<String>,
+    /// Output a json/yaml object keyed by this column instead of an array
+    #[arg(long)]
+    pub key_by: Option<String>,
+    /// Name each ini section by this column (required for --format ini)
+    #[arg(long)]
+    pub section_by: Option<String>,
+    /// With --key-by, let a repeated key keep its last row instead of failing
+    #[arg(long, default_value_t = false, requires = "key_by")]
+    pub allow_dup_keys: bool,
+    /// Gzip the output; refuses to write to a terminal
+    #[arg(long, default_value_t = false, conflicts_with = "append")]
+    pub gzip: bool,
+    #[arg(long, default_value_t = false)]
+    pub stats: bool,
+    #[arg(short, long, default_value_t = false, requires = "output_dir")]
+    pub recursive: bool,
+    #[arg(long, requires = "recursive")]
+    pub output_dir: Option<PathBuf>,
+    /// Name each --recursive output from its input path relative to --output-dir, e.g.
+    /// `{dir}/{stem}.json`
+    #[arg(long, requires = "recursive", value_parser = verify_output_template)]
+    pub output_template: Option<String>,
+    /// Write `<output>.0.<format>`, `<output>.1.<format>`, ... of at most this many rows each
+    /// (ndjson and csv only); `-o out.csv` writes `out.0.csv`, ...
+    #[arg(long, requires = "output", conflicts_with_all = ["append", "gzip", "recursive"], value_parser = clap::value_parser!(u64).range(1..))]
+    pub split_rows: Option<u64>,
+    /// Set from the global `--quiet`: don't report skipped rows on stderr
+    #[arg(skip)]
+    pub quiet: bool,
+}
+
+impl CsvOpts {
+    /// The explicit `--format`, else the one implied by the `--output` extension, else JSON.
+    pub fn output_format(&self) -> OutputFormat {
+        self.format
+            .or_else(|| {
+                self.output
+                    .as_deref()
+                    .and_then(|output| OutputFormat::from_path(Path::new(output)))
+            })
+            .unwrap_or(OutputFormat::Json)
+    }
 }
 
 fn parse_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
     format.parse()
 }
+
+fn parse_on_error(mode: &str) -> Result<OnError, anyhow::Error> {
+    mode.parse()
+}
+
+fn parse_input_encoding(encoding: &str) -> Result<InputEncoding, anyhow::Error> {
+    encoding.parse()
+}
+
+fn parse_dedupe_keys(mode: &str) -> Result<DedupeKeys, anyhow::Error> {
+    mode.parse()
+}
+
+fn parse_quote_style(style: &str) -> Result<CsvQuoteStyle, anyhow::Error> {
+    style.parse()
+}
+
+fn parse_header_case(case: &str) -> Result<HeaderCase, anyhow::Error> {
+    case.parse()
+}
+
+fn parse_rename(rename: &str) -> Result<(String, String), &'static str> {
+    match rename.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err("rename must look like OLD=NEW"),
+    }
+}
+
+fn parse_comment(comment: &str) -> Result<u8, &'static str> {
+    match comment.as_bytes() {
+        [c] if c.is_ascii() => Ok(*c),
+        _ => Err("comment must be a single ASCII character"),
+    }
+}
+
+fn parse_delimiter(delimiter: &str) -> Result<u8, &'static str> {
+    match delimiter {
+        "\\t" => Ok(b'\t'),
+        d if d.len() == 1 && d.is_ascii() => Ok(d.as_bytes()[0]),
+        _ => Err("delimiter must be a single ASCII character or \\t"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(","), Ok(b','));
+        assert_eq!(parse_delimiter("\\t"), Ok(b'\t'));
+        assert_eq!(parse_delimiter("\t"), Ok(b'\t'));
+        assert!(parse_delimiter(";;").is_err());
+    }
+
+    #[test]
+    fn test_parse_rename() {
+        assert_eq!(
+            parse_rename("first_name=firstName"),
+            Ok(("first_name".into(), "firstName".into()))
+        );
+        assert!(parse_rename("first_name").is_err());
+        assert!(parse_rename("=firstName").is_err());
+    }
+
+    #[test]
+    fn test_output_format_from_path() {
+        let format = |output: &str| {
+            CsvOpts::try_parse_from(["csv", "-i", "-", "-o", output])
+                .unwrap()
+                .output_format()
+        };
+        assert!(matches!(format("data.yaml"), OutputFormat::Yaml));
+        assert!(matches!(format("data.toml"), OutputFormat::Json));
+        assert!(matches!(format("data"), OutputFormat::Json));
+        assert!(matches!(format("out.ndjson.gz"), OutputFormat::Ndjson));
+        assert!(matches!(format("out.gz"), OutputFormat::Json));
+
+        let opts =
+            CsvOpts::try_parse_from(["csv", "-i", "-", "-o", "data.yaml", "--format", "csv"])
+                .unwrap();
+        assert!(matches!(opts.output_format(), OutputFormat::Csv));
+    }
+}
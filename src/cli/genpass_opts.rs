@@ -1,9 +1,16 @@
+use core::fmt;
+use std::{path::PathBuf, str::FromStr};
+
 use clap::Parser;
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenPassOpts {
-    #[arg(short, long, default_value_t = 16)]
-    pub length: u8,
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[arg(short, long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..=1024))]
+    pub length: u16,
 
     #[arg(short = 'u', long, default_value_t = false)]
     pub has_uppercase: bool,
@@ -16,4 +23,109 @@ pub struct GenPassOpts {
 
     #[arg(short = 's', long, default_value_t = false)]
     pub has_symbol: bool,
+
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    #[arg(long, default_value_t = false, conflicts_with = "output")]
+    pub clipboard: bool,
+
+    #[arg(long, default_value = "plain", value_parser = parse_genpass_format)]
+    pub output_format: GenPassFormat,
+
+    /// Variable name for `--output-format env`
+    #[arg(long, default_value = "PASSWORD", value_parser = parse_env_name)]
+    pub name: String,
+
+    /// Also print the estimated entropy in bits to stderr
+    #[arg(long, default_value_t = false)]
+    pub show_bits: bool,
+}
+
+#[derive(Debug, Parser)]
+pub enum GenPassSubCommand {
+    #[command(about = "Tally character frequencies over many passwords to check for bias")]
+    Audit(GenPassAuditOpts),
+    #[command(about = "Compare the median strength of the preset password policies")]
+    Compare(GenPassCompareOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassCompareOpts {
+    #[arg(long, default_value_t = 200, value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples: u32,
+}
+
+#[derive(Debug, Parser)]
+pub struct GenPassAuditOpts {
+    #[arg(long, default_value_t = 100_000, value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples: u32,
+
+    #[arg(short, long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..=1024))]
+    pub length: u16,
+
+    #[arg(short = 'u', long, default_value_t = false)]
+    pub has_uppercase: bool,
+
+    #[arg(long, default_value_t = false)]
+    pub has_lowercase: bool,
+
+    #[arg(short = 'n', long, default_value_t = false)]
+    pub has_number: bool,
+
+    #[arg(short = 's', long, default_value_t = false)]
+    pub has_symbol: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenPassFormat {
+    Plain,
+    Json,
+    Env,
+}
+
+fn parse_genpass_format(format: &str) -> Result<GenPassFormat, anyhow::Error> {
+    format.parse()
+}
+
+fn parse_env_name(name: &str) -> Result<String, &'static str> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name.into())
+    } else {
+        Err("name must be a valid environment variable name")
+    }
+}
+
+impl FromStr for GenPassFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(GenPassFormat::Plain),
+            "json" => Ok(GenPassFormat::Json),
+            "env" => Ok(GenPassFormat::Env),
+            _ => Err(anyhow::anyhow!("Invalid output format")),
+        }
+    }
+}
+
+impl From<GenPassFormat> for &'static str {
+    fn from(format: GenPassFormat) -> Self {
+        match format {
+            GenPassFormat::Plain => "plain",
+            GenPassFormat::Json => "json",
+            GenPassFormat::Env => "env",
+        }
+    }
+}
+
+impl fmt::Display for GenPassFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
 }
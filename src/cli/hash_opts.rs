@@ -0,0 +1,72 @@
+use core::fmt;
+use std::{path::PathBuf, str::FromStr};
+
+use clap::Parser;
+
+use super::{verify_file, verify_input, verify_path};
+
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct HashOpts {
+    #[command(subcommand)]
+    pub cmd: Option<HashSubCommand>,
+    /// A file, `-` for stdin, or a glob pattern hashing every matching file
+    #[arg(short,long,value_parser=verify_input,default_value="-")]
+    pub input: String,
+    #[arg(long,value_parser=parse_hash_algo,default_value="sha256")]
+    pub algo: HashAlgo,
+    /// Re-hash the digest until this many hashes have been applied
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub repeat: u32,
+}
+
+#[derive(Debug, Parser)]
+pub enum HashSubCommand {
+    #[command(about = "Write or check a SHA256SUMS-style manifest for a directory")]
+    Manifest(HashManifestOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct HashManifestOpts {
+    #[arg(short, long,value_parser=verify_path)]
+    pub dir: PathBuf,
+    #[arg(long,value_parser=verify_file)]
+    pub check: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum HashAlgo {
+    Sha256,
+    Blake3,
+}
+
+fn parse_hash_algo(algo: &str) -> Result<HashAlgo, anyhow::Error> {
+    algo.parse()
+}
+
+impl FromStr for HashAlgo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashAlgo::Sha256),
+            "blake3" => Ok(HashAlgo::Blake3),
+            _ => Err(anyhow::anyhow!("Invalid algo")),
+        }
+    }
+}
+
+impl From<HashAlgo> for &'static str {
+    fn from(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha256 => "sha256",
+            HashAlgo::Blake3 => "blake3",
+        }
+    }
+}
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
@@ -0,0 +1,9 @@
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct IdentifyOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+}
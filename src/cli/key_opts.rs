@@ -0,0 +1,27 @@
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+pub enum KeySubCommand {
+    #[command(about = "Store a key in the OS keyring")]
+    Store(KeyStoreOpts),
+    #[command(about = "Delete a key from the OS keyring")]
+    Delete(KeyDeleteOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyStoreOpts {
+    #[arg(long)]
+    pub service: String,
+    #[arg(long)]
+    pub account: String,
+    #[arg(short, long, default_value = "@-")]
+    pub key: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyDeleteOpts {
+    #[arg(long)]
+    pub service: String,
+    #[arg(long)]
+    pub account: String,
+}
@@ -1,36 +1,240 @@
 mod baes64_opts;
+mod bench_opts;
+mod compare_opts;
+mod count_opts;
 mod csv_opts;
 mod genpass_opts;
+mod hash_opts;
+mod identify_opts;
+mod key_opts;
+mod pipe_opts;
 mod text_opts;
+mod version_opts;
 
-use std::path::{Path, PathBuf};
+use core::fmt;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-pub use baes64_opts::{Base64Format, Base64SubCommand};
+pub use baes64_opts::{Base64DecodeOpts, Base64Format, Base64SubCommand, DecodeAs};
+pub use bench_opts::BenchOpt;
 use clap::Parser;
-pub use csv_opts::{CsvOpts, OutputFormat};
-pub use genpass_opts::GenPassOpts;
+pub use compare_opts::CompareOpt;
+pub use count_opts::CountOpt;
+pub use csv_opts::{
+    CsvOpts, CsvQuoteStyle, DedupeKeys, HeaderCase, InputEncoding, OnError, OutputFormat,
+};
+pub use genpass_opts::{
+    GenPassAuditOpts, GenPassCompareOpts, GenPassFormat, GenPassOpts, GenPassSubCommand,
+};
+pub use hash_opts::{HashAlgo, HashManifestOpts, HashOpts, HashSubCommand};
+pub use identify_opts::IdentifyOpt;
+pub use key_opts::{KeyDeleteOpts, KeyStoreOpts, KeySubCommand};
+pub use pipe_opts::{PipeOpt, PipeStep};
 pub use text_opts::{
-    NonceGenerateOpt, TextDecryptOpt, TextEncryptOpt, TextSignFormat, TextSignOpt, TextSubCommand,
+    KeyArgs, KeyFormat, NonceGenerateOpt, SigEncoding, TestVectorsOpt, TextDecryptOpt,
+    TextEncryptOpt, TextFramesOpt, TextLinesOpt, TextOpenOpt, TextOpts, TextRekeyOpt, TextSealOpt,
+    TextSignFormat, TextSignOpt, TextSubCommand, TextVerifyDirOpt, TextVerifyFramesOpt,
     TextVerifyOpt,
 };
+pub use version_opts::VersionOpt;
 
 #[derive(Debug, Parser)]
 #[command(name = "rcil", version, author)]
 pub struct Opts {
     #[command(subcommand)]
     pub cmd: SubCommand,
+    #[arg(long, global = true, default_value_t = false)]
+    pub no_newline: bool,
+    #[arg(short, long, global = true, default_value_t = false)]
+    pub quiet: bool,
+    /// Ignore ~/.config/rcli/config.toml
+    #[arg(long, global = true, default_value_t = false)]
+    pub no_config: bool,
+    #[arg(long, global = true, default_value = "text", value_parser = parse_error_format)]
+    pub error_format: ErrorFormat,
+    /// Fail instead of waiting forever when stdin is a terminal and nothing is typed, or a
+    /// FIFO --input has no writer
+    #[arg(long, global = true, value_name = "SECS")]
+    pub stdin_timeout: Option<u64>,
+    /// Append a JSON line per command (never keys or contents) to this file
+    #[arg(long, global = true)]
+    pub audit_log: Option<PathBuf>,
+    /// Also write everything printed to stdout into this file
+    #[arg(long, global = true)]
+    pub tee: Option<PathBuf>,
+    /// In batch commands (glob inputs, csv --recursive), report failed inputs at the end
+    /// instead of stopping at the first
+    #[arg(long, global = true, default_value_t = false)]
+    pub keep_going: bool,
+    /// Bytes read at a time by the streaming encode, count and hash paths (4 KiB to 16 MiB)
+    #[arg(long, global = true, default_value_t = crate::DEFAULT_BUFFER_SIZE, value_parser = parse_buffer_size)]
+    pub buffer_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+fn parse_error_format(format: &str) -> Result<ErrorFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for ErrorFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ErrorFormat::Text),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(anyhow::anyhow!("Invalid error format")),
+        }
+    }
+}
+
+impl From<ErrorFormat> for &'static str {
+    fn from(format: ErrorFormat) -> Self {
+        match format {
+            ErrorFormat::Text => "text",
+            ErrorFormat::Json => "json",
+        }
+    }
+}
+
+impl fmt::Display for ErrorFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
 }
 
+const CSV_EXAMPLES: &str = "\
+Examples:
+  rcli csv -i players.csv                        # json, written to output.json
+  rcli csv -i players.csv -o players.yaml        # format from the extension
+  rcli csv -i players.csv --format csv --output-delimiter ';' -o players.scsv";
+
+const GENPASS_EXAMPLES: &str = "\
+Examples:
+  rcli genpass -l 24 -u -n -s
+  rcli genpass -l 20 -u --has-lowercase -n";
+
+const BASE64_EXAMPLES: &str = "\
+Examples:
+  echo hi | rcli base64 encode                   # `-i -`, stdin, is the default
+  rcli base64 encode -i logo.png --format urlsafe
+  rcli base64 decode -i logo.b64 -o logo.png";
+
+const TEXT_EXAMPLES: &str = "\
+Examples:
+  rcli text generate --format ed25519 -o keys --create-dirs
+  sig=$(rcli text sign --format ed25519 --key-file keys/ed25519.sk -i msg.txt | cut -d: -f2)
+  rcli text verify --format ed25519 --key-file keys/ed25519.pk -i msg.txt --sig \"$sig\"
+  echo secret | rcli text encrypt --key-file chacha.key --nonce chacha.nonce";
+
+const HASH_EXAMPLES: &str = "\
+Examples:
+  rcli hash -i release.tar.gz --algo blake3
+  rcli hash -i 'src/**/*.rs'
+  rcli hash manifest -d release > SHA256SUMS";
+
+const PIPE_EXAMPLES: &str = "\
+Examples:
+  rcli pipe --from players.csv --steps csv2json,minify
+  rcli pipe --from report.json --steps json2csv,gzip,b64encode --to report.b64";
+
 #[derive(Debug, Parser)]
 pub enum SubCommand {
-    #[command(name = "csv", about = "Show Csv ,or convert Csv to other type")]
+    #[command(
+        name = "csv",
+        about = "Show Csv ,or convert Csv to other type",
+        after_help = CSV_EXAMPLES
+    )]
     Csv(CsvOpts),
-    #[command(name = "genpass", about = "Generate a random password")]
+    #[command(
+        name = "genpass",
+        about = "Generate a random password",
+        after_help = GENPASS_EXAMPLES
+    )]
     GenPass(GenPassOpts),
-    #[command(subcommand)]
+    #[command(subcommand, after_help = BASE64_EXAMPLES)]
     Base64(Base64SubCommand),
-    #[command(subcommand)]
-    Text(TextSubCommand),
+    #[command(after_help = TEXT_EXAMPLES)]
+    Text(TextOpts),
+    #[command(name = "identify", about = "Guess the encoding of an unknown blob")]
+    Identify(IdentifyOpt),
+    #[command(
+        name = "hash",
+        about = "Hash a file, or write/check a directory manifest",
+        after_help = HASH_EXAMPLES
+    )]
+    Hash(HashOpts),
+    #[command(subcommand, about = "Manage keys in the OS keyring")]
+    Key(KeySubCommand),
+    #[command(name = "compare", about = "Compare two files in constant time")]
+    Compare(CompareOpt),
+    #[command(name = "count", about = "Count lines, words and bytes like wc")]
+    Count(CountOpt),
+    #[command(
+        name = "pipe",
+        about = "Chain csv, json, base64 and gzip transforms",
+        after_help = PIPE_EXAMPLES
+    )]
+    Pipe(PipeOpt),
+    #[command(
+        name = "bench",
+        about = "Measure sign and encrypt throughput",
+        hide = true
+    )]
+    Bench(BenchOpt),
+    #[command(name = "repl", about = "Run rcli commands from an interactive prompt")]
+    Repl,
+    #[command(
+        name = "test-vectors",
+        about = "Print fixed blake3, ed25519 or chacha test vectors as JSON"
+    )]
+    TestVectors(TestVectorsOpt),
+    #[command(name = "version", about = "Show version and build info")]
+    Version(VersionOpt),
+}
+
+impl SubCommand {
+    /// The command as typed, e.g. `text sign`, for the audit log.
+    pub fn name(&self) -> String {
+        match self {
+            SubCommand::Csv(_) => "csv".into(),
+            SubCommand::GenPass(opts) => match opts.cmd {
+                Some(GenPassSubCommand::Audit(_)) => "genpass audit".into(),
+                Some(GenPassSubCommand::Compare(_)) => "genpass compare".into(),
+                None => "genpass".into(),
+            },
+            SubCommand::Base64(cmd) => {
+                let name = match cmd {
+                    Base64SubCommand::Encode(_) => "encode",
+                    Base64SubCommand::Decode(_) => "decode",
+                    Base64SubCommand::Transcode(_) => "transcode",
+                    Base64SubCommand::Split(_) => "split",
+                    Base64SubCommand::Join(_) => "join",
+                };
+                format!("base64 {}", name)
+            }
+            SubCommand::Text(text) => format!("text {}", text.cmd.name()),
+            SubCommand::Identify(_) => "identify".into(),
+            SubCommand::Hash(opts) if opts.cmd.is_some() => "hash manifest".into(),
+            SubCommand::Hash(_) => "hash".into(),
+            SubCommand::Key(KeySubCommand::Store(_)) => "key store".into(),
+            SubCommand::Key(KeySubCommand::Delete(_)) => "key delete".into(),
+            SubCommand::Compare(_) => "compare".into(),
+            SubCommand::Count(_) => "count".into(),
+            SubCommand::Pipe(_) => "pipe".into(),
+            SubCommand::Bench(_) => "bench".into(),
+            SubCommand::TestVectors(_) => "test-vectors".into(),
+            SubCommand::Version(_) => "version".into(),
+            SubCommand::Repl => "repl".into(),
+        }
+    }
 }
 
 fn verify_file(filename: &str) -> Result<String, &'static str> {
@@ -41,6 +245,29 @@ fn verify_file(filename: &str) -> Result<String, &'static str> {
     }
 }
 
+/// Like `verify_file`, but also lets through glob patterns such as `src/**/*.rs`.
+fn verify_input(input: &str) -> Result<String, &'static str> {
+    if crate::is_glob(input) {
+        Ok(input.into())
+    } else {
+        verify_file(input)
+    }
+}
+
+fn verify_output_template(template: &str) -> Result<String, &'static str> {
+    match crate::expand_output_template(template, Path::new("dir/input.txt")) {
+        Ok(_) => Ok(template.into()),
+        Err(_) => Err("output template may only use {name}, {stem}, {ext} and {dir}"),
+    }
+}
+
+fn parse_buffer_size(size: &str) -> Result<usize, &'static str> {
+    match size.parse::<usize>() {
+        Ok(size) if (4 * 1024..=16 * 1024 * 1024).contains(&size) => Ok(size),
+        _ => Err("buffer size must be a byte count between 4096 and 16777216"),
+    }
+}
+
 fn verify_path(path: &str) -> Result<PathBuf, &'static str> {
     let p = Path::new(path);
     if p.exists() && p.is_dir() {
@@ -50,11 +277,11 @@ fn verify_path(path: &str) -> Result<PathBuf, &'static str> {
     }
 }
 
-fn verify_key(key: &str) -> Result<String, &'static str> {
-    if key.len() >= 32 {
+fn verify_key_file(key: &str) -> Result<String, &'static str> {
+    if key == "@-" || key.starts_with("env:") || key.starts_with("keyring:") {
         Ok(key.into())
     } else {
-        Err("key length must be or more than 32")
+        verify_file(key)
     }
 }
 
@@ -68,4 +295,73 @@ mod tests {
         assert_eq!(verify_file("Cargo.toml"), Ok("Cargo.toml".into()));
         assert_eq!(verify_file("not-exist"), Err("File does not exist"));
     }
+
+    #[test]
+    fn test_help_examples() {
+        use clap::CommandFactory;
+
+        let mut cmd = Opts::command();
+        for (name, example) in [("csv", "rcli csv -i"), ("text", "rcli text verify")] {
+            let help = cmd
+                .find_subcommand_mut(name)
+                .expect("subcommand exists")
+                .render_long_help()
+                .to_string();
+            assert!(help.contains(example), "{} help: {}", name, help);
+        }
+    }
+
+    #[test]
+    fn test_verify_input() {
+        assert_eq!(verify_input("fixtures/*.txt"), Ok("fixtures/*.txt".into()));
+        assert_eq!(verify_input("not-exist"), Err("File does not exist"));
+    }
+
+    #[test]
+    fn test_verify_output_template() {
+        assert!(verify_output_template("{dir}/out/{stem}.json").is_ok());
+        assert!(verify_output_template("{base}.json").is_err());
+    }
+
+    #[test]
+    fn test_parse_buffer_size() {
+        assert_eq!(parse_buffer_size("8192"), Ok(8192));
+        assert!(parse_buffer_size("1024").is_err());
+        assert!(parse_buffer_size("64k").is_err());
+    }
+
+    #[test]
+    fn test_verify_key_file() {
+        assert_eq!(verify_key_file("env:MY_KEY"), Ok("env:MY_KEY".into()));
+        assert_eq!(verify_key_file("@-"), Ok("@-".into()));
+        assert_eq!(verify_key_file("not-exist"), Err("File does not exist"));
+    }
+
+    #[test]
+    fn test_output_template_conflicts_with_alphabet() {
+        let args = [
+            "rcli",
+            "base64",
+            "encode",
+            "-i",
+            "fixtures/*.txt",
+            "--alphabet",
+            "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_",
+            "--output-template",
+            "{stem}.b64",
+        ];
+        let err = Opts::try_parse_from(args).unwrap_err();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_subcommand_name() {
+        for (args, name) in [
+            (&["rcli", "genpass"][..], "genpass"),
+            (&["rcli", "genpass", "audit"][..], "genpass audit"),
+            (&["rcli", "genpass", "compare"][..], "genpass compare"),
+        ] {
+            assert_eq!(Opts::parse_from(args).cmd.name(), name);
+        }
+    }
 }
@@ -0,0 +1,73 @@
+use core::fmt;
+use std::str::FromStr;
+
+use clap::Parser;
+
+use super::verify_file;
+
+#[derive(Debug, Parser)]
+pub struct PipeOpt {
+    #[arg(long, value_parser = verify_file, default_value = "-")]
+    pub from: String,
+    /// Write the result here instead of stdout
+    #[arg(long)]
+    pub to: Option<String>,
+    /// Comma-separated steps, applied in order
+    #[arg(long, required = true, value_delimiter = ',', value_parser = parse_pipe_step)]
+    pub steps: Vec<PipeStep>,
+    /// Print the size after each step to stderr
+    #[arg(short, long, default_value_t = false)]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipeStep {
+    Csv2Json,
+    Json2Csv,
+    Minify,
+    B64Encode,
+    B64Decode,
+    Gzip,
+    Gunzip,
+}
+
+fn parse_pipe_step(step: &str) -> Result<PipeStep, anyhow::Error> {
+    step.parse()
+}
+
+impl FromStr for PipeStep {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv2json" => Ok(PipeStep::Csv2Json),
+            "json2csv" => Ok(PipeStep::Json2Csv),
+            "minify" => Ok(PipeStep::Minify),
+            "b64encode" => Ok(PipeStep::B64Encode),
+            "b64decode" => Ok(PipeStep::B64Decode),
+            "gzip" => Ok(PipeStep::Gzip),
+            "gunzip" => Ok(PipeStep::Gunzip),
+            _ => Err(anyhow::anyhow!("Invalid pipe step")),
+        }
+    }
+}
+
+impl From<PipeStep> for &'static str {
+    fn from(step: PipeStep) -> Self {
+        match step {
+            PipeStep::Csv2Json => "csv2json",
+            PipeStep::Json2Csv => "json2csv",
+            PipeStep::Minify => "minify",
+            PipeStep::B64Encode => "b64encode",
+            PipeStep::B64Decode => "b64decode",
+            PipeStep::Gzip => "gzip",
+            PipeStep::Gunzip => "gunzip",
+        }
+    }
+}
+
+impl fmt::Display for PipeStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
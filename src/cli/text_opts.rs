@@ -1,9 +1,18 @@
 use core::fmt;
 use std::{path::PathBuf, str::FromStr};
 
-use clap::{arg, Parser};
+use clap::{arg, Args, Parser};
 
-use super::{verify_file, verify_key, verify_path};
+use super::{verify_file, verify_key_file, verify_output_template, verify_path};
+
+#[derive(Debug, Args)]
+pub struct TextOpts {
+    #[command(subcommand)]
+    pub cmd: TextSubCommand,
+    /// Print the input's length and blake3 fingerprint to stderr instead of running the command
+    #[arg(long, global = true, default_value_t = false)]
+    pub peek: bool,
+}
 
 #[derive(Debug, Parser)]
 pub enum TextSubCommand {
@@ -11,7 +20,17 @@ pub enum TextSubCommand {
     Sign(TextSignOpt),
     #[command(about = "Verify a text with a public/session key")]
     Verify(TextVerifyOpt),
-    #[command(about = "Generate a randow blake3 or ed25519 key")]
+    #[command(about = "Verify every file in a directory against its .sig sidecar")]
+    VerifyDir(TextVerifyDirOpt),
+    #[command(about = "Append a blake3 MAC to every line of a text")]
+    SignLines(TextLinesOpt),
+    #[command(about = "Check the per-line MACs written by sign-lines")]
+    VerifyLines(TextLinesOpt),
+    #[command(about = "Sign each u32 length-prefixed frame of a stream separately")]
+    SignFrames(TextFramesOpt),
+    #[command(about = "Check the per-frame signatures written by sign-frames")]
+    VerifyFrames(TextVerifyFramesOpt),
+    #[command(about = "Generate a randow blake3, ed25519, x25519 or chacha key")]
     Generate(KeyGenerateOpt),
     #[command(about = "encrypt text")]
     Encrypt(TextEncryptOpt),
@@ -19,68 +38,407 @@ pub enum TextSubCommand {
     Decrypt(TextDecryptOpt),
     #[command(about = "generate a random key")]
     GenerateNonce(NonceGenerateOpt),
+    #[command(about = "Ed25519-sign then encrypt text")]
+    Seal(TextSealOpt),
+    #[command(about = "Decrypt and verify text written by seal")]
+    Open(TextOpenOpt),
+    #[command(about = "Re-encrypt ciphertext under a new key")]
+    Rekey(TextRekeyOpt),
+    #[command(about = "Rewrite a 32-byte key file in another encoding")]
+    KeyConvert(KeyConvertOpt),
+    #[command(about = "Show a key's type, length, validity and fingerprint")]
+    KeyInfo(KeyInfoOpt),
+}
+
+impl TextSubCommand {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextSubCommand::Sign(_) => "sign",
+            TextSubCommand::Verify(_) => "verify",
+            TextSubCommand::VerifyDir(_) => "verify-dir",
+            TextSubCommand::SignLines(_) => "sign-lines",
+            TextSubCommand::VerifyLines(_) => "verify-lines",
+            TextSubCommand::SignFrames(_) => "sign-frames",
+            TextSubCommand::VerifyFrames(_) => "verify-frames",
+            TextSubCommand::Generate(_) => "generate",
+            TextSubCommand::Encrypt(_) => "encrypt",
+            TextSubCommand::Decrypt(_) => "decrypt",
+            TextSubCommand::GenerateNonce(_) => "generate-nonce",
+            TextSubCommand::Seal(_) => "seal",
+            TextSubCommand::Open(_) => "open",
+            TextSubCommand::Rekey(_) => "rekey",
+            TextSubCommand::KeyConvert(_) => "key-convert",
+            TextSubCommand::KeyInfo(_) => "key-info",
+        }
+    }
+
+    /// The `--input` the command reads, with its `--text` literal if it takes one.
+    pub fn input(&self) -> Option<(&str, Option<&str>)> {
+        match self {
+            TextSubCommand::Sign(opts) => Some((opts.input.as_str(), opts.text.as_deref())),
+            TextSubCommand::Verify(opts) => Some((opts.input.as_str(), opts.text.as_deref())),
+            TextSubCommand::Encrypt(opts) => Some((opts.input.as_str(), opts.text.as_deref())),
+            TextSubCommand::Seal(opts) => Some((opts.input.as_str(), opts.text.as_deref())),
+            TextSubCommand::SignLines(opts) | TextSubCommand::VerifyLines(opts) => {
+                Some((opts.input.as_str(), None))
+            }
+            TextSubCommand::SignFrames(opts) => Some((opts.input.as_str(), None)),
+            TextSubCommand::VerifyFrames(opts) => Some((opts.input.as_str(), None)),
+            TextSubCommand::Decrypt(opts) => Some((opts.input.as_str(), None)),
+            TextSubCommand::Open(opts) => Some((opts.input.as_str(), None)),
+            TextSubCommand::Rekey(opts) => Some((opts.input.as_str(), None)),
+            TextSubCommand::KeyConvert(opts) => Some((opts.input.as_str(), None)),
+            TextSubCommand::VerifyDir(_)
+            | TextSubCommand::Generate(_)
+            | TextSubCommand::GenerateNonce(_)
+            | TextSubCommand::KeyInfo(_) => None,
+        }
+    }
+}
+
+/// Where a crypto subcommand gets its key; all forms also accept `@-`, `env:VAR` and
+/// `keyring:service/account`, and with none given the key comes from `$RCLI_KEY`.
+#[derive(Debug, Args)]
+pub struct KeyArgs {
+    /// Repeatable for `verify`, which accepts a signature from any of the keys
+    #[arg(long, value_parser = verify_key_file, conflicts_with_all = ["key_literal", "key"])]
+    pub key_file: Vec<String>,
+    #[arg(long, conflicts_with = "key")]
+    pub key_literal: Option<String>,
+    /// Deprecated alias for --key-literal
+    #[arg(short, long, hide = true)]
+    pub key: Option<String>,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextSignOpt {
     #[arg(short,long,value_parser=verify_file,default_value="-")]
     pub input: String,
-    #[arg(short,long,value_parser=verify_file)]
-    pub key: String,
+    /// Use this string as the input instead of reading --input
+    #[arg(long, conflicts_with = "input")]
+    pub text: Option<String>,
+    #[command(flatten)]
+    pub key: KeyArgs,
     #[arg(long,default_value="blake3",value_parser=parse_text_sign_format)]
     pub format: TextSignFormat,
+    #[arg(long,default_value="raw",value_parser=parse_key_format)]
+    pub key_format: KeyFormat,
+    #[arg(long, default_value_t = false)]
+    pub strict_key: bool,
+    #[arg(long, default_value_t = false)]
+    pub combined: bool,
+    /// Signature encoding: url_safe (base64url), standard (base64std) or hex
+    #[arg(long, default_value = "url_safe", value_parser = parse_sig_encoding, conflicts_with = "combined")]
+    pub sig_format: SigEncoding,
+    #[arg(long, default_value_t = 32, value_parser = clap::value_parser!(u16).range(1..=64))]
+    pub mac_len: u16,
+    /// Sign a prehash (SHA-512 Ed25519ph, or a blake3 MAC of the blake3 hash); not
+    /// interchangeable with direct signatures
+    #[arg(long, default_value_t = false)]
+    pub prehash: bool,
+    /// Derive the blake3 key from the given key and this context string
+    #[arg(long)]
+    pub derive_context: Option<String>,
+    /// Ed25519 domain separation string; signatures only verify under the same context
+    #[arg(long)]
+    pub context: Option<String>,
+    /// Treat CRLF and CR line endings as LF
+    #[arg(long, default_value_t = false)]
+    pub normalize_newlines: bool,
+    /// Append a `crc32:<hex>` line computed over the encoded signature
+    #[arg(long, default_value_t = false)]
+    pub with_checksum: bool,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextVerifyOpt {
     #[arg(short,long,value_parser=verify_file,default_value="-")]
     pub input: String,
-    #[arg(short,long,value_parser=verify_file)]
-    pub key: String,
+    /// Use this string as the input instead of reading --input
+    #[arg(long, conflicts_with = "input")]
+    pub text: Option<String>,
+    #[command(flatten)]
+    pub key: KeyArgs,
+    #[arg(long, required_unless_present_any = ["combined", "sig_file"])]
+    pub sig: Option<String>,
+    /// Read the signature from this file, or from stdin with `-`
+    #[arg(long, value_parser = verify_file, conflicts_with_all = ["sig", "combined"])]
+    pub sig_file: Option<String>,
+    #[arg(long, conflicts_with = "sig")]
+    pub combined: Option<String>,
+    #[arg(long,default_value="url_safe",value_parser=parse_sig_encoding)]
+    pub sig_encoding: SigEncoding,
+    #[arg(long,default_value="blake3",value_parser=parse_text_sign_format)]
+    pub format: TextSignFormat,
+    #[arg(long,default_value="raw",value_parser=parse_key_format)]
+    pub key_format: KeyFormat,
+    #[arg(long, default_value_t = false)]
+    pub strict_key: bool,
+    #[arg(long, default_value_t = 32, value_parser = clap::value_parser!(u16).range(1..=64))]
+    pub mac_len: u16,
+    /// Verify a signature made over the prehash (SHA-512 Ed25519ph, or a blake3 MAC of the
+    /// blake3 hash); direct signatures don't verify this way
+    #[arg(long, default_value_t = false)]
+    pub prehash: bool,
+    /// Derive the blake3 key from the given key and this context string
     #[arg(long)]
-    pub sig: String,
+    pub derive_context: Option<String>,
+    /// Ed25519 domain separation string; signatures only verify under the same context
+    #[arg(long)]
+    pub context: Option<String>,
+    /// Treat CRLF and CR line endings as LF
+    #[arg(long, default_value_t = false)]
+    pub normalize_newlines: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct TestVectorsOpt {
+    #[arg(long, default_value = "blake3", value_parser = parse_text_sign_format)]
+    pub format: TextSignFormat,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextVerifyDirOpt {
+    #[arg(long, value_parser = verify_path)]
+    pub dir: PathBuf,
+    #[command(flatten)]
+    pub key: KeyArgs,
+    #[arg(long,default_value="url_safe",value_parser=parse_sig_encoding)]
+    pub sig_encoding: SigEncoding,
+    #[arg(long,default_value="blake3",value_parser=parse_text_sign_format)]
+    pub format: TextSignFormat,
+    #[arg(long,default_value="raw",value_parser=parse_key_format)]
+    pub key_format: KeyFormat,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextLinesOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    #[command(flatten)]
+    pub key: KeyArgs,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextFramesOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    #[command(flatten)]
+    pub key: KeyArgs,
     #[arg(long,default_value="blake3",value_parser=parse_text_sign_format)]
     pub format: TextSignFormat,
+    #[arg(long,default_value="raw",value_parser=parse_key_format)]
+    pub key_format: KeyFormat,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextVerifyFramesOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    /// The `<index>:<signature>` lines written by sign-frames
+    #[arg(long, value_parser = verify_file)]
+    pub sigs: String,
+    #[command(flatten)]
+    pub key: KeyArgs,
+    #[arg(long,default_value="blake3",value_parser=parse_text_sign_format)]
+    pub format: TextSignFormat,
+    #[arg(long,default_value="raw",value_parser=parse_key_format)]
+    pub key_format: KeyFormat,
 }
 
 #[derive(Debug, Parser)]
 pub struct KeyGenerateOpt {
     #[arg(long,default_value="blake3",value_parser=parse_text_sign_format)]
     pub format: TextSignFormat,
-    #[arg(short, long,value_parser=verify_path)]
+    /// Existing output directory, unless --create-dirs is given
+    #[arg(short, long)]
     pub output_path: PathBuf,
+    #[arg(long, default_value_t = false)]
+    pub create_dirs: bool,
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub count: u32,
+    /// Key file encoding; raw by default, base64 for chacha
+    #[arg(long, value_parser = parse_key_encoding)]
+    pub encoding: Option<KeyFormat>,
+    /// Derive the ed25519 keypair from this 32-byte hex seed instead of the OS RNG
+    #[arg(long, value_parser = parse_seed)]
+    pub seed: Option<[u8; 32]>,
+    /// Print the fingerprint of each public (or blake3) key to stderr
+    #[arg(long, default_value_t = false)]
+    pub print_fingerprint: bool,
+    /// Rename each key file inside --output-path, e.g. `{stem}.prod.{ext}`
+    #[arg(long, value_parser = verify_output_template)]
+    pub output_template: Option<String>,
+}
+
+impl KeyGenerateOpt {
+    /// The explicit `--encoding`, else base64 for chacha and raw for everything else.
+    pub fn encoding(&self) -> KeyFormat {
+        self.encoding.unwrap_or(match self.format {
+            TextSignFormat::Chacha => KeyFormat::Base64,
+            _ => KeyFormat::Raw,
+        })
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyConvertOpt {
+    #[arg(long = "in", value_parser = verify_file, default_value = "-")]
+    pub input: String,
+    #[arg(long, default_value = "raw", value_parser = parse_key_encoding)]
+    pub in_encoding: KeyFormat,
+    #[arg(long, default_value = "base64", value_parser = parse_key_encoding)]
+    pub out_encoding: KeyFormat,
+    /// Write the converted key here (mode 0600) instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+pub struct KeyInfoOpt {
+    #[arg(long, value_parser = verify_key_file)]
+    pub key: String,
+    #[arg(long, default_value = "blake3", value_parser = parse_text_sign_format)]
+    pub format: TextSignFormat,
+    #[arg(long, default_value = "raw", value_parser = parse_key_encoding)]
+    pub key_format: KeyFormat,
+    /// The key is an ed25519 secret key (a seed), so skip the curve point check
+    #[arg(long, default_value_t = false)]
+    pub secret: bool,
 }
 
 #[derive(Debug, Parser)]
 pub struct NonceGenerateOpt {
-    #[arg(short, long,value_parser=verify_path)]
+    /// Existing output directory, unless --create-dirs is given
+    #[arg(short, long)]
     pub output_path: PathBuf,
+    #[arg(long, default_value_t = false)]
+    pub create_dirs: bool,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextEncryptOpt {
     #[arg(short,long,value_parser=verify_file,default_value="-")]
     pub input: String,
-    #[arg(short, long,value_parser=verify_key)]
-    pub key: String,
+    /// Use this string as the input instead of reading --input
+    #[arg(long, conflicts_with = "input")]
+    pub text: Option<String>,
+    #[command(flatten)]
+    pub key: KeyArgs,
+    #[arg(short, long,value_parser=verify_file,default_value="fixtures/chacha2.nonce")]
+    pub nonce: String,
+    #[arg(long, value_parser = parse_base_nonce, requires = "message_index")]
+    pub base_nonce: Option<[u8; 8]>,
+    #[arg(long, requires = "base_nonce")]
+    pub message_index: Option<u32>,
+    #[arg(long, default_value = "")]
+    pub aad: String,
+    /// Encrypt to an x25519 public key instead of a shared key
+    #[arg(long, value_parser = verify_key_file, conflicts_with_all = ["key_file", "key_literal", "key", "base_nonce"])]
+    pub recipient: Option<String>,
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Write the ciphertext bytes to --output instead of base64
+    #[arg(long, default_value_t = false, requires = "output")]
+    pub raw: bool,
+    /// Print the base64 ciphertext as a QR code (requires the `qr` feature)
+    #[arg(long, default_value_t = false, conflicts_with = "raw")]
+    pub qr: bool,
+    /// Also save the QR code as a PNG image
+    #[arg(long, requires = "qr")]
+    pub qr_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextSealOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    /// Use this string as the input instead of reading --input
+    #[arg(long, conflicts_with = "input")]
+    pub text: Option<String>,
+    /// Ed25519 private key to sign with
+    #[arg(long, value_parser = verify_key_file)]
+    pub key_sign: String,
+    /// 32-byte ChaCha20-Poly1305 key to encrypt with
+    #[arg(long, value_parser = verify_key_file)]
+    pub key_enc: String,
+    #[arg(short, long,value_parser=verify_file,default_value="fixtures/chacha2.nonce")]
+    pub nonce: String,
+    #[arg(long, default_value = "")]
+    pub aad: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextOpenOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    /// Ed25519 public key of the sender
+    #[arg(long, value_parser = verify_key_file)]
+    pub key_verify: String,
+    #[arg(long, value_parser = verify_key_file)]
+    pub key_enc: String,
     #[arg(short, long,value_parser=verify_file,default_value="fixtures/chacha2.nonce")]
     pub nonce: String,
+    #[arg(long, default_value = "")]
+    pub aad: String,
+}
+
+#[derive(Debug, Parser)]
+pub struct TextRekeyOpt {
+    #[arg(short,long,value_parser=verify_file,default_value="-")]
+    pub input: String,
+    #[arg(long, value_parser = verify_key_file)]
+    pub old_key: String,
+    #[arg(long, value_parser = verify_key_file)]
+    pub new_key: String,
+    #[arg(short, long,value_parser=verify_file,default_value="fixtures/chacha2.nonce")]
+    pub nonce: String,
+    /// Nonce for the new ciphertext; by default a fresh one is generated
+    #[arg(long, value_parser = verify_file, conflicts_with = "reuse_nonce")]
+    pub new_nonce: Option<String>,
+    /// Where to write the generated nonce; defaults to `<input>.nonce`
+    #[arg(long, conflicts_with_all = ["new_nonce", "reuse_nonce"])]
+    pub new_nonce_out: Option<PathBuf>,
+    /// Encrypt under the old --nonce again instead of a fresh one
+    #[arg(long, default_value_t = false)]
+    pub reuse_nonce: bool,
+    #[arg(long, default_value = "")]
+    pub aad: String,
 }
 
 #[derive(Debug, Parser)]
 pub struct TextDecryptOpt {
     #[arg(short,long,value_parser=verify_file,default_value="-")]
     pub input: String,
-    #[arg(short, long,value_parser=verify_key)]
-    pub key: String,
+    #[command(flatten)]
+    pub key: KeyArgs,
     #[arg(short, long,value_parser=verify_file,default_value="fixtures/chacha2.nonce")]
     pub nonce: String,
+    #[arg(long, value_parser = parse_base_nonce, requires = "message_index")]
+    pub base_nonce: Option<[u8; 8]>,
+    #[arg(long, requires = "base_nonce")]
+    pub message_index: Option<u32>,
+    #[arg(long, default_value = "")]
+    pub aad: String,
+    /// Decrypt with the x25519 private key matching `encrypt --recipient`
+    #[arg(long, value_parser = verify_key_file, conflicts_with_all = ["key_file", "key_literal", "key", "base_nonce"])]
+    pub identity: Option<String>,
+    /// Replace invalid UTF-8 in the plaintext with U+FFFD
+    #[arg(long, default_value_t = false)]
+    pub lossy: bool,
+    /// Read raw ciphertext bytes, as written by `encrypt --raw`, instead of base64
+    #[arg(long, default_value_t = false)]
+    pub raw: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum TextSignFormat {
     Blake3,
     Ed25519,
+    /// Encryption-only keypairs for `encrypt --recipient`/`decrypt --identity`
+    X25519,
+    /// A ChaCha20-Poly1305 key and nonce for `encrypt`/`decrypt`; `generate` only
+    Chacha,
 }
 
 fn parse_text_sign_format(format: &str) -> Result<TextSignFormat, anyhow::Error> {
@@ -94,6 +452,8 @@ impl FromStr for TextSignFormat {
         match s {
             "blake3" => Ok(TextSignFormat::Blake3),
             "ed25519" => Ok(TextSignFormat::Ed25519),
+            "x25519" => Ok(TextSignFormat::X25519),
+            "chacha" => Ok(TextSignFormat::Chacha),
             _ => Err(anyhow::anyhow!("Invalid format")),
         }
     }
@@ -104,6 +464,8 @@ impl From<TextSignFormat> for &'static str {
         match format {
             TextSignFormat::Blake3 => "blake3",
             TextSignFormat::Ed25519 => "ed25519",
+            TextSignFormat::X25519 => "x25519",
+            TextSignFormat::Chacha => "chacha",
         }
     }
 }
@@ -113,3 +475,105 @@ impl fmt::Display for TextSignFormat {
         write!(f, "{}", Into::<&str>::into(*self))
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+pub enum KeyFormat {
+    Raw,
+    Hex,
+    Base64,
+    Pkcs8,
+}
+
+fn parse_key_format(format: &str) -> Result<KeyFormat, anyhow::Error> {
+    format.parse()
+}
+
+fn parse_key_encoding(encoding: &str) -> Result<KeyFormat, anyhow::Error> {
+    match encoding.parse::<KeyFormat>()? {
+        KeyFormat::Pkcs8 => Err(anyhow::anyhow!("pkcs8 is not a supported key encoding")),
+        encoding => Ok(encoding),
+    }
+}
+
+impl FromStr for KeyFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(KeyFormat::Raw),
+            "hex" => Ok(KeyFormat::Hex),
+            "base64" => Ok(KeyFormat::Base64),
+            "pkcs8" => Ok(KeyFormat::Pkcs8),
+            _ => Err(anyhow::anyhow!("Invalid key format")),
+        }
+    }
+}
+
+impl From<KeyFormat> for &'static str {
+    fn from(format: KeyFormat) -> Self {
+        match format {
+            KeyFormat::Raw => "raw",
+            KeyFormat::Hex => "hex",
+            KeyFormat::Base64 => "base64",
+            KeyFormat::Pkcs8 => "pkcs8",
+        }
+    }
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SigEncoding {
+    UrlSafe,
+    Standard,
+    Hex,
+}
+
+fn parse_base_nonce(base: &str) -> Result<[u8; 8], &'static str> {
+    let base = hex::decode(base).map_err(|_| "base nonce must be hex")?;
+    base.try_into()
+        .map_err(|_| "base nonce must be 8 bytes (16 hex digits)")
+}
+
+fn parse_seed(seed: &str) -> Result<[u8; 32], &'static str> {
+    let seed = hex::decode(seed).map_err(|_| "seed must be hex")?;
+    seed.try_into()
+        .map_err(|_| "seed must be 32 bytes (64 hex digits)")
+}
+
+fn parse_sig_encoding(encoding: &str) -> Result<SigEncoding, anyhow::Error> {
+    encoding.parse()
+}
+
+impl FromStr for SigEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "url_safe" | "base64url" => Ok(SigEncoding::UrlSafe),
+            "standard" | "base64std" => Ok(SigEncoding::Standard),
+            "hex" => Ok(SigEncoding::Hex),
+            _ => Err(anyhow::anyhow!("Invalid signature encoding")),
+        }
+    }
+}
+
+impl From<SigEncoding> for &'static str {
+    fn from(encoding: SigEncoding) -> Self {
+        match encoding {
+            SigEncoding::UrlSafe => "url_safe",
+            SigEncoding::Standard => "standard",
+            SigEncoding::Hex => "hex",
+        }
+    }
+}
+
+impl fmt::Display for SigEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
@@ -0,0 +1,7 @@
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+pub struct VersionOpt {
+    #[arg(long, default_value_t = false)]
+    pub json: bool,
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use clap::{parser::ValueSource, ArgMatches};
+use directories::ProjectDirs;
+use serde::Deserialize;
+
+use crate::{Opts, SubCommand};
+
+/// Per-subcommand defaults from `~/.config/rcli/config.toml`; flags given on the command line win.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub genpass: GenPassConfig,
+    pub csv: CsvConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenPassConfig {
+    pub length: Option<u16>,
+    pub has_uppercase: Option<bool>,
+    pub has_lowercase: Option<bool>,
+    pub has_number: Option<bool>,
+    pub has_symbol: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvConfig {
+    pub format: Option<String>,
+    pub delimiter: Option<char>,
+    pub trim: Option<bool>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "rcli").map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+
+    /// Load the default config file, or an empty config if there is none.
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        toml::from_str(&content).with_context(|| format!("invalid config {}", path.display()))
+    }
+
+    /// Apply the config to every option that `matches` says was not set on the command line.
+    pub fn apply(&self, opts: &mut Opts, matches: &ArgMatches) -> Result<()> {
+        match (&mut opts.cmd, matches.subcommand()) {
+            (SubCommand::GenPass(opts), Some((_, matches))) => {
+                let config = &self.genpass;
+                if let Some(length) = config.length {
+                    if !(1..=1024).contains(&length) {
+                        anyhow::bail!("genpass.length must be between 1 and 1024");
+                    }
+                }
+                set(&mut opts.length, config.length, matches, "length");
+                set(
+                    &mut opts.has_uppercase,
+                    config.has_uppercase,
+                    matches,
+                    "has_uppercase",
+                );
+                set(
+                    &mut opts.has_lowercase,
+                    config.has_lowercase,
+                    matches,
+                    "has_lowercase",
+                );
+                set(
+                    &mut opts.has_number,
+                    config.has_number,
+                    matches,
+                    "has_number",
+                );
+                set(
+                    &mut opts.has_symbol,
+                    config.has_symbol,
+                    matches,
+                    "has_symbol",
+                );
+            }
+            (SubCommand::Csv(opts), Some((_, matches))) => {
+                let config = &self.csv;
+                let format = config.format.as_deref().map(str::parse).transpose()?;
+                set(&mut opts.format, format.map(Some), matches, "format");
+                let delimiter = config
+                    .delimiter
+                    .map(|d| u8::try_from(d).context("csv.delimiter must be an ASCII character"))
+                    .transpose()?;
+                set(&mut opts.delimiter, delimiter, matches, "delimiter");
+                set(&mut opts.trim, config.trim, matches, "trim");
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>, matches: &ArgMatches, id: &str) {
+    if let Some(value) = value {
+        if matches.value_source(id) != Some(ValueSource::CommandLine) {
+            *field = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+
+    fn parse(config: &Config, args: &[&str]) -> Result<Opts> {
+        let matches = Opts::command().try_get_matches_from(args)?;
+        let mut opts = Opts::from_arg_matches(&matches)?;
+        config.apply(&mut opts, &matches)?;
+        Ok(opts)
+    }
+
+    #[test]
+    fn test_config_overrides_defaults_only() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rcli_config_{}.toml", std::process::id()));
+        fs::write(&path, "[genpass]\nlength = 24\nhas_symbol = true\n")?;
+        let config = Config::load_from(&path)?;
+        fs::remove_file(&path)?;
+
+        let SubCommand::GenPass(opts) = parse(&config, &["rcli", "genpass"])?.cmd else {
+            panic!("expected genpass");
+        };
+        assert_eq!(opts.length, 24);
+        assert!(opts.has_symbol);
+
+        let SubCommand::GenPass(opts) = parse(&config, &["rcli", "genpass", "-l", "8"])?.cmd else {
+            panic!("expected genpass");
+        };
+        assert_eq!(opts.length, 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_keys() {
+        assert!(toml::from_str::<Config>("[genpass]\nlenght = 24\n").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::{fmt, io, str::Utf8Error, string::FromUtf8Error};
+
+/// Error categories surfaced at the process boundary, each with its own exit code:
+///
+/// - `1` verification failure (bad signature, manifest mismatch)
+/// - `2` bad input or I/O (unreadable files, malformed base64/hex/CSV/UTF-8)
+/// - `3` crypto failure (encrypt/decrypt, invalid key material)
+/// - `4` anything else
+/// - `130` interrupted by Ctrl-C, as a shell reports a process killed by SIGINT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    Verification(String),
+    Input(String),
+    Crypto(String),
+    Other(String),
+    Interrupted,
+}
+
+impl AppError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Verification(_) => 1,
+            AppError::Input(_) => 2,
+            AppError::Crypto(_) => 3,
+            AppError::Other(_) => 4,
+            AppError::Interrupted => 130,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AppError::Verification(_) => "verification",
+            AppError::Input(_) => "input",
+            AppError::Crypto(_) => "crypto",
+            AppError::Other(_) => "other",
+            AppError::Interrupted => "interrupted",
+        }
+    }
+
+    /// `{"error":"<message>","kind":"<kind>"}`, for `--error-format json`.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({ "error": self.to_string(), "kind": self.kind() }).to_string()
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Verification(msg)
+            | AppError::Input(msg)
+            | AppError::Crypto(msg)
+            | AppError::Other(msg) => write!(f, "{}", msg),
+            AppError::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Typed errors for embedding `rcli` as a library, so callers can match on specific failures.
+#[derive(Debug, thiserror::Error)]
+pub enum RcliError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+    #[error(transparent)]
+    Utf8(#[from] Utf8Error),
+    #[error("{0}")]
+    Crypto(String),
+    #[error(transparent)]
+    CsvParse(#[from] csv::Error),
+    #[error("key length must be {expected} bytes, got {actual}")]
+    BadKeyLength { expected: usize, actual: usize },
+    #[error("{0}")]
+    VerifyFailed(String),
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+pub type RcliResult<T> = Result<T, RcliError>;
+
+impl From<FromUtf8Error> for RcliError {
+    fn from(err: FromUtf8Error) -> Self {
+        RcliError::Utf8(err.utf8_error())
+    }
+}
+
+/// Recover the typed error from the `anyhow` plumbing used inside the crate.
+impl From<anyhow::Error> for RcliError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<RcliError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<io::Error>() {
+            Ok(err) => return RcliError::Io(err),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<base64::DecodeError>() {
+            Ok(err) => return RcliError::Base64(err),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<csv::Error>() {
+            Ok(err) => return RcliError::CsvParse(err),
+            Err(err) => err,
+        };
+        match err.downcast_ref::<AppError>() {
+            Some(AppError::Crypto(msg)) => RcliError::Crypto(msg.clone()),
+            Some(AppError::Verification(msg)) => RcliError::VerifyFailed(msg.clone()),
+            _ => RcliError::Other(err),
+        }
+    }
+}
+
+impl From<AppError> for RcliError {
+    fn from(err: AppError) -> Self {
+        anyhow::Error::from(err).into()
+    }
+}
+
+impl From<&RcliError> for AppError {
+    fn from(err: &RcliError) -> Self {
+        let msg = err.to_string();
+        match err {
+            RcliError::Io(_)
+            | RcliError::Base64(_)
+            | RcliError::Utf8(_)
+            | RcliError::CsvParse(_)
+            | RcliError::BadKeyLength { .. } => AppError::Input(msg),
+            RcliError::Crypto(_) => AppError::Crypto(msg),
+            RcliError::VerifyFailed(_) => AppError::Verification(msg),
+            RcliError::Other(err) => AppError::classify(err),
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        AppError::classify(&err)
+    }
+}
+
+impl AppError {
+    fn classify(err: &anyhow::Error) -> Self {
+        if let Some(err) = err.downcast_ref::<AppError>() {
+            return err.clone();
+        }
+        if let Some(err) = err.downcast_ref::<RcliError>() {
+            return err.into();
+        }
+        let msg = format!("{:#}", err);
+        if err.chain().any(|e| e.is::<ed25519_dalek::SignatureError>()) {
+            AppError::Crypto(msg)
+        } else if err.chain().any(|e| {
+            e.is::<io::Error>()
+                || e.is::<csv::Error>()
+                || e.is::<base64::DecodeError>()
+                || e.is::<hex::FromHexError>()
+                || e.is::<FromUtf8Error>()
+                || e.is::<Utf8Error>()
+                || e.is::<serde_json::Error>()
+        }) {
+            AppError::Input(msg)
+        } else {
+            AppError::Other(msg)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_error_exit_codes() {
+        let err = anyhow::Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(AppError::from(err).exit_code(), 2);
+
+        let err = anyhow::Error::from(AppError::Crypto("decrypt error".into()));
+        assert_eq!(AppError::from(err).exit_code(), 3);
+
+        let err = anyhow::Error::from(AppError::Verification("bad signature".into()));
+        assert_eq!(AppError::from(err).exit_code(), 1);
+
+        let err = anyhow::anyhow!("something else");
+        assert_eq!(AppError::from(err).exit_code(), 4);
+
+        let err = anyhow::Error::from(AppError::Interrupted);
+        assert_eq!(AppError::from(err).exit_code(), 130);
+
+        let err = anyhow::Error::from(RcliError::BadKeyLength {
+            expected: 32,
+            actual: 10,
+        });
+        assert_eq!(AppError::from(err).exit_code(), 2);
+
+        let err = anyhow::Error::from(RcliError::VerifyFailed("bad signature".into()));
+        assert_eq!(AppError::from(err).exit_code(), 1);
+    }
+
+    #[test]
+    fn test_rcli_error_from_anyhow() {
+        let err = anyhow::Error::from(AppError::Crypto("decrypt error".into()));
+        assert!(matches!(RcliError::from(err), RcliError::Crypto(msg) if msg == "decrypt error"));
+
+        let err = anyhow::Error::from(AppError::Verification("bad signature".into()));
+        let err = RcliError::from(err);
+        assert!(matches!(&err, RcliError::VerifyFailed(msg) if msg == "bad signature"));
+        assert_eq!(AppError::from(&err).to_string(), "bad signature");
+
+        let err = anyhow::Error::from(io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert!(matches!(RcliError::from(err), RcliError::Io(_)));
+
+        let err = anyhow::anyhow!("something else");
+        assert!(matches!(RcliError::from(err), RcliError::Other(_)));
+    }
+}
@@ -1,7 +1,1036 @@
 mod cli;
+mod config;
+mod error;
 mod process;
 mod utils;
 
 pub use cli::*;
+pub use config::*;
+pub use error::*;
 pub use process::*;
 pub use utils::*;
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, IsTerminal, Read, Write},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use clap::Parser;
+use rustyline::{error::ReadlineError, DefaultEditor};
+use zxcvbn::zxcvbn;
+
+/// Lines sampled by `csv --detect`.
+const DETECT_LINES: usize = 20;
+
+/// Dispatch a parsed command line, as `main` does after parsing `std::env::args`.
+pub fn run(opts: Opts) -> Result<()> {
+    // only the commands whose loops poll for it; elsewhere Ctrl-C keeps its default
+    if matches!(
+        opts.cmd,
+        SubCommand::Csv(_) | SubCommand::Hash(HashOpts { cmd: Some(_), .. })
+    ) {
+        install_interrupt_handler()?;
+    }
+    let audit = opts
+        .audit_log
+        .clone()
+        .map(|path| (path, audit_entry(&opts.cmd)));
+    let ret = dispatch(opts);
+    if let Some((path, mut entry)) = audit {
+        entry["success"] = ret.is_ok().into();
+        // the command's own outcome decides the exit code, not the bookkeeping
+        if let Err(e) = append_audit(&path, &entry) {
+            eprintln!(
+                "warning: failed to write audit log {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    ret
+}
+
+fn dispatch(mut opts: Opts) -> Result<()> {
+    let end = if opts.no_newline { "" } else { "\n" };
+    let quiet = opts.quiet;
+    if let SubCommand::Csv(csv) = &mut opts.cmd {
+        csv.quiet = quiet;
+    }
+    let keep_going = opts.keep_going;
+    set_stdin_timeout(opts.stdin_timeout.map(Duration::from_secs));
+    set_buffer_size(opts.buffer_size);
+    set_tee(opts.tee.as_deref())?;
+    match opts.cmd {
+        SubCommand::Csv(opts) => match (&opts.value_counts, opts.split_rows) {
+            (Some(column), _) => {
+                let input = File::open(&opts.input)?;
+                let counts = process_csv_value_counts(input, column, opts.delimiter)?;
+                writeln!(stdout(), "{}", format_value_counts(&counts)?)?;
+            }
+            (None, _) if opts.detect => {
+                let input = File::open(&opts.input)?;
+                for score in detect_delimiter_report(input, DETECT_LINES)? {
+                    eprintln!("{}", score);
+                }
+            }
+            (None, Some(rows)) => {
+                let output = opts
+                    .output
+                    .as_deref()
+                    .ok_or_else(|| AppError::Input("--split-rows requires --output".into()))?;
+                let (parts, stats) = process_csv_split(&opts, output, rows)?;
+                if !quiet {
+                    eprintln!("Wrote {} part(s)", parts);
+                }
+                if opts.stats {
+                    eprintln!("{}", stats);
+                }
+            }
+            (None, None) if opts.recursive => {
+                let output_dir = opts
+                    .output_dir
+                    .as_deref()
+                    .ok_or_else(|| AppError::Input("--recursive requires --output-dir".into()))?;
+                let mut errors = BatchErrors::new(keep_going);
+                let count = process_csv_dir(&opts, output_dir, &mut errors)?;
+                if !quiet {
+                    eprintln!("Converted {} file(s)", count);
+                }
+                errors.finish()?;
+            }
+            (None, None) => {
+                let stats = match (&opts.output, opts.output_format()) {
+                    (Some(output), _) => process_csv(&opts, output.clone())?,
+                    (None, format) if format.is_binary() || opts.gzip => {
+                        if io::stdout().is_terminal() {
+                            let kind = if opts.gzip {
+                                "gzip".into()
+                            } else {
+                                format.to_string()
+                            };
+                            anyhow::bail!(
+                                "{} output is binary; pass --output <file> or redirect stdout",
+                                kind
+                            );
+                        }
+                        let input = File::open(&opts.input)?;
+                        let (bytes, stats) = process_csv_with_stats(input, format, &opts)?;
+                        stdout().write_all(&bytes)?;
+                        stats
+                    }
+                    (None, format) => process_csv(&opts, format!("output.{}", format))?,
+                };
+                if opts.stats {
+                    eprintln!("{}", stats);
+                }
+            }
+        },
+        SubCommand::GenPass(GenPassOpts {
+            cmd: Some(GenPassSubCommand::Audit(opts)),
+            ..
+        }) => {
+            let audit = process_genpass_audit(
+                opts.samples,
+                opts.length,
+                opts.has_uppercase,
+                opts.has_lowercase,
+                opts.has_number,
+                opts.has_symbol,
+            )?;
+            let total = audit.counts.values().sum::<u64>() as f64;
+            eprintln!("{:<4} {:>10} {:>8}", "char", "count", "freq");
+            for (c, count) in &audit.counts {
+                let freq = *count as f64 / total * 100.0;
+                eprintln!("{:<4} {:>10} {:>7.3}%", c, count, freq);
+            }
+            eprintln!(
+                "chi-squared: {:.2} ({} degrees of freedom)",
+                audit.chi_squared,
+                audit.alphabet_len - 1
+            );
+        }
+        SubCommand::GenPass(GenPassOpts {
+            cmd: Some(GenPassSubCommand::Compare(opts)),
+            ..
+        }) => {
+            let scores = process_genpass_compare(GENPASS_POLICIES, opts.samples)?;
+            eprintln!("{:<16} {:>6} {:>8}", "policy", "score", "bits");
+            for score in &scores {
+                eprintln!(
+                    "{:<16} {:>6} {:>8.1}",
+                    score.name, score.median_score, score.median_bits
+                );
+            }
+        }
+        SubCommand::GenPass(opts) => {
+            let password = process_genpass(
+                opts.length,
+                opts.has_uppercase,
+                opts.has_lowercase,
+                opts.has_number,
+                opts.has_symbol,
+            )?;
+            let estimate = zxcvbn(&password, &[])?;
+            let score = estimate.score();
+            let bits = opts.show_bits.then(|| entropy_bits(&estimate));
+            let formatted = format_genpass(&password, score, bits, opts.output_format, &opts.name)?;
+            match &opts.output {
+                Some(output) => write_private(output, formatted.as_bytes())?,
+                None if opts.clipboard => {
+                    copy_to_clipboard(&password)?;
+                    if !quiet {
+                        eprintln!("copied to clipboard");
+                    }
+                }
+                None => write!(stdout(), "{}{}", formatted, end)?,
+            }
+
+            if !quiet && opts.output_format != GenPassFormat::Json {
+                eprintln!("Password strength: {}", score);
+                if let Some(bits) = bits {
+                    eprintln!("entropy: {:.1} bits", bits);
+                }
+            }
+        }
+        SubCommand::Base64(subcmd) => match subcmd {
+            Base64SubCommand::Encode(opts) if opts.alphabet.is_some() => {
+                if opts.text.is_none() && is_glob(&opts.input) {
+                    anyhow::bail!("--alphabet can't be combined with a glob --input");
+                }
+                ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+                let alphabet = opts.alphabet.as_deref().expect("guarded by is_some");
+                let alphabet = CustomAlphabet::parse(alphabet)?;
+                let reader = get_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                let mut reader = limit_reader(reader, opts.limit);
+                let encode = process_encode_custom(&mut reader, &alphabet)?;
+                match &opts.output {
+                    Some(output) => write_atomic(output, encode.as_bytes())?,
+                    None => write!(stdout(), "encode:{}{}", encode, end)?,
+                }
+            }
+            Base64SubCommand::Encode(opts)
+                if opts.output_template.is_some()
+                    || (opts.text.is_none() && is_glob(&opts.input)) =>
+            {
+                if opts.output.is_some() {
+                    anyhow::bail!("--output can't be combined with a glob --input");
+                }
+                if opts.with_checksum {
+                    anyhow::bail!("--with-checksum can't be combined with a glob --input");
+                }
+                if opts.input == "-" {
+                    anyhow::bail!("--output-template needs file inputs, not stdin");
+                }
+                let mut errors = BatchErrors::new(keep_going);
+                for input in expand_input(&opts.input)? {
+                    let encode = errors.run(&input, || {
+                        let mut reader = limit_reader(get_reader(&input)?, opts.limit);
+                        Ok(process_encode(&mut reader, opts.format)?)
+                    })?;
+                    match (encode, &opts.output_template) {
+                        (Some(encode), Some(template)) => {
+                            let output = expand_output_template(template, Path::new(&input))?;
+                            if let Some(parent) = output.parent() {
+                                std::fs::create_dir_all(parent)?;
+                            }
+                            write_atomic(output, encode.as_bytes())?;
+                        }
+                        (Some(encode), None) => writeln!(stdout(), "{}: encode:{}", input, encode)?,
+                        (None, _) => {}
+                    }
+                }
+                errors.finish()?;
+            }
+            Base64SubCommand::Encode(opts) => {
+                ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+                let reader = get_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                let mut reader = limit_reader(reader, opts.limit);
+                match opts.output.as_deref() {
+                    Some(output) => {
+                        let mut encode = |writer: &mut dyn Write| -> Result<()> {
+                            if opts.with_checksum {
+                                let mut hashed = Crc32Writer::new(&mut *writer);
+                                process_encode_stream(&mut reader, &mut hashed, opts.format)?;
+                                let checksum = hashed.finish();
+                                writeln!(writer, "\n{}", checksum)?;
+                            } else {
+                                process_encode_stream(&mut reader, writer, opts.format)?;
+                            }
+                            Ok(())
+                        };
+                        match output {
+                            "-" => encode(&mut stdout())?,
+                            output => write_atomic_with(output, encode)?,
+                        }
+                    }
+                    None if opts.with_checksum => {
+                        let encode = process_encode(&mut reader, opts.format)?;
+                        let checksum = checksum_line(encode.as_bytes());
+                        write!(stdout(), "encode:{}\n{}{}", encode, checksum, end)?;
+                    }
+                    None => {
+                        let encode = process_encode(&mut reader, opts.format)?;
+                        write!(stdout(), "encode:{}{}", encode, end)?;
+                    }
+                }
+            }
+            Base64SubCommand::Decode(opts) if opts.alphabet.is_some() => {
+                ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+                let alphabet = opts.alphabet.as_deref().expect("guarded by is_some");
+                let alphabet = CustomAlphabet::parse(alphabet)?;
+                let mut reader = get_text_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                let decoded = process_decode_custom(&mut reader, &alphabet)?;
+                match &opts.output {
+                    Some(output) => write_atomic(output, &decoded)?,
+                    None if opts.lossy => {
+                        writeln!(stdout(), "decoded:{}", String::from_utf8_lossy(&decoded))?
+                    }
+                    None => writeln!(stdout(), "decoded:{}", String::from_utf8(decoded)?)?,
+                }
+            }
+            Base64SubCommand::Decode(opts) if opts.check => {
+                let mut reader = base64_decode_reader(&opts, quiet)?;
+                if process_decode_check(&mut reader, opts.format)? {
+                    if !quiet {
+                        eprintln!("valid");
+                    }
+                } else {
+                    if !quiet {
+                        eprintln!("invalid");
+                    }
+                    return Err(AppError::Verification("input is not valid base64".into()).into());
+                }
+            }
+            Base64SubCommand::Decode(opts)
+                if opts.skip.is_some() || opts.take.is_some() || opts.hexdump =>
+            {
+                let mut reader = base64_decode_reader(&opts, quiet)?;
+                let skip = opts.skip.unwrap_or_default();
+                let window = process_decode_window(&mut reader, opts.format, skip, opts.take)?;
+                if opts.hexdump {
+                    write!(stdout(), "{}", hexdump(&window))?;
+                } else {
+                    stdout().write_all(&window)?;
+                }
+            }
+            Base64SubCommand::Decode(opts) if opts.as_format != DecodeAs::Utf8 => {
+                let mut reader = base64_decode_reader(&opts, quiet)?;
+                let decoded = process_decode_window(&mut reader, opts.format, 0, opts.head)?;
+                writeln!(stdout(), "{}", format_decoded(&decoded, opts.as_format))?;
+            }
+            Base64SubCommand::Decode(opts)
+                if opts.head.is_none()
+                    && !opts.lossy
+                    && (opts.output.is_some() || !io::stdout().is_terminal()) =>
+            {
+                let mut reader = base64_decode_reader(&opts, quiet)?;
+                match opts.output.as_deref() {
+                    Some(output) => write_atomic_with(output, |writer| {
+                        Ok(process_decode_to_writer(&mut reader, writer, opts.format)?)
+                    })?,
+                    None => process_decode_to_writer(&mut reader, &mut stdout(), opts.format)?,
+                }
+            }
+            Base64SubCommand::Decode(opts) => {
+                let mut reader = base64_decode_reader(&opts, quiet)?;
+                let decoded = process_decode(&mut reader, opts.format, opts.head, opts.lossy)?;
+                writeln!(stdout(), "decoded:{}", decoded)?;
+            }
+            Base64SubCommand::Split(opts) => {
+                let mut reader = get_text_reader(&opts.input)?;
+                for chunk in process_split(&mut reader, opts.format, opts.chunk_bytes as usize)? {
+                    writeln!(stdout(), "{}", chunk)?;
+                }
+            }
+            Base64SubCommand::Join(opts) => {
+                let mut reader = get_text_reader(&opts.input)?;
+                let joined = process_join(&mut reader, opts.format)?;
+                match &opts.output {
+                    Some(output) => write_atomic(output, &joined)?,
+                    None => stdout().write_all(&joined)?,
+                }
+            }
+            Base64SubCommand::Transcode(opts) => {
+                let mut reader = get_text_reader(&opts.input)?;
+                let transcoded = process_transcode(&mut reader, opts.from, opts.to)?;
+                writeln!(stdout(), "transcode:{}", transcoded)?;
+            }
+        },
+        SubCommand::Text(text) if text.peek => {
+            let (input, literal) = text
+                .cmd
+                .input()
+                .ok_or_else(|| anyhow::anyhow!("--peek: this command reads no input"))?;
+            let (len, fingerprint) = process_peek(&mut get_reader_for(Some(input), literal)?)?;
+            eprintln!("{} bytes, blake3:{}", len, fingerprint);
+        }
+        SubCommand::Text(TextOpts { cmd: subcmd, .. }) => match subcmd {
+            TextSubCommand::Sign(opts) => {
+                ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+                let mut reader = get_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                let key = resolve_key_args(&opts.key, quiet)?;
+                let options = SignOptions {
+                    format: opts.format,
+                    key_format: opts.key_format,
+                    strict_key: opts.strict_key,
+                    mac_len: opts.mac_len.into(),
+                    prehash: opts.prehash,
+                    derive_context: opts.derive_context,
+                    context: opts.context,
+                    normalize_newlines: opts.normalize_newlines,
+                };
+                let sig = process_text_sign(&mut reader, &key, &options)?;
+                let encoded = if opts.combined {
+                    format_combined_sig(opts.format, &sig)
+                } else {
+                    encode_sig(&sig, opts.sig_format)
+                };
+                if opts.with_checksum {
+                    let checksum = checksum_line(encoded.as_bytes());
+                    write!(stdout(), "sig:{}\n{}{}", encoded, checksum, end)?;
+                } else {
+                    write!(stdout(), "sig:{}{}", encoded, end)?;
+                }
+            }
+            TextSubCommand::Generate(opts) => {
+                prepare_output_dir(&opts.output_path, opts.create_dirs)?;
+                let map = process_text_keys_generate(
+                    opts.format,
+                    opts.count,
+                    opts.encoding(),
+                    opts.seed,
+                )?;
+                let key_path = |name: &str| match &opts.output_template {
+                    Some(template) => expand_output_template(template, Path::new(name))
+                        .map(|path| opts.output_path.join(path)),
+                    None => Ok(opts.output_path.join(name)),
+                };
+                let mut names = map.keys().collect::<Vec<_>>();
+                names.sort();
+                let mut paths = Vec::with_capacity(names.len());
+                for name in names {
+                    let path = key_path(name)?;
+                    if let Some((other, _)) = paths.iter().find(|(_, p)| *p == path) {
+                        anyhow::bail!(
+                            "--output-template maps both {} and {} to {}",
+                            other,
+                            name,
+                            path.display()
+                        );
+                    }
+                    // a template could land on an unrelated file; the default names overwrite
+                    if opts.output_template.is_some() && path.exists() {
+                        anyhow::bail!("{} already exists", path.display());
+                    }
+                    paths.push((name, path));
+                }
+                for (name, path) in paths {
+                    // only public keys stay readable by others
+                    if name.ends_with(".pk") {
+                        write_atomic(path, &map[name])?;
+                    } else {
+                        write_private(path, &map[name])?;
+                    }
+                }
+                if opts.print_fingerprint {
+                    let mut names = map
+                        .keys()
+                        .filter(|k| !k.contains(".sk") && !k.ends_with(".nonce"))
+                        .collect::<Vec<_>>();
+                    names.sort();
+                    for name in names {
+                        let fp = fingerprint_key_file(&map[name], opts.format, opts.encoding())?;
+                        eprintln!("fingerprint:{}", fp);
+                    }
+                }
+            }
+            TextSubCommand::Verify(opts) => {
+                ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+                let keys = resolve_key_args_all(&opts.key, quiet)?;
+                let (format, decoded) = match &opts.combined {
+                    Some(combined) => parse_combined_sig(combined)?,
+                    None => {
+                        let sig = match (&opts.sig, &opts.sig_file) {
+                            (Some(sig), _) => sig.clone(),
+                            (None, Some(file)) => {
+                                if file == "-" && opts.input == "-" && opts.text.is_none() {
+                                    return Err(AppError::Input(
+                                        "--input and --sig-file can't both read stdin".into(),
+                                    )
+                                    .into());
+                                }
+                                read_sig(&mut get_reader(file)?)?
+                            }
+                            (None, None) => anyhow::bail!("--sig or --sig-file is required"),
+                        };
+                        let sig = strip_checksum(&sig, "sig:")?;
+                        (opts.format, decode_sig(sig, opts.sig_encoding)?)
+                    }
+                };
+                let options = SignOptions {
+                    format,
+                    key_format: opts.key_format,
+                    strict_key: opts.strict_key,
+                    mac_len: opts.mac_len.into(),
+                    prehash: opts.prehash,
+                    derive_context: opts.derive_context,
+                    context: opts.context,
+                    normalize_newlines: opts.normalize_newlines,
+                };
+                let matched = if let [(_, key)] = keys.as_slice() {
+                    let mut reader = get_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                    process_text_verify(&mut reader, key, &decoded, &options)?.then_some(0)
+                } else {
+                    // several candidate keys need the input more than once
+                    let mut content = Vec::new();
+                    get_reader_for(Some(&opts.input), opts.text.as_deref())?
+                        .read_to_end(&mut content)?;
+                    let mut matched = None;
+                    for (i, (_, key)) in keys.iter().enumerate() {
+                        if process_text_verify(&mut content.as_slice(), key, &decoded, &options)? {
+                            matched = Some(i);
+                            break;
+                        }
+                    }
+                    matched
+                };
+                if let Some(i) = matched {
+                    if keys.len() > 1 && !quiet {
+                        eprintln!("matched key {}", keys[i].0);
+                    }
+                    writeln!(stdout(), "verified")?;
+                } else {
+                    writeln!(stdout(), "not verified")?;
+                    return Err(AppError::Verification("signature did not verify".into()).into());
+                }
+            }
+            TextSubCommand::VerifyDir(opts) => {
+                let key = resolve_key_args(&opts.key, quiet)?;
+                let options = SignOptions {
+                    key_format: opts.key_format,
+                    ..SignOptions::new(opts.format)
+                };
+                let results =
+                    process_text_verify_dir(&opts.dir, &key, opts.sig_encoding, &options)?;
+                let (mut verified, mut failed, mut skipped) = (0, 0, 0);
+                for (path, status) in results {
+                    match status {
+                        SigStatus::Verified => {
+                            verified += 1;
+                            writeln!(stdout(), "{}: OK", path)?;
+                        }
+                        SigStatus::Failed => {
+                            failed += 1;
+                            writeln!(stdout(), "{}: FAILED", path)?;
+                        }
+                        SigStatus::Missing => {
+                            skipped += 1;
+                            if !quiet {
+                                eprintln!("warning: {} has no .sig sidecar, skipped", path);
+                            }
+                        }
+                    }
+                }
+                if !quiet {
+                    eprintln!(
+                        "{} verified, {} failed, {} skipped",
+                        verified, failed, skipped
+                    );
+                }
+                if failed > 0 {
+                    let msg = format!("{} file(s) failed verification", failed);
+                    return Err(AppError::Verification(msg).into());
+                }
+            }
+            TextSubCommand::SignLines(opts) => {
+                let mut reader = get_text_reader(&opts.input)?;
+                let key = resolve_key_args(&opts.key, quiet)?;
+                process_sign_lines(&mut reader, &key, &mut stdout())?;
+            }
+            TextSubCommand::SignFrames(opts) => {
+                let mut reader = get_reader(&opts.input)?;
+                let key = resolve_key_args(&opts.key, quiet)?;
+                let options = SignOptions {
+                    key_format: opts.key_format,
+                    ..SignOptions::new(opts.format)
+                };
+                process_sign_frames(&mut reader, &key, &options, &mut stdout())?;
+            }
+            TextSubCommand::VerifyFrames(opts) => {
+                let mut reader = get_reader(&opts.input)?;
+                let mut sigs = get_reader(&opts.sigs)?;
+                let key = resolve_key_args(&opts.key, quiet)?;
+                let options = SignOptions {
+                    key_format: opts.key_format,
+                    ..SignOptions::new(opts.format)
+                };
+                let failed = process_verify_frames(&mut reader, &mut sigs, &key, &options)?;
+                for index in &failed {
+                    writeln!(stdout(), "frame {}: not verified", index)?;
+                }
+                if !failed.is_empty() {
+                    let msg = format!("{} frame(s) failed verification", failed.len());
+                    return Err(AppError::Verification(msg).into());
+                }
+                writeln!(stdout(), "verified")?;
+            }
+            TextSubCommand::VerifyLines(opts) => {
+                let mut reader = get_text_reader(&opts.input)?;
+                let key = resolve_key_args(&opts.key, quiet)?;
+                let failed = process_verify_lines(&mut reader, &key)?;
+                for line in &failed {
+                    writeln!(stdout(), "line {}: not verified", line)?;
+                }
+                if !failed.is_empty() {
+                    let msg = format!("{} line(s) failed verification", failed.len());
+                    return Err(AppError::Verification(msg).into());
+                }
+                writeln!(stdout(), "verified")?;
+            }
+            TextSubCommand::GenerateNonce(opts) => {
+                prepare_output_dir(&opts.output_path, opts.create_dirs)?;
+                let nonce = process_text_nonce_generate()?;
+                for (k, v) in nonce {
+                    write_atomic(opts.output_path.join(k), &v)?;
+                }
+            }
+            TextSubCommand::Encrypt(opts) => {
+                ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+                let mut reader = get_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                let encrypt = match &opts.recipient {
+                    Some(recipient) => {
+                        let recipient = resolve_key(Some(recipient), get_content)?;
+                        process_text_encrypt_recipient(
+                            &mut reader,
+                            &recipient,
+                            opts.aad.as_bytes(),
+                        )?
+                    }
+                    None => {
+                        let key = resolve_chacha_key_args(&opts.key, quiet)?;
+                        let nonce = nonce_source(&opts.nonce, opts.base_nonce, opts.message_index);
+                        process_text_encrypt(&mut reader, &key, &nonce, opts.aad.as_bytes())?
+                    }
+                };
+                let qr = if opts.qr {
+                    Some(render_qr(&URL_SAFE_NO_PAD.encode(&encrypt))?)
+                } else {
+                    None
+                };
+                match &opts.output {
+                    Some(output) if opts.raw => write_atomic(output, &encrypt)?,
+                    Some(output) => {
+                        write_atomic(output, URL_SAFE_NO_PAD.encode(&encrypt).as_bytes())?
+                    }
+                    None if qr.is_some() => {}
+                    None => {
+                        let encrypt = URL_SAFE_NO_PAD.encode(&encrypt);
+                        write!(stdout(), "encrypt:{}{}", encrypt, end)?;
+                    }
+                }
+                if let Some(qr) = qr {
+                    if let Some(qr_file) = &opts.qr_file {
+                        write_qr_png(&URL_SAFE_NO_PAD.encode(&encrypt), qr_file)?;
+                    }
+                    writeln!(stdout(), "{}", qr)?;
+                }
+            }
+            TextSubCommand::Seal(opts) => {
+                let mut reader = get_reader_for(Some(&opts.input), opts.text.as_deref())?;
+                let sign_key = resolve_key(Some(&opts.key_sign), get_content)?;
+                let enc_key = resolve_key(Some(&opts.key_enc), get_chacha_key)?;
+                let nonce = NonceSource::File(opts.nonce);
+                let sealed = process_text_seal(
+                    &mut reader,
+                    &sign_key,
+                    &enc_key,
+                    &nonce,
+                    opts.aad.as_bytes(),
+                )?;
+                write!(stdout(), "sealed:{}{}", URL_SAFE_NO_PAD.encode(sealed), end)?;
+            }
+            TextSubCommand::Open(opts) => {
+                let mut reader = get_reader(&opts.input)?;
+                let verify_key = resolve_key(Some(&opts.key_verify), get_content)?;
+                let enc_key = resolve_key(Some(&opts.key_enc), get_chacha_key)?;
+                let mut encoded = String::new();
+                reader.read_to_string(&mut encoded)?;
+                let encoded = encoded.trim();
+                let sealed =
+                    URL_SAFE_NO_PAD.decode(encoded.strip_prefix("sealed:").unwrap_or(encoded))?;
+                let nonce = NonceSource::File(opts.nonce);
+                let opened =
+                    process_text_open(&sealed, &verify_key, &enc_key, &nonce, opts.aad.as_bytes())?;
+                write!(stdout(), "opened:")?;
+                stdout().write_all(&opened)?;
+                writeln!(stdout())?;
+            }
+            TextSubCommand::Rekey(opts) => {
+                let mut reader = get_reader(&opts.input)?;
+                let old_key = resolve_key(Some(&opts.old_key), get_chacha_key)?;
+                let new_key = resolve_key(Some(&opts.new_key), get_chacha_key)?;
+                let mut encoded = String::new();
+                reader.read_to_string(&mut encoded)?;
+                let mut ciphertext = URL_SAFE_NO_PAD.decode(encoded.trim())?;
+                // a fresh nonce unless one is given, saved before use so the new ciphertext is
+                // never printed without the nonce that decrypts it
+                let generated = opts.new_nonce.is_none() && !opts.reuse_nonce;
+                let new_nonce = match (opts.new_nonce, generated) {
+                    (Some(nonce), _) => nonce,
+                    (None, false) => opts.nonce.clone(),
+                    (None, true) => {
+                        let path = match opts.new_nonce_out {
+                            Some(path) => path,
+                            None if opts.input == "-" => {
+                                anyhow::bail!("pass --new-nonce-out to save the generated nonce")
+                            }
+                            None => format!("{}.nonce", opts.input).into(),
+                        };
+                        if path.exists() {
+                            anyhow::bail!("{} already exists", path.display());
+                        }
+                        write_atomic(&path, &process_text_nonce_generate()?["chacha2.nonce"])?;
+                        if !quiet {
+                            eprintln!("new nonce written to {}", path.display());
+                        }
+                        path.display().to_string()
+                    }
+                };
+                let rekeyed = process_text_rekey(
+                    &mut ciphertext,
+                    &old_key,
+                    &NonceSource::File(opts.nonce),
+                    &new_key,
+                    &NonceSource::File(new_nonce.clone()),
+                    opts.aad.as_bytes(),
+                );
+                if rekeyed.is_err() && generated {
+                    let _ = std::fs::remove_file(&new_nonce);
+                }
+                let rekeyed = rekeyed?;
+                write!(stdout(), "rekey:{}{}", URL_SAFE_NO_PAD.encode(rekeyed), end)?;
+            }
+            TextSubCommand::KeyInfo(opts) => {
+                let content = resolve_key(Some(&opts.key), get_content)?;
+                let info = process_key_info(&content, opts.format, opts.key_format, opts.secret)?;
+                writeln!(stdout(), "type: {}", info.format)?;
+                writeln!(stdout(), "length: {} bytes", info.len)?;
+                if let Some(valid) = info.valid_point {
+                    let valid = if valid { "yes" } else { "no" };
+                    writeln!(stdout(), "valid point: {}", valid)?;
+                }
+                writeln!(stdout(), "fingerprint: {}", info.fingerprint)?;
+            }
+            TextSubCommand::KeyConvert(opts) => {
+                ensure_stdin_piped(&opts.input, None)?;
+                let content = get_content(&opts.input)?;
+                let key = process_key_convert(&content, opts.in_encoding, opts.out_encoding)?;
+                match &opts.output {
+                    Some(output) => write_private(output, &key)?,
+                    None => stdout().write_all(&key)?,
+                }
+            }
+            TextSubCommand::Decrypt(opts) if opts.identity.is_some() => {
+                ensure_stdin_piped(&opts.input, None)?;
+                let mut reader = get_reader(&opts.input)?;
+                let identity = resolve_key(opts.identity.as_deref(), get_content)?;
+                let mut ciphertext = Vec::new();
+                reader.read_to_end(&mut ciphertext)?;
+                if !opts.raw {
+                    ciphertext = URL_SAFE_NO_PAD.decode(String::from_utf8(ciphertext)?.trim())?;
+                }
+                let decrypt =
+                    process_text_decrypt_identity(&ciphertext, &identity, opts.aad.as_bytes())?;
+                write_decrypted(&decrypt, opts.lossy)?;
+            }
+            TextSubCommand::Decrypt(opts) => {
+                ensure_stdin_piped(&opts.input, None)?;
+                let mut reader = get_reader(&opts.input)?;
+                let key = resolve_chacha_key_args(&opts.key, quiet)?;
+                let nonce = nonce_source(&opts.nonce, opts.base_nonce, opts.message_index);
+                let mut decrypt = Vec::new();
+                if opts.raw {
+                    let mut ciphertext = Vec::new();
+                    reader.read_to_end(&mut ciphertext)?;
+                    decrypt =
+                        process_text_decrypt(&mut ciphertext, &key, &nonce, opts.aad.as_bytes())?;
+                } else {
+                    let aad = opts.aad.as_bytes();
+                    process_text_decrypt_stream(&mut reader, &key, &nonce, aad, &mut decrypt)?;
+                }
+                write_decrypted(&decrypt, opts.lossy)?;
+            }
+        },
+        SubCommand::Identify(opts) => {
+            let mut reader = get_reader(&opts.input)?;
+            let guesses = process_identify(&mut reader)?;
+            eprintln!("{:<16} {:>8} {:>6}", "encoding", "bytes", "score");
+            for guess in guesses {
+                eprintln!(
+                    "{:<16} {:>8} {:>6}",
+                    guess.encoding, guess.decoded_len, guess.score
+                );
+            }
+        }
+        SubCommand::Hash(opts) => match opts.cmd {
+            Some(HashSubCommand::Manifest(opts)) => match opts.check {
+                Some(check) => {
+                    let manifest = String::from_utf8(get_content(&check)?)?;
+                    let results = process_hash_check(&opts.dir, &manifest)?;
+                    let mut failed = 0;
+                    for (path, status) in results {
+                        let status = match status {
+                            ManifestStatus::Ok => "OK",
+                            ManifestStatus::Mismatch => "FAILED",
+                            ManifestStatus::Missing => "MISSING",
+                        };
+                        if status != "OK" {
+                            failed += 1;
+                        }
+                        writeln!(stdout(), "{}: {}", path, status)?;
+                    }
+                    if failed > 0 {
+                        let msg = format!("{} file(s) failed verification", failed);
+                        return Err(AppError::Verification(msg).into());
+                    }
+                }
+                None => write!(stdout(), "{}", process_hash_manifest(&opts.dir)?)?,
+            },
+            None => {
+                let glob = is_glob(&opts.input);
+                let mut errors = BatchErrors::new(keep_going);
+                for input in expand_input(&opts.input)? {
+                    let start = Instant::now();
+                    let digest = errors.run(&input, || {
+                        let mut reader = get_reader(&input)?;
+                        Ok(process_hash_repeat(&mut reader, opts.algo, opts.repeat)?)
+                    })?;
+                    let Some(digest) = digest else { continue };
+                    if glob {
+                        writeln!(stdout(), "{}: {}", input, hex::encode(digest))?;
+                    } else {
+                        writeln!(stdout(), "{}", hex::encode(digest))?;
+                    }
+                    if opts.repeat > 1 && !quiet {
+                        eprintln!("{} rounds in {:.3?}", opts.repeat, start.elapsed());
+                    }
+                }
+                errors.finish()?;
+            }
+        },
+        SubCommand::Compare(opts) => {
+            let a = get_content(&opts.a)?;
+            let b = get_content(&opts.b)?;
+            if !process_compare(&a, &b) {
+                return Err(AppError::Verification("inputs differ".into()).into());
+            }
+        }
+        SubCommand::Key(subcmd) => match subcmd {
+            KeySubCommand::Store(opts) => {
+                let key = resolve_key(Some(&opts.key), |key| Ok(key.as_bytes().to_vec()))?;
+                store_key_keyring(&opts.service, &opts.account, &key)?;
+            }
+            KeySubCommand::Delete(opts) => delete_key_keyring(&opts.service, &opts.account)?,
+        },
+        SubCommand::Count(opts) => {
+            let mut reader = get_reader(&opts.input)?;
+            let (lines, words, bytes) = process_count(&mut reader)?;
+            let all = !(opts.lines || opts.words || opts.bytes);
+            let counts = [
+                (opts.lines, lines),
+                (opts.words, words),
+                (opts.bytes, bytes),
+            ]
+            .into_iter()
+            .filter(|(selected, _)| all || *selected)
+            .map(|(_, count)| count.to_string())
+            .collect::<Vec<_>>();
+            writeln!(stdout(), "{} {}", counts.join(" "), opts.input)?;
+        }
+        SubCommand::Bench(opts) => {
+            let results = process_bench(opts.size)?;
+            eprintln!("{:<10} {:>10}", "algorithm", "MB/s");
+            for (name, elapsed) in results {
+                let mbps = opts.size as f64 / 1_000_000.0 / elapsed.as_secs_f64();
+                eprintln!("{:<10} {:>10.1}", name, mbps);
+            }
+        }
+        SubCommand::Pipe(opts) => {
+            let mut input = Vec::new();
+            get_reader(&opts.from)?.read_to_end(&mut input)?;
+            let (output, sizes) = process_pipe(input, &opts.steps)?;
+            if opts.verbose {
+                for (step, size) in opts.steps.iter().zip(sizes) {
+                    eprintln!("{:<10} {:>10} bytes", step, size);
+                }
+            }
+            match &opts.to {
+                Some(to) => write_atomic(to, &output)?,
+                None => stdout().write_all(&output)?,
+            }
+        }
+        SubCommand::Repl => repl()?,
+        SubCommand::TestVectors(opts) => {
+            let vectors = process_test_vectors(opts.format)?;
+            writeln!(stdout(), "{}", serde_json::to_string_pretty(&vectors)?)?;
+        }
+        SubCommand::Version(opts) => {
+            let info = process_version();
+            if opts.json {
+                writeln!(stdout(), "{}", serde_json::to_string(&info)?)?;
+            } else {
+                writeln!(
+                    stdout(),
+                    "rcli {} ({}, rustc {}, {})",
+                    info.version,
+                    info.git,
+                    info.rustc,
+                    info.target
+                )?;
+            }
+        }
+    }
+    stdout().flush()?;
+    Ok(())
+}
+
+/// What the audit log records about a command: its name, format and input path, but never
+/// key material, `--text` literals or contents.
+fn audit_entry(cmd: &SubCommand) -> serde_json::Value {
+    let (format, input) = match cmd {
+        SubCommand::Csv(opts) => (
+            Some(opts.output_format().to_string()),
+            Some(opts.input.clone()),
+        ),
+        SubCommand::Base64(Base64SubCommand::Encode(opts)) => (
+            Some(opts.format.to_string()),
+            Some(literal_or(&opts.input, opts.text.as_deref())),
+        ),
+        SubCommand::Base64(Base64SubCommand::Decode(opts)) => (
+            Some(opts.format.to_string()),
+            Some(literal_or(&opts.input, opts.text.as_deref())),
+        ),
+        SubCommand::Text(text) => {
+            let format = match &text.cmd {
+                TextSubCommand::Sign(opts) => Some(opts.format.to_string()),
+                TextSubCommand::Verify(opts) => Some(opts.format.to_string()),
+                TextSubCommand::VerifyDir(opts) => Some(opts.format.to_string()),
+                TextSubCommand::Generate(opts) => Some(opts.format.to_string()),
+                _ => None,
+            };
+            let input = match &text.cmd {
+                TextSubCommand::VerifyDir(opts) => Some(opts.dir.display().to_string()),
+                cmd => cmd.input().map(|(input, text)| literal_or(input, text)),
+            };
+            (format, input)
+        }
+        SubCommand::Hash(HashOpts {
+            cmd: Some(HashSubCommand::Manifest(opts)),
+            ..
+        }) => (None, Some(opts.dir.display().to_string())),
+        SubCommand::Hash(opts) => (Some(opts.algo.to_string()), Some(opts.input.clone())),
+        SubCommand::Identify(opts) => (None, Some(opts.input.clone())),
+        SubCommand::Count(opts) => (None, Some(opts.input.clone())),
+        SubCommand::Pipe(opts) => (None, Some(opts.from.clone())),
+        _ => (None, None),
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    serde_json::json!({
+        "timestamp": timestamp,
+        "command": cmd.name(),
+        "format": format,
+        "input": input,
+    })
+}
+
+fn literal_or(input: &str, text: Option<&str>) -> String {
+    match text {
+        Some(_) => "--text".into(),
+        None => input.into(),
+    }
+}
+
+fn append_audit(path: &Path, entry: &serde_json::Value) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", entry)?;
+    Ok(())
+}
+
+fn write_decrypted(decrypt: &[u8], lossy: bool) -> Result<()> {
+    write!(stdout(), "decrypt:")?;
+    if lossy {
+        write!(stdout(), "{}", String::from_utf8_lossy(decrypt))?;
+    } else {
+        stdout().write_all(decrypt)?;
+    }
+    writeln!(stdout())?;
+    Ok(())
+}
+
+/// The input of `base64 decode`; with `--format auto` it is read up front so the detected
+/// variant can be reported before decoding.
+fn base64_decode_reader(opts: &Base64DecodeOpts, quiet: bool) -> Result<Box<dyn BufRead>> {
+    ensure_stdin_piped(&opts.input, opts.text.as_deref())?;
+    let mut reader = get_text_reader_for(Some(&opts.input), opts.text.as_deref())?;
+    if opts.with_checksum {
+        let mut content = String::new();
+        reader.read_to_string(&mut content)?;
+        reader = Box::new(io::Cursor::new(
+            strip_checksum(&content, "encode:")?.to_string(),
+        ));
+    }
+    if opts.format != Base64Format::Auto {
+        return Ok(reader);
+    }
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    if let (Some(format), false) = (detect_format(&content), quiet) {
+        eprintln!("detected format: {}", format);
+    }
+    Ok(Box::new(io::Cursor::new(content)))
+}
+
+fn nonce_source(nonce: &str, base: Option<[u8; 8]>, index: Option<u32>) -> NonceSource {
+    match (base, index) {
+        (Some(base), Some(index)) => NonceSource::Counter { base, index },
+        _ => NonceSource::File(nonce.to_string()),
+    }
+}
+
+fn repl() -> Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    loop {
+        let line = match editor.readline("rcli> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line == "quit" {
+            break;
+        }
+        editor.add_history_entry(line)?;
+        let Some(args) = shlex::split(line) else {
+            eprintln!("Error: unbalanced quotes");
+            continue;
+        };
+        match Opts::try_parse_from(std::iter::once("rcli".to_string()).chain(args)) {
+            Ok(opts) => {
+                if let Err(err) = run(opts) {
+                    eprintln!("Error: {}", AppError::from(err));
+                }
+            }
+            Err(e) => e.print()?,
+        }
+    }
+    Ok(())
+}
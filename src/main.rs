@@ -1,99 +1,37 @@
-use std::fs;
+use std::{io, process};
 
-#[warn(unused_imports)]
-use anyhow::Result;
-use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use clap::Parser;
-use rcli::{
-    get_content, get_reader, process_csv, process_decode, process_encode, process_genpass,
-    process_text_decrypt, process_text_encrypt, process_text_key_generate,
-    process_text_nonce_generate, process_text_sign, process_text_verify, Base64SubCommand, Opts,
-    SubCommand, TextSubCommand,
-};
-use zxcvbn::zxcvbn;
+use clap::{CommandFactory, FromArgMatches};
+use rcli::{run, AppError, Config, ErrorFormat, Opts, RcliError};
 
-fn main() -> Result<()> {
-    let opts = Opts::parse();
-    match opts.cmd {
-        SubCommand::Csv(opts) => {
-            let output = if let Some(output) = opts.output {
-                output.clone()
-            } else {
-                format!("output.{}", opts.format)
-            };
-            process_csv(&opts.input, output, opts.format)?;
+fn main() {
+    let matches = Opts::command().get_matches();
+    let mut opts = Opts::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let error_format = opts.error_format;
+    let config = if opts.no_config {
+        Ok(())
+    } else {
+        Config::load().and_then(|config| config.apply(&mut opts, &matches))
+    };
+    if let Err(err) = config.and_then(|_| run(opts)) {
+        if is_broken_pipe(&err) {
+            // the reader went away, e.g. `rcli ... | head`; that's not our failure
+            process::exit(0);
         }
-        SubCommand::GenPass(opts) => {
-            let password = process_genpass(
-                opts.length,
-                opts.has_uppercase,
-                opts.has_lowercase,
-                opts.has_number,
-                opts.has_symbol,
-            )?;
-            println!("{}", password);
-
-            let estimate = zxcvbn(&password, &[])?;
-            eprintln!("Password strength: {}", estimate.score());
+        let err = AppError::from(err);
+        match error_format {
+            ErrorFormat::Text => eprintln!("Error: {}", err),
+            ErrorFormat::Json => eprintln!("{}", err.to_json()),
         }
-        SubCommand::Base64(subcmd) => match subcmd {
-            Base64SubCommand::Encode(opts) => {
-                let mut reader = get_reader(&opts.input)?;
-                let encode = process_encode(&mut reader, opts.format)?;
-                println!("encode:{}", encode);
-            }
-            Base64SubCommand::Decode(opts) => {
-                let mut reader = get_reader(&opts.input)?;
-                let decoded = process_decode(&mut reader, opts.format)?;
-                println!("decoded:{}", decoded);
-            }
-        },
-        SubCommand::Text(subcmd) => match subcmd {
-            TextSubCommand::Sign(opts) => {
-                let mut reader = get_reader(&opts.input)?;
-                let key = get_content(&opts.key)?;
-                let sig = process_text_sign(&mut reader, &key, opts.format)?;
-                let encoded = URL_SAFE_NO_PAD.encode(sig);
-                println!("sig:{}", encoded);
-            }
-            TextSubCommand::Generate(opts) => {
-                let map = process_text_key_generate(opts.format)?;
-                for (k, v) in map {
-                    fs::write(opts.output_path.join(k), v)?;
-                }
-            }
-            TextSubCommand::Verify(opts) => {
-                let mut reader = get_reader(&opts.input)?;
-                let key = get_content(&opts.key)?;
-                let decoded = URL_SAFE_NO_PAD.decode(&opts.sig)?;
-                let verified = process_text_verify(&mut reader, &key, &decoded, opts.format)?;
-                if verified {
-                    println!("verified");
-                } else {
-                    println!("not verified");
-                }
-            }
-            TextSubCommand::GenerateNonce(opts) => {
-                let nonce = process_text_nonce_generate()?;
-                for (k, v) in nonce {
-                    fs::write(opts.output_path.join(k), v)?;
-                }
-            }
-            TextSubCommand::Encrypt(opts) => {
-                let mut reader = get_reader(&opts.input)?;
-                let key = opts.key.into_bytes();
-                let encrypt = process_text_encrypt(&mut reader, &key, &opts.nonce)?;
-                let encrypt = URL_SAFE_NO_PAD.encode(encrypt);
-                println!("encrypt:{}", encrypt);
-            }
-            TextSubCommand::Decrypt(opts) => {
-                let reader = get_content(&opts.input)?;
-                let mut reader = URL_SAFE_NO_PAD.decode(reader)?;
-                let key = opts.key.into_bytes();
-                let decrypt = process_text_decrypt(&mut reader, &key, &opts.nonce)?;
-                println!("decrypt:{}", String::from_utf8(decrypt)?);
-            }
-        },
+        process::exit(err.exit_code());
     }
-    Ok(())
+}
+
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| {
+        let io_err = match e.downcast_ref::<RcliError>() {
+            Some(RcliError::Io(e)) => Some(e),
+            _ => e.downcast_ref::<io::Error>(),
+        };
+        io_err.is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
 }
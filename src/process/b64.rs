@@ -1,32 +1,410 @@
-use std::io::Read;
+use std::io::{self, BufRead, Read, Write};
 
-use crate::Base64Format;
-use anyhow::{Ok, Result};
+use crate::{buffer_size, Base64Format, DecodeAs, RcliResult, SkipWhitespace};
+use anyhow::Result;
 use base64::{
-    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    alphabet::Alphabet,
+    engine::{
+        general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD},
+        DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig,
+    },
+    read::DecoderReader,
+    write::EncoderWriter,
     Engine as _,
 };
 
-pub fn process_encode(reader: &mut dyn Read, format: Base64Format) -> Result<String> {
+/// The variants `Base64Format::Auto` tries, in order.
+const AUTO_ENGINES: [(&str, GeneralPurpose); 4] = [
+    ("urlsafe", URL_SAFE_NO_PAD),
+    ("standard", STANDARD),
+    ("urlsafe (padded)", URL_SAFE),
+    ("standard (unpadded)", STANDARD_NO_PAD),
+];
+
+pub fn process_encode(reader: &mut dyn Read, format: Base64Format) -> RcliResult<String> {
     let mut buf = Vec::new();
     reader.read_to_end(&mut buf)?;
     let encode = match format {
         Base64Format::Standard => STANDARD.encode(&buf),
         Base64Format::UrlSafe => URL_SAFE_NO_PAD.encode(&buf),
+        Base64Format::Auto => return Err(anyhow::anyhow!("auto only applies when decoding").into()),
     };
     Ok(encode)
 }
 
-pub fn process_decode(reader: &mut dyn Read, format: Base64Format) -> Result<String> {
+pub fn process_encode_stream(
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> RcliResult<()> {
+    match format {
+        Base64Format::Standard => encode_stream(reader, EncoderWriter::new(writer, &STANDARD))?,
+        Base64Format::UrlSafe => {
+            encode_stream(reader, EncoderWriter::new(writer, &URL_SAFE_NO_PAD))?
+        }
+        Base64Format::Auto => return Err(anyhow::anyhow!("auto only applies when decoding").into()),
+    }
+    Ok(())
+}
+
+fn encode_stream<E: base64::Engine, W: Write>(
+    reader: &mut dyn Read,
+    mut encoder: EncoderWriter<'_, E, W>,
+) -> Result<()> {
+    let mut buf = vec![0u8; buffer_size()];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        encoder.write_all(&buf[..n])?;
+    }
+    let mut writer = encoder.finish()?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decode to text. Invalid UTF-8 is an error, or with `lossy` is replaced by U+FFFD.
+pub fn process_decode(
+    reader: &mut dyn Read,
+    format: Base64Format,
+    head: Option<usize>,
+    lossy: bool,
+) -> RcliResult<String> {
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let buf = strip_data_uri(buf.trim())?;
+
+    if let Some(head) = head {
+        // base64 decodes in 4-char/3-byte groups, so only decode enough groups to cover `head`
+        let len = buf.len().min(head.div_ceil(3) * 4);
+        let mut decoded = decode(&buf.as_bytes()[..len], format)?;
+        decoded.truncate(head);
+        let decoded = match lossy {
+            true => String::from_utf8_lossy(&decoded).into_owned(),
+            false => String::from_utf8(decoded).unwrap_or_else(|e| {
+                let valid = e.utf8_error().valid_up_to();
+                if e.utf8_error().error_len().is_some() || valid == 0 {
+                    return hex::encode(e.as_bytes());
+                }
+                // the cut landed inside a multi-byte character; drop that partial character
+                String::from_utf8_lossy(&e.as_bytes()[..valid]).into_owned()
+            }),
+        };
+        return Ok(decoded);
+    }
+
+    let decoded = decode(buf.as_bytes(), format)?;
+    let decoded = match lossy {
+        true => String::from_utf8_lossy(&decoded).into_owned(),
+        false => String::from_utf8(decoded)?,
+    };
+    Ok(decoded)
+}
+
+/// Render decoded bytes for `decode --as`. `Utf8` falls back to hex for binary data.
+pub fn format_decoded(bytes: &[u8], format: DecodeAs) -> String {
+    match format {
+        DecodeAs::Utf8 => String::from_utf8(bytes.to_vec()).unwrap_or_else(|_| hex::encode(bytes)),
+        DecodeAs::Hex => hex::encode(bytes),
+        DecodeAs::Rust => {
+            let mut literal = String::from("b\"");
+            for &b in bytes {
+                match b {
+                    b'\n' => literal.push_str("\\n"),
+                    b'\r' => literal.push_str("\\r"),
+                    b'\t' => literal.push_str("\\t"),
+                    b'\0' => literal.push_str("\\0"),
+                    b'"' | b'\\' => {
+                        literal.push('\\');
+                        literal.push(b as char);
+                    }
+                    0x20..=0x7e => literal.push(b as char),
+                    _ => literal.push_str(&format!("\\x{:02x}", b)),
+                }
+            }
+            literal.push('"');
+            literal
+        }
+        DecodeAs::C => {
+            let mut literal = String::from("\"");
+            for &b in bytes {
+                match b {
+                    b'\n' => literal.push_str("\\n"),
+                    b'\r' => literal.push_str("\\r"),
+                    b'\t' => literal.push_str("\\t"),
+                    b'"' | b'\\' => {
+                        literal.push('\\');
+                        literal.push(b as char);
+                    }
+                    // `?` too, so `??x` can't form a trigraph
+                    b'?' => literal.push_str("\\?"),
+                    0x20..=0x7e => literal.push(b as char),
+                    // octal escapes stop after three digits, unlike `\x` which would swallow
+                    // a following hex digit
+                    _ => literal.push_str(&format!("\\{:03o}", b)),
+                }
+            }
+            literal.push('"');
+            literal
+        }
+    }
+}
+
+/// Decode the whole input, then keep `take` bytes (or everything) from offset `skip`.
+pub fn process_decode_window(
+    reader: &mut dyn Read,
+    format: Base64Format,
+    skip: usize,
+    take: Option<usize>,
+) -> RcliResult<Vec<u8>> {
     let mut buf = String::new();
     reader.read_to_string(&mut buf)?;
-    let buf = buf.trim();
+    let mut decoded = decode(strip_data_uri(buf.trim())?.as_bytes(), format)?;
+    if skip > decoded.len() {
+        return Err(anyhow::anyhow!(
+            "--skip {} is past the end of the {} decoded bytes",
+            skip,
+            decoded.len()
+        )
+        .into());
+    }
+    if let Some(take) = take {
+        decoded.truncate(skip.saturating_add(take));
+    }
+    Ok(decoded.split_off(skip))
+}
+
+/// Stream-decode into `writer` without buffering the output or requiring it to be UTF-8.
+/// Whitespace (e.g. wrapped lines) is ignored, as is a leading `data:...;base64,` prefix.
+pub fn process_decode_to_writer(
+    reader: &mut dyn BufRead,
+    writer: &mut dyn Write,
+    format: Base64Format,
+) -> RcliResult<()> {
+    if reader.fill_buf()?.starts_with(b"data:") {
+        let mut meta = Vec::new();
+        reader.read_until(b',', &mut meta)?;
+        strip_data_uri(std::str::from_utf8(&meta)?)?;
+    }
+    let mut reader = SkipWhitespace(reader);
+    if format == Base64Format::Auto {
+        // detection needs the whole input, so this can't stream
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        writer.write_all(&decode(&buf, format)?)?;
+        writer.flush()?;
+        return Ok(());
+    }
+    match format {
+        Base64Format::Standard => {
+            io::copy(&mut DecoderReader::new(&mut reader, &STANDARD), writer)?
+        }
+        Base64Format::UrlSafe => io::copy(
+            &mut DecoderReader::new(&mut reader, &URL_SAFE_NO_PAD),
+            writer,
+        )?,
+        Base64Format::Auto => unreachable!("handled above"),
+    };
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn process_transcode(
+    reader: &mut dyn Read,
+    from: Base64Format,
+    to: Base64Format,
+) -> RcliResult<String> {
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let decoded = decode(buf.trim().as_bytes(), from)?;
+    let encoded = match to {
+        Base64Format::Standard => STANDARD.encode(decoded),
+        Base64Format::UrlSafe => URL_SAFE_NO_PAD.encode(decoded),
+        Base64Format::Auto => return Err(anyhow::anyhow!("auto only applies when decoding").into()),
+    };
+    Ok(encoded)
+}
+
+/// Report whether the input decodes cleanly, without producing the (possibly binary) output.
+pub fn process_decode_check(reader: &mut dyn Read, format: Base64Format) -> RcliResult<bool> {
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let valid = strip_data_uri(buf.trim()).is_ok_and(|buf| decode(buf.as_bytes(), format).is_ok());
+    Ok(valid)
+}
+
+/// Encode `reader` in independent `chunk_bytes` pieces, one `NNN:<base64>` line per piece.
+pub fn process_split(
+    reader: &mut dyn Read,
+    format: Base64Format,
+    chunk_bytes: usize,
+) -> RcliResult<Vec<String>> {
+    let mut chunks = Vec::new();
+    loop {
+        let mut buf = Vec::with_capacity(chunk_bytes);
+        (&mut *reader)
+            .take(chunk_bytes as u64)
+            .read_to_end(&mut buf)?;
+        if buf.is_empty() {
+            break;
+        }
+        let encoded = process_encode(&mut buf.as_slice(), format)?;
+        chunks.push(format!("{:03}:{}", chunks.len(), encoded));
+    }
+    Ok(chunks)
+}
+
+/// Reassemble the output of `process_split`; lines may come in any order.
+pub fn process_join(reader: &mut dyn Read, format: Base64Format) -> RcliResult<Vec<u8>> {
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let mut chunks = Vec::new();
+    for line in buf.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (index, chunk) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("chunk lines must look like NNN:<base64>"))?;
+        chunks.push((index.parse::<usize>().map_err(anyhow::Error::from)?, chunk));
+    }
+    chunks.sort_by_key(|(index, _)| *index);
+    let mut joined = Vec::new();
+    for (expected, (index, chunk)) in chunks.into_iter().enumerate() {
+        if index != expected {
+            return Err(anyhow::anyhow!("chunk {:03} is missing or duplicated", expected).into());
+        }
+        joined.extend(decode(chunk.as_bytes(), format)?);
+    }
+    Ok(joined)
+}
+
+/// Format `bytes` like `xxd`: offset, 16 bytes as hex pairs, then the printable ASCII.
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex = chunk
+            .chunks(2)
+            .map(hex::encode)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        out.push_str(&format!("{:08x}: {:<39}  {}\n", row * 16, hex, ascii));
+    }
+    out
+}
+
+/// Strip a leading `data:<mediatype>;base64,` prefix, if any.
+fn strip_data_uri(buf: &str) -> Result<&str> {
+    let Some(uri) = buf.strip_prefix("data:") else {
+        return Ok(buf);
+    };
+    let (meta, data) = uri
+        .split_once(',')
+        .ok_or_else(|| anyhow::anyhow!("invalid data URI: missing ','"))?;
+    if !meta.ends_with(";base64") {
+        return Err(anyhow::anyhow!("data URI is not base64-encoded"));
+    }
+    Ok(data)
+}
+
+/// Name the base64 variant `Base64Format::Auto` would decode `encoded` as, if any.
+pub fn detect_format(encoded: &str) -> Option<&'static str> {
+    let encoded = strip_data_uri(encoded.trim()).ok()?;
+    let encoded = encoded
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    AUTO_ENGINES
+        .iter()
+        .find(|(_, engine)| engine.decode(&encoded).is_ok())
+        .map(|(name, _)| *name)
+}
+
+/// A nonstandard alphabet from `--alphabet`: 64 distinct characters, optionally followed by a
+/// padding character (none means unpadded output).
+#[derive(Debug, Clone)]
+pub struct CustomAlphabet {
+    engine: GeneralPurpose,
+    pad: Option<char>,
+}
+
+impl CustomAlphabet {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let chars = spec.chars().collect::<Vec<_>>();
+        let (symbols, pad) = match chars.len() {
+            64 => (spec, None),
+            65 => (&spec[..spec.len() - chars[64].len_utf8()], Some(chars[64])),
+            n => anyhow::bail!(
+                "alphabet must be 64 characters plus an optional pad, got {}",
+                n
+            ),
+        };
+        let alphabet =
+            Alphabet::new(symbols).map_err(|e| anyhow::anyhow!("invalid alphabet: {}", e))?;
+        if let Some(pad) = pad {
+            if !pad.is_ascii() || symbols.contains(pad) {
+                anyhow::bail!(
+                    "pad character {:?} must be ascii and not in the alphabet",
+                    pad
+                );
+            }
+        }
+        let config = GeneralPurposeConfig::new()
+            .with_encode_padding(pad.is_some())
+            .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+        Ok(Self {
+            engine: GeneralPurpose::new(&alphabet, config),
+            pad,
+        })
+    }
+}
+
+pub fn process_encode_custom(
+    reader: &mut dyn Read,
+    alphabet: &CustomAlphabet,
+) -> RcliResult<String> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let encoded = alphabet.engine.encode(&buf);
+    Ok(match alphabet.pad {
+        Some(pad) if pad != '=' => encoded.replace('=', pad.encode_utf8(&mut [0; 4])),
+        _ => encoded,
+    })
+}
+
+pub fn process_decode_custom(
+    reader: &mut dyn Read,
+    alphabet: &CustomAlphabet,
+) -> RcliResult<Vec<u8>> {
+    let mut buf = String::new();
+    reader.read_to_string(&mut buf)?;
+    let mut buf = buf
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect::<String>();
+    if let Some(pad) = alphabet.pad {
+        buf = buf.replace(pad, "=");
+    }
+    Ok(alphabet.engine.decode(buf)?)
+}
 
+fn decode(buf: &[u8], format: Base64Format) -> Result<Vec<u8>> {
     let decoded = match format {
         Base64Format::Standard => STANDARD.decode(buf)?,
         Base64Format::UrlSafe => URL_SAFE_NO_PAD.decode(buf)?,
+        Base64Format::Auto => AUTO_ENGINES
+            .iter()
+            .find_map(|(_, engine)| engine.decode(buf).ok())
+            .ok_or_else(|| anyhow::anyhow!("input is not valid in any base64 variant"))?,
     };
-    let decoded = String::from_utf8(decoded)?;
     Ok(decoded)
 }
 
@@ -36,6 +414,118 @@ mod tests {
 
     use super::*;
 
+    /// Base64 encoding, blake3 digest and `(lines, words, bytes)` of the same input.
+    type Outputs = (Vec<u8>, Vec<u8>, (usize, usize, usize));
+
+    #[test]
+    fn test_buffer_size_does_not_change_output() -> Result<()> {
+        let data = (0..3_000_001u32)
+            .map(|i| (i * 31) as u8)
+            .collect::<Vec<_>>();
+        let run = |size| -> Result<Outputs> {
+            crate::set_buffer_size(size);
+            let mut encoded = Vec::new();
+            process_encode_stream(&mut data.as_slice(), &mut encoded, Base64Format::Standard)?;
+            let digest = crate::process_hash(&mut data.as_slice(), crate::HashAlgo::Blake3)?;
+            let counts = crate::process_count(&mut data.as_slice())?;
+            Ok((encoded, digest, counts))
+        };
+        let small = run(8 * 1024)?;
+        let large = run(1024 * 1024)?;
+        crate::set_buffer_size(crate::DEFAULT_BUFFER_SIZE);
+        assert_eq!(small.0, STANDARD.encode(&data).into_bytes());
+        assert_eq!(small, large);
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_alphabet() -> Result<()> {
+        const SHUFFLED: &str = "zyxwvutsrqponmlkjihgfedcbaZYXWVUTSRQPONMLKJIHGFEDCBA9876543210-_";
+        let data = b"hello custom world";
+        for spec in [SHUFFLED.to_string(), format!("{}.", SHUFFLED)] {
+            let alphabet = CustomAlphabet::parse(&spec)?;
+            let encoded = process_encode_custom(&mut data.as_slice(), &alphabet)?;
+            assert_ne!(encoded, STANDARD.encode(data));
+            let decoded = process_decode_custom(&mut encoded.as_bytes(), &alphabet)?;
+            assert_eq!(decoded, data);
+        }
+        let padded = CustomAlphabet::parse(&format!("{}.", SHUFFLED))?;
+        assert!(process_encode_custom(&mut "hi".as_bytes(), &padded)?.ends_with('.'));
+
+        assert!(CustomAlphabet::parse(&SHUFFLED[1..]).is_err());
+        let repeated = format!("a{}", &SHUFFLED[1..]);
+        assert!(CustomAlphabet::parse(&repeated).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_auto() -> Result<()> {
+        let url_safe = URL_SAFE_NO_PAD.encode([0xfb, 0xff, 0xfe]);
+        assert!(url_safe.contains('-') || url_safe.contains('_'));
+        assert_eq!(detect_format(&url_safe), Some("urlsafe"));
+        assert_eq!(
+            decode(url_safe.as_bytes(), Base64Format::Auto)?,
+            [0xfb, 0xff, 0xfe]
+        );
+
+        let standard = STANDARD.encode([0xfb, 0xff, 0xfe, 0x01]);
+        assert!(standard.contains('+') || standard.contains('/'));
+        assert_eq!(detect_format(&standard), Some("standard"));
+        assert_eq!(
+            decode(standard.as_bytes(), Base64Format::Auto)?,
+            [0xfb, 0xff, 0xfe, 0x01]
+        );
+
+        // explicit formats don't fall back
+        assert!(decode(url_safe.as_bytes(), Base64Format::Standard).is_err());
+        assert_eq!(detect_format("not base64!"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_decoded() {
+        let bytes = b"\x89PNG\r\n\x1a\n\0\"a\\?";
+        assert_eq!(
+            format_decoded(bytes, DecodeAs::Rust),
+            r#"b"\x89PNG\r\n\x1a\n\0\"a\\?""#
+        );
+        assert_eq!(
+            format_decoded(bytes, DecodeAs::C),
+            r#""\211PNG\r\n\032\n\000\"a\\\?""#
+        );
+        assert_eq!(format_decoded(b"\xff\x01", DecodeAs::Hex), "ff01");
+        assert_eq!(format_decoded(b"\xff\x01", DecodeAs::Utf8), "ff01");
+        assert_eq!(format_decoded(b"hi", DecodeAs::Utf8), "hi");
+    }
+
+    #[test]
+    fn test_process_split_join() -> Result<()> {
+        let data = (0..=255u8).cycle().take(20_000).collect::<Vec<_>>();
+        let chunks = process_split(&mut data.as_slice(), Base64Format::UrlSafe, 8192)?;
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[2].starts_with("002:"));
+
+        let shuffled = [&chunks[2], &chunks[0], &chunks[1]]
+            .iter()
+            .map(|chunk| format!("{}\n", chunk))
+            .collect::<String>();
+        let joined = process_join(&mut shuffled.as_bytes(), Base64Format::UrlSafe)?;
+        assert_eq!(joined, data);
+
+        let missing = format!("{}\n{}\n", chunks[0], chunks[2]);
+        assert!(process_join(&mut missing.as_bytes(), Base64Format::UrlSafe).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_check() -> Result<()> {
+        let mut reader = "aGVsbG8gd29ybGQ=".as_bytes();
+        assert!(process_decode_check(&mut reader, Base64Format::Standard)?);
+        let mut reader = "aGVsbG8*d29ybGQ=".as_bytes();
+        assert!(!process_decode_check(&mut reader, Base64Format::Standard)?);
+        Ok(())
+    }
+
     #[test]
     fn test_process_encode() -> Result<()> {
         let input = "Cargo.toml";
@@ -45,12 +535,137 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_process_encode_stream() -> Result<()> {
+        let data: Vec<u8> = (0..10 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        for format in [Base64Format::Standard, Base64Format::UrlSafe] {
+            let mut reader = std::io::Cursor::new(&data);
+            let mut out = Vec::new();
+            process_encode_stream(&mut reader, &mut out, format)?;
+            let mut reader = std::io::Cursor::new(&data);
+            let expected = process_encode(&mut reader, format)?;
+            assert_eq!(String::from_utf8(out)?, expected);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_process_decode() -> Result<()> {
         let input = "fixtures/b64.txt";
         let mut reader = get_reader(input)?;
         let format = Base64Format::Standard;
-        process_decode(&mut reader, format).unwrap();
+        process_decode(&mut reader, format, None, false).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_data_uri() -> Result<()> {
+        let mut reader = std::io::Cursor::new("data:text/plain;base64,aGVsbG8gd29ybGQ=\n");
+        let decoded = process_decode(&mut reader, Base64Format::Standard, None, false)?;
+        assert_eq!(decoded, "hello world");
+
+        let mut reader = std::io::Cursor::new("data:text/plain,hello");
+        assert!(process_decode(&mut reader, Base64Format::Standard, None, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_lossy() -> Result<()> {
+        let encoded = STANDARD.encode(b"caf\xe9 ok");
+        let strict = process_decode(&mut encoded.as_bytes(), Base64Format::Standard, None, false);
+        assert!(strict.is_err());
+        let lossy = process_decode(&mut encoded.as_bytes(), Base64Format::Standard, None, true)?;
+        assert_eq!(lossy, "caf\u{fffd} ok");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_window() -> Result<()> {
+        let data: Vec<u8> = (0..=255).collect();
+        let encoded = STANDARD.encode(&data);
+        let window =
+            process_decode_window(&mut encoded.as_bytes(), Base64Format::Standard, 16, Some(4))?;
+        assert_eq!(window, [16, 17, 18, 19]);
+        let tail = process_decode_window(
+            &mut encoded.as_bytes(),
+            Base64Format::Standard,
+            250,
+            Some(100),
+        )?;
+        assert_eq!(tail, [250, 251, 252, 253, 254, 255]);
+        assert!(
+            process_decode_window(&mut encoded.as_bytes(), Base64Format::Standard, 257, None)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"\x89PNG");
+        assert_eq!(
+            dump,
+            format!("00000000: 8950 4e47{}  .PNG\n", " ".repeat(30))
+        );
+
+        let dump = hexdump(&[b'a'; 20]);
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("00000010: 6161 6161  "));
+    }
+
+    #[test]
+    fn test_process_decode_to_writer() -> Result<()> {
+        let data: Vec<u8> = (0..=255).cycle().take(100_000).collect();
+        let encoded = STANDARD.encode(&data);
+        let wrapped = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|line| format!("{}\n", std::str::from_utf8(line).unwrap()))
+            .collect::<String>();
+        let mut out = Vec::new();
+        process_decode_to_writer(&mut wrapped.as_bytes(), &mut out, Base64Format::Standard)?;
+        assert_eq!(out, data);
+
+        let uri = format!(
+            "data:application/octet-stream;base64,{}",
+            STANDARD.encode([0xff, 0xfe])
+        );
+        let mut out = Vec::new();
+        process_decode_to_writer(&mut uri.as_bytes(), &mut out, Base64Format::Standard)?;
+        assert_eq!(out, [0xff, 0xfe]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_transcode() -> Result<()> {
+        let data: Vec<u8> = (0..=255).collect();
+        let url_safe = URL_SAFE_NO_PAD.encode(&data);
+
+        let mut reader = std::io::Cursor::new(url_safe.clone());
+        let standard =
+            process_transcode(&mut reader, Base64Format::UrlSafe, Base64Format::Standard)?;
+        assert_eq!(standard, STANDARD.encode(&data));
+
+        let mut reader = std::io::Cursor::new(standard);
+        let back = process_transcode(&mut reader, Base64Format::Standard, Base64Format::UrlSafe)?;
+        assert_eq!(back, url_safe);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decode_head() -> Result<()> {
+        let mut reader = std::io::Cursor::new("iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJ");
+        let decoded = process_decode(&mut reader, Base64Format::Standard, Some(4), false)?;
+        assert_eq!(decoded, "89504e47");
+
+        // `é` is two bytes, so a head of 2 cuts it in half
+        let mut reader = std::io::Cursor::new(STANDARD.encode("héllo"));
+        let decoded = process_decode(&mut reader, Base64Format::Standard, Some(2), false)?;
+        assert_eq!(decoded, "h");
+        let mut reader = std::io::Cursor::new(STANDARD.encode("héllo"));
+        let decoded = process_decode(&mut reader, Base64Format::Standard, Some(3), false)?;
+        assert_eq!(decoded, "hé");
         Ok(())
     }
 }
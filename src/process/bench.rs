@@ -0,0 +1,50 @@
+use std::time::{Duration, Instant};
+
+use rand::RngCore;
+
+use crate::{
+    process_text_encrypt, process_text_key_generate, process_text_sign, KeyFormat, NonceSource,
+    RcliResult, SignOptions, TextSignFormat,
+};
+
+/// Time Blake3 and Ed25519 signing and ChaCha20-Poly1305 encryption of `size` random bytes,
+/// each with a freshly generated key.
+pub fn process_bench(size: usize) -> RcliResult<Vec<(&'static str, Duration)>> {
+    let mut data = vec![0u8; size];
+    rand::thread_rng().fill_bytes(&mut data);
+
+    let mut results = Vec::new();
+    for (name, format, key) in [
+        ("blake3", TextSignFormat::Blake3, "blake3.txt"),
+        ("ed25519", TextSignFormat::Ed25519, "ed25519.sk"),
+    ] {
+        let key = &process_text_key_generate(format, KeyFormat::Raw)?[key];
+        let start = Instant::now();
+        process_text_sign(&mut data.as_slice(), key, &SignOptions::new(format))?;
+        results.push((name, start.elapsed()));
+    }
+
+    let key = &process_text_key_generate(TextSignFormat::Chacha, KeyFormat::Raw)?["chacha.key"];
+    let mut base = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut base);
+    let nonce = NonceSource::Counter { base, index: 0 };
+    let start = Instant::now();
+    process_text_encrypt(&mut data.as_slice(), key, &nonce, b"")?;
+    results.push(("chacha20", start.elapsed()));
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_process_bench() -> Result<()> {
+        let results = process_bench(4096)?;
+        let names = results.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+        assert_eq!(names, ["blake3", "ed25519", "chacha20"]);
+        Ok(())
+    }
+}
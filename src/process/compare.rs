@@ -0,0 +1,18 @@
+use subtle::ConstantTimeEq;
+
+/// Compare two secrets in constant time with respect to their contents.
+pub fn process_compare(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_compare() {
+        assert!(process_compare(b"s3cret", b"s3cret"));
+        assert!(!process_compare(b"s3cret", b"s3creT"));
+        assert!(!process_compare(b"s3cret", b"s3cret!"));
+    }
+}
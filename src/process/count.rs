@@ -0,0 +1,48 @@
+use std::io::Read;
+
+use crate::{buffer_size, RcliResult};
+
+/// Count `(lines, words, bytes)` like `wc`: lines are newline characters, so a last line
+/// without a trailing newline is not counted, and words are runs of non-whitespace.
+pub fn process_count(reader: &mut dyn Read) -> RcliResult<(usize, usize, usize)> {
+    let (mut lines, mut words, mut bytes) = (0, 0, 0);
+    let mut in_word = false;
+    let mut buf = vec![0u8; buffer_size()];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        bytes += n;
+        for &b in &buf[..n] {
+            if b == b'\n' {
+                lines += 1;
+            }
+            if b.is_ascii_whitespace() {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                words += 1;
+            }
+        }
+    }
+    Ok((lines, words, bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+    use crate::get_reader;
+
+    #[test]
+    fn test_process_count() -> Result<()> {
+        let mut reader = get_reader("fixtures/count.txt")?;
+        assert_eq!(process_count(&mut reader)?, (2, 7, 34));
+
+        let mut reader = "one\ntwo\n".as_bytes();
+        assert_eq!(process_count(&mut reader)?, (2, 2, 8));
+        Ok(())
+    }
+}
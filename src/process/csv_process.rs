@@ -1,11 +1,25 @@
-use std::fs;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, Cursor, Read, Write},
+    path::Path,
+};
 
-use anyhow::{Ok, Result};
-use csv::Reader;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use anyhow::Result;
+use csv::{QuoteStyle, ReaderBuilder, StringRecord, Terminator, Trim, WriterBuilder};
+use flate2::{write::GzEncoder, Compression};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+use serde_json::{Map, Value};
+use walkdir::WalkDir;
 
-use crate::OutputFormat;
+use crate::{
+    check_interrupted, expand_output_template, skip_bom, write_atomic, write_atomic_with,
+    BatchErrors, CsvOpts, CsvQuoteStyle, DedupeKeys, HeaderCase, InputEncoding, OnError,
+    OutputFormat, RcliResult,
+};
 
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
@@ -19,20 +33,1914 @@ struct Player {
     kit: u8,
 }
 
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let mut ret = Vec::new();
-    let headers = reader.headers()?.clone();
-    for result in reader.records() {
-        let record = result?;
-        let json_value = headers.iter().zip(record.iter()).collect::<Value>();
-        ret.push(json_value);
+/// Summary of a conversion, printed by `--stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvStats {
+    pub rows: usize,
+    pub columns: usize,
+    pub bytes_out: usize,
+}
+
+impl fmt::Display for CsvStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "rows: {}, columns: {}, bytes_out: {}",
+            self.rows, self.columns, self.bytes_out
+        )
+    }
+}
+
+pub fn process_csv(opts: &CsvOpts, output: String) -> RcliResult<CsvStats> {
+    Ok(convert_file(
+        Path::new(&opts.input),
+        Path::new(&output),
+        opts,
+    )?)
+}
+
+/// Convert every `.csv` file under `opts.input` into `output_dir`, keeping the relative layout
+/// and swapping the extension for the output format. Returns the number of files converted.
+pub fn process_csv_dir(
+    opts: &CsvOpts,
+    output_dir: &Path,
+    errors: &mut BatchErrors,
+) -> RcliResult<usize> {
+    let input_dir = Path::new(&opts.input);
+    let format = opts.output_format();
+    let extension = match opts.gzip {
+        true => format!("{}.gz", format),
+        false => format.to_string(),
+    };
+    let mut count = 0;
+    for entry in WalkDir::new(input_dir).sort_by_file_name() {
+        let entry = entry.map_err(anyhow::Error::from)?;
+        let path = entry.path();
+        if !entry.file_type().is_file()
+            || path.extension().and_then(|ext| ext.to_str()) != Some("csv")
+        {
+            continue;
+        }
+        let relative = path.strip_prefix(input_dir).map_err(anyhow::Error::from)?;
+        let output = match &opts.output_template {
+            Some(template) => output_dir.join(expand_output_template(template, relative)?),
+            None => output_dir.join(relative).with_extension(&extension),
+        };
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if errors
+            .run(&path.display().to_string(), || {
+                convert_file(path, &output, opts)
+            })?
+            .is_some()
+        {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn convert_file(input: &Path, output: &Path, opts: &CsvOpts) -> Result<CsvStats> {
+    let input = File::open(input)?;
+    let format = opts.output_format();
+    if opts.append {
+        if !matches!(format, OutputFormat::Csv) {
+            anyhow::bail!(
+                "--append only supports csv output, {} files can't be concatenated",
+                format
+            );
+        }
+        // only a new (or empty) file gets the header row
+        let exists = fs::metadata(output).is_ok_and(|meta| meta.len() > 0);
+        let (content, stats) = convert(input, format, opts, !exists)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(output)?;
+        file.write_all(&content)?;
+        return Ok(stats);
+    }
+    let table = read_table(input, format, opts)?;
+    write_atomic_with(output, |writer| {
+        write_table(writer, format, &table, &table.records, opts, true)
+    })
+}
+
+/// Convert `opts.input` and write it as `<output>.<n>.<format>` parts of at most `rows` rows,
+/// repeating the header in every csv part; a `.<format>` extension on `output` is dropped first.
+/// Returns the number of parts written (at least one).
+pub fn process_csv_split(opts: &CsvOpts, output: &str, rows: u64) -> RcliResult<(usize, CsvStats)> {
+    let format = opts.output_format();
+    if !matches!(format, OutputFormat::Ndjson | OutputFormat::Csv) {
+        return Err(anyhow::anyhow!(
+            "--split-rows only applies to line-oriented output (ndjson, csv), not {}",
+            format
+        )
+        .into());
+    }
+    if rows == 0 {
+        return Err(anyhow::anyhow!("--split-rows must be at least 1").into());
+    }
+    let table = read_table(File::open(&opts.input)?, format, opts)?;
+    let base = output
+        .strip_suffix(&format!(".{}", format))
+        .filter(|base| !base.is_empty())
+        .unwrap_or(output);
+    // an empty input still gets one part, holding just the csv header
+    let parts = if table.records.is_empty() {
+        vec![&table.records[..]]
+    } else {
+        table.records.chunks(rows as usize).collect()
+    };
+    let mut stats = CsvStats {
+        rows: table.records.len(),
+        columns: table.headers.len(),
+        bytes_out: 0,
+    };
+    for (n, records) in parts.iter().enumerate() {
+        let part = write_atomic_with(format!("{}.{}.{}", base, n, format), |writer| {
+            write_table(writer, format, &table, records, opts, true)
+        })?;
+        stats.bytes_out += part.bytes_out;
+    }
+    Ok((parts.len(), stats))
+}
+
+/// Convert CSV read from any `Read` into `format`, without touching the filesystem.
+///
+/// ```
+/// use clap::Parser;
+/// use rcli::{process_csv_reader, CsvOpts, OutputFormat};
+///
+/// let opts = CsvOpts::parse_from(["csv", "-i", "-"]);
+/// let input = std::io::Cursor::new("name,age\nalice,30\nbob,25\n");
+/// let json = process_csv_reader(input, OutputFormat::Json, &opts).unwrap();
+/// let rows: serde_json::Value = serde_json::from_str(&json).unwrap();
+/// assert_eq!(rows[0]["name"], "alice");
+/// assert_eq!(rows[1]["age"], "25");
+/// ```
+pub fn process_csv_reader(
+    reader: impl Read,
+    format: OutputFormat,
+    opts: &CsvOpts,
+) -> RcliResult<String> {
+    if format.is_binary() {
+        return Err(anyhow::anyhow!("{} output is binary, use process_csv_bytes", format).into());
+    }
+    if opts.gzip {
+        return Err(anyhow::anyhow!("gzip output is binary, use process_csv_bytes").into());
+    }
+    Ok(String::from_utf8(process_csv_bytes(reader, format, opts)?)?)
+}
+
+/// Like `process_csv_reader`, but also supports the binary `Msgpack` and `Cbor` formats and
+/// `--gzip`.
+pub fn process_csv_bytes(
+    reader: impl Read,
+    format: OutputFormat,
+    opts: &CsvOpts,
+) -> RcliResult<Vec<u8>> {
+    Ok(process_csv_with_stats(reader, format, opts)?.0)
+}
+
+pub fn process_csv_with_stats(
+    reader: impl Read,
+    format: OutputFormat,
+    opts: &CsvOpts,
+) -> RcliResult<(Vec<u8>, CsvStats)> {
+    Ok(convert(reader, format, opts, true)?)
+}
+
+fn convert(
+    reader: impl Read,
+    format: OutputFormat,
+    opts: &CsvOpts,
+    csv_header: bool,
+) -> Result<(Vec<u8>, CsvStats)> {
+    let table = read_table(reader, format, opts)?;
+    let mut content = Vec::new();
+    let stats = write_table(
+        &mut content,
+        format,
+        &table,
+        &table.records,
+        opts,
+        csv_header,
+    )?;
+    Ok((content, stats))
+}
+
+/// The input after parsing and every column and row transform, ready to write in any format.
+struct Table {
+    headers: StringRecord,
+    types: Vec<ColumnType>,
+    records: Vec<StringRecord>,
+}
+
+fn read_table(reader: impl Read, format: OutputFormat, opts: &CsvOpts) -> Result<Table> {
+    let mut reader = BufReader::new(decode_input(reader, opts.input_encoding)?);
+    skip_bom(&mut reader)?;
+    // the error report quotes the raw lines, so keep the input around to look them up
+    let mut raw = Vec::new();
+    let reader: Box<dyn Read + '_> = match &opts.error_report {
+        Some(_) if opts.on_error != OnError::Skip => {
+            anyhow::bail!("--error-report needs --on-error skip")
+        }
+        Some(_) => {
+            reader.read_to_end(&mut raw)?;
+            Box::new(raw.as_slice())
+        }
+        None => Box::new(reader),
+    };
+    let trim = if opts.trim { Trim::All } else { Trim::None };
+    let mut reader = ReaderBuilder::new()
+        .delimiter(opts.delimiter)
+        .trim(trim)
+        .comment(opts.comment)
+        .flexible(opts.flexible)
+        .from_reader(reader);
+    let headers = dedupe_headers(reader.headers()?, opts.dedupe_keys)?;
+    let (mut records, bad_rows) = read_records(&mut reader, opts)?;
+    if opts.flexible {
+        for (row, record) in records.iter_mut().enumerate() {
+            if record.len() > headers.len() {
+                anyhow::bail!(
+                    "row {}: {} fields, but the header only has {}",
+                    row + 1,
+                    record.len(),
+                    headers.len()
+                );
+            }
+            while record.len() < headers.len() {
+                record.push_field("");
+            }
+        }
+    }
+    if let Some(path) = &opts.error_report {
+        write_atomic(path, &error_report(&raw, &bad_rows)?)?;
     }
+    if let Some(column) = &opts.explode {
+        records = explode(&headers, records, column, opts.sub_delimiter)?;
+    }
+    let columns = match &opts.columns_file {
+        Some(path) => read_column_list(path)?,
+        None => opts.columns.clone(),
+    };
+    let (headers, records) = if columns.is_empty() {
+        (headers, records)
+    } else {
+        select_columns(&headers, &records, &columns)?
+    };
+    let (headers, records) = if opts.reorder.is_empty() {
+        (headers, records)
+    } else {
+        let columns = reorder_columns(&headers, &opts.reorder)?;
+        select_columns(&headers, &records, &columns)?
+    };
+    let headers = rename_headers(&headers, &opts.rename, opts.header_case)?;
+    let types = match &opts.schema {
+        Some(path) => check_schema(&headers, &records, &load_schema(path)?, opts)?,
+        None => Vec::new(),
+    };
+    if opts.key_by.is_some() && !matches!(format, OutputFormat::Json | OutputFormat::Yaml) {
+        anyhow::bail!("--key-by only applies to json and yaml output");
+    }
+    if opts.section_by.is_some() && !matches!(format, OutputFormat::Ini) {
+        anyhow::bail!("--section-by only applies to ini output");
+    }
+    Ok(Table {
+        headers,
+        types,
+        records,
+    })
+}
+
+/// Write `records` (all of `table.records`, or a run of them) to `out` in `format`, through
+/// gzip with `--gzip`. The rows are already in memory, but the output is never buffered whole:
+/// ndjson goes out row by row and gzip compresses as it is written.
+fn write_table(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    table: &Table,
+    records: &[StringRecord],
+    opts: &CsvOpts,
+    csv_header: bool,
+) -> Result<CsvStats> {
+    let mut out = CountingWriter {
+        inner: out,
+        count: 0,
+    };
+    if opts.gzip {
+        let mut encoder = GzEncoder::new(&mut out, Compression::default());
+        write_rows(&mut encoder, format, table, records, opts, csv_header)?;
+        encoder.finish()?;
+    } else {
+        write_rows(&mut out, format, table, records, opts, csv_header)?;
+    }
+    Ok(CsvStats {
+        rows: records.len(),
+        columns: table.headers.len(),
+        bytes_out: out.count,
+    })
+}
+
+fn write_rows(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    table: &Table,
+    records: &[StringRecord],
+    opts: &CsvOpts,
+    csv_header: bool,
+) -> Result<()> {
+    let (headers, types) = (&table.headers, &table.types);
+    match format {
+        OutputFormat::Ndjson => write_ndjson(out, headers, types, records, opts),
+        format => {
+            let content = render(format, headers, types, records, opts, csv_header)?;
+            out.write_all(&content)?;
+            Ok(())
+        }
+    }
+}
+
+/// Counts the bytes passed through to `inner`, for `CsvStats::bytes_out`.
+struct CountingWriter<W> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Every format but ndjson, which `convert` writes row by row.
+fn render(
+    format: OutputFormat,
+    headers: &StringRecord,
+    types: &[ColumnType],
+    records: &[StringRecord],
+    opts: &CsvOpts,
+    csv_header: bool,
+) -> Result<Vec<u8>> {
     let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&ret)?,
-        OutputFormat::Yaml => serde_yml::to_string(&ret)?,
+        OutputFormat::Json => match &opts.key_by {
+            Some(column) => {
+                let keyed = to_keyed(headers, types, records, column, opts)?;
+                serde_json::to_string_pretty(&keyed)?.into_bytes()
+            }
+            None => to_json(headers, types, records, opts)?.into_bytes(),
+        },
+        OutputFormat::Yaml => match &opts.key_by {
+            Some(column) => {
+                let keyed = to_keyed(headers, types, records, column, opts)?;
+                serde_yml::to_string(&keyed)?.into_bytes()
+            }
+            None => serde_yml::to_string(&to_values(headers, types, records, opts))?.into_bytes(),
+        },
+        OutputFormat::Csv => {
+            let (delimiter, quote) = (opts.output_delimiter, opts.quote_style);
+            to_csv(headers, records, delimiter, quote, csv_header)?.into_bytes()
+        }
+        OutputFormat::Msgpack => rmp_serde::to_vec(&to_values(headers, types, records, opts))?,
+        OutputFormat::Cbor => {
+            let mut buf = Vec::new();
+            ciborium::into_writer(&to_values(headers, types, records, opts), &mut buf)?;
+            buf
+        }
+        OutputFormat::Ini => {
+            let column = opts
+                .section_by
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("--format ini needs --section-by COLUMN"))?;
+            to_ini(headers, records, column)?.into_bytes()
+        }
+        OutputFormat::Ndjson => unreachable!("ndjson is written row by row"),
+    };
+    Ok(content)
+}
+
+/// Make repeated header names unique, so no column overwrites another in a row object.
+/// Later copies get the first free `_2`, `_3`, ... suffix.
+fn dedupe_headers(headers: &StringRecord, mode: DedupeKeys) -> Result<StringRecord> {
+    let mut seen = HashSet::new();
+    let mut deduped = Vec::with_capacity(headers.len());
+    for header in headers {
+        if seen.insert(header.to_string()) {
+            deduped.push(header.to_string());
+            continue;
+        }
+        if mode == DedupeKeys::Error {
+            anyhow::bail!(
+                "duplicate column {} (use --dedupe-keys suffix to rename the later ones)",
+                header
+            );
+        }
+        let name = (2..)
+            .map(|n| format!("{}_{}", header, n))
+            .find(|name| !headers.iter().any(|h| h == name) && !seen.contains(name))
+            .expect("some suffix is free");
+        seen.insert(name.clone());
+        deduped.push(name);
+    }
+    Ok(deduped.into_iter().collect())
+}
+
+/// Apply `--rename old=new` pairs to the header row, then `case` to the columns not renamed.
+fn rename_headers(
+    headers: &StringRecord,
+    renames: &[(String, String)],
+    case: HeaderCase,
+) -> Result<StringRecord> {
+    if let Some((old, _)) = renames
+        .iter()
+        .find(|(old, _)| !headers.iter().any(|header| header == old))
+    {
+        anyhow::bail!("--rename: column {} not found", old);
+    }
+    let renamed = headers
+        .iter()
+        .map(
+            |header| match renames.iter().find(|(old, _)| old == header) {
+                Some((_, new)) => new.clone(),
+                None => convert_case(header, case),
+            },
+        )
+        .collect::<Vec<_>>();
+    if case != HeaderCase::None {
+        for (i, name) in renamed.iter().enumerate() {
+            if let Some(j) = renamed[..i].iter().position(|other| other == name) {
+                anyhow::bail!(
+                    "--header-case {}: columns {} and {} both become {}",
+                    case,
+                    &headers[j],
+                    &headers[i],
+                    name
+                );
+            }
+        }
+    }
+    Ok(renamed.into_iter().collect())
+}
+
+fn convert_case(header: &str, case: HeaderCase) -> String {
+    match case {
+        HeaderCase::None => header.to_string(),
+        HeaderCase::Lower => header.to_lowercase(),
+        HeaderCase::Snake => header_words(header).join("_").to_lowercase(),
+        HeaderCase::Camel => header_words(header)
+            .iter()
+            .enumerate()
+            .map(|(i, word)| {
+                let word = word.to_lowercase();
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) if i > 0 => first.to_uppercase().chain(chars).collect(),
+                    _ => word,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Split a header into words at spaces, `-`, `_` and lower-to-upper case changes.
+fn header_words(header: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut after_lower = false;
+    for c in header.trim().chars() {
+        if c.is_whitespace() || c == '-' || c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            after_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && after_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        after_lower = c.is_lowercase() || c.is_numeric();
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Transcode legacy single-byte input to UTF-8 before it reaches the CSV parser. WHATWG treats
+/// latin1 as windows-1252, which only differs in the otherwise unused 0x80-0x9F range.
+fn decode_input<'a>(
+    mut reader: impl Read + 'a,
+    encoding: InputEncoding,
+) -> Result<Box<dyn Read + 'a>> {
+    if encoding == InputEncoding::Utf8 {
+        return Ok(Box::new(reader));
+    }
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let (decoded, _, _) = encoding_rs::WINDOWS_1252.decode(&buf);
+    Ok(Box::new(Cursor::new(decoded.into_owned().into_bytes())))
+}
+
+/// A row skipped by `OnError::Skip`: its line number, byte offset and the parse error.
+struct BadRow {
+    line: u64,
+    byte: u64,
+    error: String,
+}
+
+/// Collect records, failing on the first malformed one or, with `OnError::Skip`, reporting
+/// each bad line to stderr (unless `opts.quiet`) and carrying on.
+fn read_records<R: Read>(
+    reader: &mut csv::Reader<R>,
+    opts: &CsvOpts,
+) -> Result<(Vec<StringRecord>, Vec<BadRow>)> {
+    let mut records = Vec::new();
+    let mut bad_rows = Vec::new();
+    for record in reader.records() {
+        check_interrupted()?;
+        match record {
+            Ok(record) => {
+                records.push(record);
+                if let Some(path) = &opts.progress_file {
+                    if (records.len() as u64).is_multiple_of(opts.progress_every) {
+                        write_progress(path, records.len())?;
+                    }
+                }
+            }
+            Err(e) if opts.on_error == OnError::Skip => {
+                let (line, byte) = e.position().map_or((0, 0), |pos| (pos.line(), pos.byte()));
+                if !opts.quiet {
+                    eprintln!("skipping line {}: {}", line, e);
+                }
+                bad_rows.push(BadRow {
+                    line,
+                    byte,
+                    error: e.to_string(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    if !bad_rows.is_empty() && !opts.quiet {
+        eprintln!("skipped {} malformed row(s)", bad_rows.len());
+    }
+    if let Some(path) = &opts.progress_file {
+        write_progress(path, records.len())?;
+    }
+    Ok((records, bad_rows))
+}
+
+fn write_progress(path: &Path, rows_done: usize) -> Result<()> {
+    let progress = serde_json::json!({ "rows_done": rows_done });
+    write_atomic(path, progress.to_string().as_bytes())
+}
+
+/// Render skipped rows as `line,error,raw` csv, quoting the first line of each bad record.
+fn error_report(raw: &[u8], bad_rows: &[BadRow]) -> Result<Vec<u8>> {
+    let mut writer = WriterBuilder::new().from_writer(Vec::new());
+    writer.write_record(["line", "error", "raw"])?;
+    for row in bad_rows {
+        let rest = raw.get(row.byte as usize..).unwrap_or_default();
+        let line = rest.split(|&b| b == b'\n').next().unwrap_or_default();
+        let line = String::from_utf8_lossy(line.strip_suffix(b"\r").unwrap_or(line));
+        writer.write_record([row.line.to_string().as_str(), &row.error, &line])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+/// One `[section]` per row, named by `column`, with the other cells as `key=value` lines.
+fn to_ini(headers: &StringRecord, records: &[StringRecord], column: &str) -> Result<String> {
+    let index = headers
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| anyhow::anyhow!("--section-by: column {} not found", column))?;
+    let mut ini = String::new();
+    for (i, record) in records.iter().enumerate() {
+        if i > 0 {
+            ini.push('\n');
+        }
+        ini.push_str(&format!(
+            "[{}]\n",
+            escape_ini(record.get(index).unwrap_or_default())
+        ));
+        for (j, (header, cell)) in headers.iter().zip(record.iter()).enumerate() {
+            if j != index {
+                ini.push_str(&format!("{}={}\n", header, escape_ini(cell)));
+            }
+        }
+    }
+    Ok(ini)
+}
+
+fn escape_ini(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Column names from a `--columns-file`: one per line, skipping blank lines and `#` comments.
+fn read_column_list(path: &str) -> Result<Vec<String>> {
+    let columns = fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        anyhow::bail!("--columns-file {} lists no columns", path);
+    }
+    Ok(columns)
+}
+
+/// Keep only `columns`, in the given order.
+fn select_columns(
+    headers: &StringRecord,
+    records: &[StringRecord],
+    columns: &[String],
+) -> Result<(StringRecord, Vec<StringRecord>)> {
+    let indices = columns
+        .iter()
+        .map(|column| {
+            headers
+                .iter()
+                .position(|header| header == column)
+                .ok_or_else(|| anyhow::anyhow!("column {} not found", column))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let select = |record: &StringRecord| {
+        indices
+            .iter()
+            .map(|&i| record.get(i).unwrap_or_default())
+            .collect::<StringRecord>()
     };
+    Ok((select(headers), records.iter().map(select).collect()))
+}
+
+/// `front` followed by every other header in its original order.
+fn reorder_columns(headers: &StringRecord, front: &[String]) -> Result<Vec<String>> {
+    for (i, column) in front.iter().enumerate() {
+        if front[..i].contains(column) {
+            anyhow::bail!("column {} is listed twice in --reorder", column);
+        }
+    }
+    let rest = headers
+        .iter()
+        .filter(|header| !front.iter().any(|column| column == header))
+        .map(String::from);
+    Ok(front.iter().cloned().chain(rest).collect())
+}
+
+/// Emit one record per `sub_delimiter`-separated value of `column`, copying the other cells.
+fn explode(
+    headers: &StringRecord,
+    records: Vec<StringRecord>,
+    column: &str,
+    sub_delimiter: char,
+) -> Result<Vec<StringRecord>> {
+    let index = headers
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| anyhow::anyhow!("column {} not found", column))?;
+    let mut exploded = Vec::with_capacity(records.len());
+    for record in records {
+        let cell = record.get(index).unwrap_or_default();
+        for value in cell.split(sub_delimiter) {
+            let row = record
+                .iter()
+                .enumerate()
+                .map(|(i, field)| if i == index { value } else { field })
+                .collect::<StringRecord>();
+            exploded.push(row);
+        }
+    }
+    Ok(exploded)
+}
+
+pub fn process_csv_value_counts(
+    reader: impl Read,
+    column: &str,
+    delimiter: u8,
+) -> RcliResult<HashMap<String, usize>> {
+    let mut reader = BufReader::new(reader);
+    skip_bom(&mut reader)?;
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let index = reader
+        .headers()?
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| anyhow::anyhow!("column {} not found", column))?;
+    let mut counts = HashMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let value = record.get(index).unwrap_or_default();
+        *counts.entry(value.to_string()).or_insert(0) += 1;
+    }
+    Ok(counts)
+}
+
+/// How well one candidate delimiter splits the sampled lines, reported by `--detect`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelimiterScore {
+    pub delimiter: u8,
+    pub columns: usize,
+    /// Fraction of sampled rows that have `columns` fields; 0 when the delimiter never splits.
+    pub score: f64,
+}
+
+impl fmt::Display for DelimiterScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<4} columns={:<3} score={:.2}",
+            (self.delimiter as char).escape_default().to_string(),
+            self.columns,
+            self.score
+        )
+    }
+}
+
+/// Try each common delimiter on the first `lines` lines and rank them, best first.
+pub fn detect_delimiter_report(reader: impl Read, lines: usize) -> Result<Vec<DelimiterScore>> {
+    let mut reader = BufReader::new(reader);
+    skip_bom(&mut reader)?;
+    let mut sample = Vec::new();
+    for line in std::io::BufRead::split(reader, b'\n').take(lines) {
+        sample.extend(line?);
+        sample.push(b'\n');
+    }
+    let mut scores = [b',', b';', b'\t', b'|']
+        .into_iter()
+        .map(|delimiter| score_delimiter(&sample, delimiter))
+        .collect::<Result<Vec<_>>>()?;
+    scores.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.columns.cmp(&a.columns))
+    });
+    Ok(scores)
+}
+
+fn score_delimiter(sample: &[u8], delimiter: u8) -> Result<DelimiterScore> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(sample);
+    let mut widths: HashMap<usize, usize> = HashMap::new();
+    let mut rows = 0;
+    for record in reader.records() {
+        // a quote left open by the wrong delimiter ends the sample rather than the scan
+        let Some(record) = record.ok() else { break };
+        *widths.entry(record.len()).or_insert(0) += 1;
+        rows += 1;
+    }
+    let (columns, count) = widths
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)))
+        .unwrap_or((0, 0));
+    let score = if columns > 1 {
+        count as f64 / rows as f64
+    } else {
+        0.0
+    };
+    Ok(DelimiterScore {
+        delimiter,
+        columns,
+        score,
+    })
+}
+
+/// Serialize value counts as a JSON object ordered by descending count.
+pub fn format_value_counts(counts: &HashMap<String, usize>) -> Result<String> {
+    let mut counts = counts.iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    Ok(serde_json::to_string_pretty(&SortedCounts(counts))?)
+}
+
+struct SortedCounts<'a>(Vec<(&'a String, &'a usize)>);
+
+impl Serialize for SortedCounts<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (value, count) in &self.0 {
+            map.serialize_entry(value, count)?;
+        }
+        map.end()
+    }
+}
+
+fn to_values(
+    headers: &StringRecord,
+    types: &[ColumnType],
+    records: &[StringRecord],
+    opts: &CsvOpts,
+) -> Vec<Value> {
+    records
+        .iter()
+        .map(|record| to_row(headers, types, record, opts))
+        .collect()
+}
+
+/// Rows as one object keyed by the `column` value of each row, in input order.
+fn to_keyed(
+    headers: &StringRecord,
+    types: &[ColumnType],
+    records: &[StringRecord],
+    column: &str,
+    opts: &CsvOpts,
+) -> Result<Value> {
+    let index = headers
+        .iter()
+        .position(|header| header == column)
+        .ok_or_else(|| anyhow::anyhow!("--key-by: column {} not found", column))?;
+    let mut keyed = Map::new();
+    for record in records {
+        let key = record.get(index).unwrap_or_default().to_string();
+        let row = to_row(headers, types, record, opts);
+        if keyed.insert(key.clone(), row).is_some() && !opts.allow_dup_keys {
+            anyhow::bail!(
+                "--key-by: duplicate key {} (use --allow-dup-keys to keep the last row)",
+                key
+            );
+        }
+    }
+    Ok(Value::Object(keyed))
+}
+
+/// Build a row as a JSON object whose keys keep the header order (serde_json `preserve_order`).
+/// Cells are strings unless `types` (from `--schema`, already checked) says otherwise.
+fn to_row(
+    headers: &StringRecord,
+    types: &[ColumnType],
+    record: &StringRecord,
+    opts: &CsvOpts,
+) -> Value {
+    let row = headers
+        .iter()
+        .zip(record.iter())
+        .enumerate()
+        .map(|(i, (header, cell))| {
+            let value = match (is_null(cell, opts), types.get(i)) {
+                (true, _) => Value::Null,
+                (false, Some(ty)) => ty
+                    .parse(cell)
+                    .unwrap_or_else(|| Value::String(cell.to_string())),
+                (false, None) if opts.parse_json_cells => json_cell(cell),
+                (false, None) => Value::String(cell.to_string()),
+            };
+            (header.to_string(), value)
+        })
+        .collect::<Map<String, Value>>();
+    Value::Object(row)
+}
+
+/// A cell holding a JSON object or array becomes that value; anything else stays a string,
+/// so `--parse-json-cells` never turns `1` or `true` into a number or bool.
+fn json_cell(cell: &str) -> Value {
+    match serde_json::from_str(cell) {
+        Ok(value @ (Value::Object(_) | Value::Array(_))) => value,
+        _ => Value::String(cell.to_string()),
+    }
+}
+
+fn is_null(cell: &str, opts: &CsvOpts) -> bool {
+    (opts.empty_as_null && cell.is_empty()) || opts.null_value.iter().any(|v| v == cell)
+}
 
-    fs::write(output, content)?;
+/// A `--schema` file: the expected columns, in order, with their types.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SchemaColumn {
+    name: String,
+    #[serde(rename = "type")]
+    ty: ColumnType,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ColumnType {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+impl ColumnType {
+    fn parse(self, cell: &str) -> Option<Value> {
+        match self {
+            ColumnType::String => Some(Value::String(cell.to_string())),
+            ColumnType::Int => cell.parse::<i64>().ok().map(Value::from),
+            ColumnType::Float => cell
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number),
+            ColumnType::Bool => cell.parse::<bool>().ok().map(Value::Bool),
+        }
+    }
+}
+
+fn load_schema(path: &str) -> Result<Vec<SchemaColumn>> {
+    let schema = fs::read_to_string(path)?;
+    serde_json::from_str(&schema).map_err(|e| anyhow::anyhow!("invalid schema {}: {}", path, e))
+}
+
+/// Check the header and every non-null cell against `schema`, returning the column types.
+fn check_schema(
+    headers: &StringRecord,
+    records: &[StringRecord],
+    schema: &[SchemaColumn],
+    opts: &CsvOpts,
+) -> Result<Vec<ColumnType>> {
+    let expected = schema
+        .iter()
+        .map(|col| col.name.as_str())
+        .collect::<Vec<_>>();
+    if headers.iter().ne(expected.iter().copied()) {
+        anyhow::bail!(
+            "columns {} don't match the schema's {}",
+            headers.iter().collect::<Vec<_>>().join(","),
+            expected.join(",")
+        );
+    }
+    for (row, record) in records.iter().enumerate() {
+        for (col, cell) in schema.iter().zip(record.iter()) {
+            if !is_null(cell, opts) && col.ty.parse(cell).is_none() {
+                anyhow::bail!(
+                    "row {}, column {}: {:?} is not a valid {}",
+                    row + 1,
+                    col.name,
+                    cell,
+                    format!("{:?}", col.ty).to_lowercase()
+                );
+            }
+        }
+    }
+    Ok(schema.iter().map(|col| col.ty).collect())
+}
+
+/// Pretty-print rows as a JSON array, serializing rows in parallel with the `rayon` feature.
+fn to_json(
+    headers: &StringRecord,
+    types: &[ColumnType],
+    records: &[StringRecord],
+    opts: &CsvOpts,
+) -> Result<String> {
+    if records.is_empty() {
+        return Ok("[]".to_string());
+    }
+    #[cfg(feature = "rayon")]
+    let rows = records
+        .par_iter()
+        .map(|record| row_to_json(headers, types, record, opts))
+        .collect::<Result<Vec<_>>>()?;
+    #[cfg(not(feature = "rayon"))]
+    let rows = records
+        .iter()
+        .map(|record| row_to_json(headers, types, record, opts))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!("[\n{}\n]", rows.join(",\n")))
+}
+
+/// One compact JSON object per line.
+fn write_ndjson(
+    out: &mut dyn Write,
+    headers: &StringRecord,
+    types: &[ColumnType],
+    records: &[StringRecord],
+    opts: &CsvOpts,
+) -> Result<()> {
+    for record in records {
+        serde_json::to_writer(&mut *out, &to_row(headers, types, record, opts))?;
+        out.write_all(b"\n")?;
+    }
     Ok(())
 }
+
+fn row_to_json(
+    headers: &StringRecord,
+    types: &[ColumnType],
+    record: &StringRecord,
+    opts: &CsvOpts,
+) -> Result<String> {
+    let row = to_row(headers, types, record, opts);
+    let json = serde_json::to_string_pretty(&row)?;
+    let json = json
+        .lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(json)
+}
+
+/// The reverse of json output: a JSON array of flat objects back to csv. Columns follow the
+/// order keys are first seen in; nulls and missing keys become empty cells.
+pub fn process_json_to_csv(input: &[u8]) -> RcliResult<Vec<u8>> {
+    let rows: Vec<Map<String, Value>> = serde_json::from_slice(input)
+        .map_err(|e| anyhow::anyhow!("expected a JSON array of objects: {}", e))?;
+    let mut headers = StringRecord::new();
+    for key in rows.iter().flat_map(|row| row.keys()) {
+        if !headers.iter().any(|header| header == key) {
+            headers.push_field(key);
+        }
+    }
+    let records = rows
+        .iter()
+        .map(|row| {
+            headers
+                .iter()
+                .map(|header| match row.get(header) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => s.clone(),
+                    Some(value) => value.to_string(),
+                })
+                .collect::<StringRecord>()
+        })
+        .collect::<Vec<_>>();
+    Ok(to_csv(&headers, &records, b',', CsvQuoteStyle::Necessary, true)?.into_bytes())
+}
+
+/// Rewrite with minimal quoting and `\n` line endings, so CSV-to-CSV normalizes a file for diffing.
+fn to_csv(
+    headers: &StringRecord,
+    records: &[StringRecord],
+    delimiter: u8,
+    quote: CsvQuoteStyle,
+    header: bool,
+) -> Result<String> {
+    if quote == CsvQuoteStyle::Never {
+        // unquoted, these fields would split or run into the next row when read back
+        let needs_quotes = |field: &str| {
+            field
+                .bytes()
+                .any(|b| b == delimiter || matches!(b, b'"' | b'\n' | b'\r'))
+        };
+        let rows = header.then_some(headers).into_iter().chain(records);
+        for (row, record) in rows.enumerate() {
+            if let Some(field) = record.iter().find(|field| needs_quotes(field)) {
+                anyhow::bail!(
+                    "--quote-style never: row {} field {:?} would need quoting",
+                    row + 1,
+                    field
+                );
+            }
+        }
+    }
+    let quote = match quote {
+        CsvQuoteStyle::Necessary => QuoteStyle::Necessary,
+        CsvQuoteStyle::Always => QuoteStyle::Always,
+        CsvQuoteStyle::Never => QuoteStyle::Never,
+        CsvQuoteStyle::NonNumeric => QuoteStyle::NonNumeric,
+    };
+    let mut buf = Vec::new();
+    {
+        let mut writer = WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(quote)
+            .terminator(Terminator::Any(b'\n'))
+            .from_writer(&mut buf);
+        if header {
+            writer.write_record(headers)?;
+        }
+        for record in records {
+            writer.write_record(record)?;
+        }
+        writer.flush()?;
+    }
+    Ok(String::from_utf8(buf)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_process_csv_change_delimiter() -> Result<()> {
+        let output =
+            std::env::temp_dir().join(format!("rcli_semicolon_{}.tsv", std::process::id()));
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/semicolon.csv",
+            "--format",
+            "csv",
+            "--delimiter",
+            ";",
+            "--output-delimiter",
+            "\\t",
+        ])?;
+        process_csv(&opts, output.display().to_string())?;
+
+        let content = std::fs::read_to_string(&output)?;
+        assert_eq!(
+            content,
+            "name\tnote\nalice\thello; world\nbob\t\"tab\there\"\n"
+        );
+
+        let mut original = ReaderBuilder::new()
+            .delimiter(b';')
+            .from_path("fixtures/semicolon.csv")?;
+        let mut converted = ReaderBuilder::new()
+            .delimiter(b'\t')
+            .from_reader(content.as_bytes());
+        let original = original.records().collect::<Result<Vec<_>, _>>()?;
+        let converted = converted.records().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(original, converted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_json_matches_sequential() -> Result<()> {
+        let headers = StringRecord::from(vec!["id", "name", "note"]);
+        let records = (0..10_000)
+            .map(|i| {
+                let id = i.to_string();
+                let name = format!("name {}", i);
+                StringRecord::from(vec![id.as_str(), name.as_str(), "a \"quoted\"\nline"])
+            })
+            .collect::<Vec<_>>();
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+        let expected = serde_json::to_string_pretty(&to_values(&headers, &[], &records, &opts))?;
+        assert_eq!(to_json(&headers, &[], &records, &opts)?, expected);
+        assert_eq!(to_json(&headers, &[], &[], &opts)?, "[]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_progress_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rcli_progress_{}.json", std::process::id()));
+        let path_str = path.to_str().expect("temp path is utf8");
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "-",
+            "--progress-file",
+            path_str,
+            "--progress-every",
+            "2",
+        ])?;
+        let input = "id\n1\n2\n3\n";
+        process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let progress: Value = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        fs::remove_file(&path)?;
+        assert_eq!(progress, serde_json::json!({ "rows_done": 3 }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_ini() -> Result<()> {
+        let input = fs::read("fixtures/hosts.csv")?;
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--section-by", "host"])?;
+        let ini = process_csv_reader(input.as_slice(), OutputFormat::Ini, &opts)?;
+        assert_eq!(
+            ini,
+            "[web]\nport=80\nnote=front\\nend\n\n[db]\nport=5432\nnote=primary\n"
+        );
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+        assert!(process_csv_reader(input.as_slice(), OutputFormat::Ini, &opts).is_err());
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--section-by", "name"])?;
+        assert!(process_csv_reader(input.as_slice(), OutputFormat::Ini, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_columns_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rcli_columns_{}.txt", std::process::id()));
+        fs::write(&path, "# wanted\n name \n\nid\n")?;
+        let path_str = path.to_str().expect("temp path is utf8");
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--columns-file", path_str])?;
+        let input = "id,score,name\n1,9.5,alice\n";
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts);
+        fs::remove_file(&path)?;
+        let rows: Value = serde_json::from_str(&json?)?;
+        assert_eq!(rows, serde_json::json!([{"name": "alice", "id": "1"}]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_columns() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--columns", "name,id"])?;
+        let input = "id,score,name\n1,9.5,alice\n";
+        let csv = process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts)?;
+        assert_eq!(csv, "name,id\nalice,1\n");
+
+        let ret = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "-",
+            "--columns",
+            "id",
+            "--columns-file",
+            "fixtures/hosts.csv",
+        ]);
+        assert!(ret.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_reorder() -> Result<()> {
+        let input = "score,name,city,id\n9.5,alice,paris,1\n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--reorder", "id,name"])?;
+        let csv = process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts)?;
+        assert_eq!(csv, "id,name,score,city\n1,alice,9.5,paris\n");
+
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "-",
+            "--reorder",
+            "id",
+            "--rename",
+            "id=user_id",
+        ])?;
+        let csv = process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts)?;
+        assert_eq!(csv, "user_id,score,name,city\n1,9.5,alice,paris\n");
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--reorder", "id,missing"])?;
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts).is_err());
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--reorder", "id,id"])?;
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_flexible() -> Result<()> {
+        let input = "a,b,c\n1,2\n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts).is_err());
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--flexible"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows, serde_json::json!([{"a": "1", "b": "2", "c": ""}]));
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--flexible", "--empty-as-null"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows, serde_json::json!([{"a": "1", "b": "2", "c": null}]));
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--flexible"])?;
+        let long = "a,b\n1,2\n1,2,3\n";
+        let err = process_csv_reader(long.as_bytes(), OutputFormat::Json, &opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "row 2: 3 fields, but the header only has 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_schema() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--schema", "fixtures/schema.json"])?;
+        let input = "id,score,active,name\n1,9.5,true,alice\n2,7,false,bob\n";
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([
+                {"id": 1, "score": 9.5, "active": true, "name": "alice"},
+                {"id": 2, "score": 7.0, "active": false, "name": "bob"}
+            ])
+        );
+
+        let bad = "id,score,active,name\n1,9.5,true,alice\ntwo,7,false,bob\n";
+        let err = process_csv_reader(bad.as_bytes(), OutputFormat::Json, &opts).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"row 2, column id: "two" is not a valid int"#
+        );
+
+        let missing = "id,score,name\n1,9.5,alice\n";
+        assert!(process_csv_reader(missing.as_bytes(), OutputFormat::Json, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_null_value() -> Result<()> {
+        let input = "x,y,z\na,,NA\n";
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "-",
+            "--empty-as-null",
+            "--null-value",
+            "NA",
+            "--null-value",
+            "NULL",
+        ])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows, serde_json::json!([{"x": "a", "y": null, "z": null}]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_parse_json_cells() -> Result<()> {
+        let input = "id,meta\n1,\"{\"\"a\"\":1}\"\n2,{not json\n3,42\n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--parse-json-cells"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([
+                {"id": "1", "meta": {"a": 1}},
+                {"id": "2", "meta": "{not json"},
+                {"id": "3", "meta": "42"},
+            ])
+        );
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows[0]["meta"], "{\"a\":1}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_strips_bom() -> Result<()> {
+        let input = File::open("fixtures/bom.csv")?;
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "fixtures/bom.csv"])?;
+        let json = process_csv_reader(input, OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows[0], serde_json::json!({"name": "alice", "age": "30"}));
+
+        let input = File::open("fixtures/bom.csv")?;
+        assert_eq!(process_csv_value_counts(input, "name", b',')?["bob"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_latin1() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "fixtures/latin1.csv"])?;
+        let input = File::open("fixtures/latin1.csv")?;
+        assert!(process_csv_reader(input, OutputFormat::Json, &opts).is_err());
+
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/latin1.csv",
+            "--input-encoding",
+            "latin1",
+        ])?;
+        let input = File::open("fixtures/latin1.csv")?;
+        let json = process_csv_reader(input, OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows[0],
+            serde_json::json!({"name": "José", "city": "München"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_normalize() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "fixtures/messy.csv"])?;
+        let input = File::open("fixtures/messy.csv")?;
+        let normalized = process_csv_reader(input, OutputFormat::Csv, &opts)?;
+        assert_eq!(normalized, "id,name\n1,alice\n2,bob\n3,\"has, comma\"\n");
+
+        let records = |content: &[u8]| -> Result<Vec<StringRecord>> {
+            let mut reader = ReaderBuilder::new().from_reader(content);
+            Ok(reader.records().collect::<Result<_, _>>()?)
+        };
+        let original = fs::read("fixtures/messy.csv")?;
+        assert_eq!(records(normalized.as_bytes())?, records(&original)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_rename() -> Result<()> {
+        let input = "first_name,last_name,age\nada,lovelace,36\n";
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "-",
+            "--rename",
+            "first_name=firstName",
+            "--rename",
+            "last_name=lastName",
+        ])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows[0],
+            serde_json::json!({"firstName": "ada", "lastName": "lovelace", "age": "36"})
+        );
+
+        // --columns selects by the original names, before the renames apply
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "-",
+            "--columns",
+            "age,first_name",
+            "--rename",
+            "first_name=firstName",
+        ])?;
+        let csv = process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts)?;
+        assert_eq!(csv, "age,firstName\n36,ada\n");
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--rename", "middle=m"])?;
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_case() {
+        assert_eq!(convert_case("First Name", HeaderCase::Snake), "first_name");
+        assert_eq!(convert_case("last-name", HeaderCase::Snake), "last_name");
+        assert_eq!(convert_case("zipCode", HeaderCase::Snake), "zip_code");
+        assert_eq!(convert_case("First Name", HeaderCase::Camel), "firstName");
+        assert_eq!(convert_case("last_name", HeaderCase::Camel), "lastName");
+        assert_eq!(convert_case("ID", HeaderCase::Camel), "id");
+        assert_eq!(convert_case("First Name", HeaderCase::Lower), "first name");
+    }
+
+    #[test]
+    fn test_process_csv_header_case_collision() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--header-case", "snake"])?;
+        let input = "First Name,first-name\nada,ada\n";
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts).is_err());
+        let json = process_csv_reader(
+            "First Name,Age\nada,36\n".as_bytes(),
+            OutputFormat::Json,
+            &opts,
+        )?;
+        let rows: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows[0],
+            serde_json::json!({"first_name": "ada", "age": "36"})
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_key_by() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--key-by", "id"])?;
+        let input = "id,name\n1,alice\n2,bob\n";
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let keyed: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            keyed,
+            serde_json::json!({
+                "1": {"id": "1", "name": "alice"},
+                "2": {"id": "2", "name": "bob"},
+            })
+        );
+
+        let dup = "id,name\n1,alice\n1,bob\n";
+        assert!(process_csv_reader(dup.as_bytes(), OutputFormat::Json, &opts).is_err());
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Csv, &opts).is_err());
+        let opts =
+            CsvOpts::try_parse_from(["csv", "-i", "-", "--key-by", "id", "--allow-dup-keys"])?;
+        let json = process_csv_reader(dup.as_bytes(), OutputFormat::Json, &opts)?;
+        let keyed: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(keyed["1"]["name"], "bob");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_append() -> Result<()> {
+        let output = std::env::temp_dir().join(format!("rcli_append_{}.csv", std::process::id()));
+        let _ = fs::remove_file(&output);
+        let output = output.display().to_string();
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/status.csv",
+            "-o",
+            &output,
+            "--append",
+        ])?;
+        process_csv(&opts, output.clone())?;
+        process_csv(&opts, output.clone())?;
+        let content = fs::read_to_string(&output)?;
+        fs::remove_file(&output)?;
+        assert_eq!(content.lines().count(), 1 + 6 + 6);
+        assert_eq!(
+            content.lines().filter(|line| *line == "id,status").count(),
+            1
+        );
+
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/status.csv",
+            "--append",
+            "--format",
+            "json",
+        ])?;
+        assert!(process_csv(&opts, "unused.json".into()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_value_counts() -> Result<()> {
+        let input = File::open("fixtures/status.csv")?;
+        let counts = process_csv_value_counts(input, "status", b',')?;
+        assert_eq!(counts["done"], 3);
+        assert_eq!(counts["open"], 2);
+        assert_eq!(counts["blocked"], 1);
+
+        let json = format_value_counts(&counts)?;
+        assert_eq!(
+            json,
+            "{\n  \"done\": 3,\n  \"open\": 2,\n  \"blocked\": 1\n}"
+        );
+
+        let input = File::open("fixtures/status.csv")?;
+        assert!(process_csv_value_counts(input, "missing", b',').is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_delimiter_report() -> Result<()> {
+        let input = File::open("fixtures/semicolon.csv")?;
+        let scores = detect_delimiter_report(input, 20)?;
+        assert_eq!(scores.len(), 4);
+        assert_eq!(scores[0].delimiter, b';');
+        assert_eq!(scores[0].columns, 2);
+        assert_eq!(scores[0].score, 1.0);
+        assert!(scores[1..].iter().all(|s| s.score < 1.0 || s.columns < 2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_dir() -> Result<()> {
+        let output_dir = std::env::temp_dir().join(format!("rcli_csvdir_{}", std::process::id()));
+        let output = output_dir.display().to_string();
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/csvdir",
+            "--recursive",
+            "--output-dir",
+            &output,
+            "--format",
+            "yaml",
+        ])?;
+        assert_eq!(
+            process_csv_dir(&opts, &output_dir, &mut BatchErrors::new(false))?,
+            2
+        );
+        assert!(output_dir.join("a.yaml").is_file());
+        assert!(output_dir.join("nested/b.yaml").is_file());
+        assert!(!output_dir.join("notes.yaml").exists());
+        fs::remove_dir_all(&output_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_dir_keep_going() -> Result<()> {
+        let base = std::env::temp_dir().join(format!("rcli_keepgoing_{}", std::process::id()));
+        let (input_dir, output_dir) = (base.join("in"), base.join("out"));
+        fs::create_dir_all(&input_dir)?;
+        fs::copy("fixtures/ragged.csv", input_dir.join("a.csv"))?;
+        fs::copy("fixtures/status.csv", input_dir.join("b.csv"))?;
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            &input_dir.display().to_string(),
+            "--recursive",
+            "--output-dir",
+            &output_dir.display().to_string(),
+        ])?;
+
+        assert!(process_csv_dir(&opts, &output_dir, &mut BatchErrors::new(false)).is_err());
+        assert!(!output_dir.join("b.json").exists());
+
+        let mut errors = BatchErrors::new(true);
+        assert_eq!(process_csv_dir(&opts, &output_dir, &mut errors)?, 1);
+        assert!(output_dir.join("b.json").is_file());
+        assert_eq!(errors.len(), 1);
+        assert!(errors.finish().is_err());
+        fs::remove_dir_all(&base)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_dedupe_keys() -> Result<()> {
+        let path = "fixtures/dup_headers.csv";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", path])?;
+        let err = process_csv_reader(File::open(path)?, OutputFormat::Json, &opts).unwrap_err();
+        assert!(err.to_string().contains("duplicate column phone"));
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", path, "--dedupe-keys", "suffix"])?;
+        let json = process_csv_reader(File::open(path)?, OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([{
+                "name": "alice",
+                "phone": "111",
+                "email": "a@example.com",
+                "phone_2": "222",
+                "phone_3": "333",
+            }])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_quote_style() -> Result<()> {
+        let csv = |style: &str| -> Result<String> {
+            let opts = CsvOpts::try_parse_from([
+                "csv",
+                "-i",
+                "fixtures/mixed.csv",
+                "--quote-style",
+                style,
+            ])?;
+            Ok(process_csv_reader(
+                File::open("fixtures/mixed.csv")?,
+                OutputFormat::Csv,
+                &opts,
+            )?)
+        };
+        assert_eq!(
+            csv("always")?,
+            "\"name\",\"age\",\"score\",\"city\"\n\
+             \"alice\",\"30\",\"9.5\",\"New York, NY\"\n\
+             \"bob\",\"25\",\"-3\",\"Paris\"\n"
+        );
+        assert_eq!(
+            csv("non-numeric")?,
+            "\"name\",\"age\",\"score\",\"city\"\n\
+             \"alice\",30,9.5,\"New York, NY\"\n\
+             \"bob\",25,-3,\"Paris\"\n"
+        );
+        let err = csv("never").unwrap_err();
+        assert!(err.to_string().contains("New York, NY"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_split_rows() -> Result<()> {
+        let base = std::env::temp_dir().join(format!("rcli_split_{}", std::process::id()));
+        fs::create_dir_all(&base)?;
+        let input = base.join("in.csv");
+        let rows = (0..25)
+            .map(|i| format!("{},\"line\nbreak\"\n", i))
+            .collect::<String>();
+        fs::write(&input, format!("id,note\n{}", rows))?;
+        let output = base.join("out").display().to_string();
+        for format in ["ndjson", "csv"] {
+            let opts = CsvOpts::try_parse_from([
+                "csv",
+                "-i",
+                &input.display().to_string(),
+                "--format",
+                format,
+                "-o",
+                &output,
+                "--split-rows",
+                "10",
+            ])?;
+            let (parts, stats) = process_csv_split(&opts, &output, 10)?;
+            assert_eq!((parts, stats.rows), (3, 25));
+            let mut counts = Vec::new();
+            for n in 0..3 {
+                let part = fs::read(format!("{}.{}.{}", output, n, format))?;
+                let count = match format {
+                    "ndjson" => part
+                        .split(|&b| b == b'\n')
+                        .filter(|l| !l.is_empty())
+                        .count(),
+                    _ => ReaderBuilder::new()
+                        .from_reader(part.as_slice())
+                        .records()
+                        .count(),
+                };
+                counts.push(count);
+            }
+            assert_eq!(counts, [10, 10, 5]);
+            assert!(!Path::new(&format!("{}.3.{}", output, format)).exists());
+        }
+
+        // a matching extension on --output isn't repeated in the part names
+        let named = base.join("named.csv").display().to_string();
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            &input.display().to_string(),
+            "-o",
+            &named,
+            "--split-rows",
+            "10",
+        ])?;
+        assert_eq!(process_csv_split(&opts, &named, 10)?.0, 3);
+        assert!(base.join("named.0.csv").is_file());
+        assert!(!base.join("named.csv.0.csv").exists());
+
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            &input.display().to_string(),
+            "--format",
+            "json",
+            "-o",
+            &output,
+            "--split-rows",
+            "10",
+        ])?;
+        assert!(process_csv_split(&opts, &output, 10).is_err());
+        fs::remove_dir_all(&base)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_keeps_header_order() -> Result<()> {
+        let input = "zeta,alpha,mid\n1,2,3\n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+        for format in [OutputFormat::Json, OutputFormat::Yaml] {
+            let content = process_csv_reader(input.as_bytes(), format, &opts)?;
+            let positions = ["zeta", "alpha", "mid"]
+                .iter()
+                .map(|key| content.find(key).unwrap())
+                .collect::<Vec<_>>();
+            assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", content);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_comment() -> Result<()> {
+        let opts =
+            CsvOpts::try_parse_from(["csv", "-i", "fixtures/commented.csv", "--comment", "#"])?;
+        let input = File::open(&opts.input)?;
+        let json = process_csv_reader(input, OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([
+                {"name": "probe-1", "reading": "0.5"},
+                {"name": "probe-2", "reading": "0.7"}
+            ])
+        );
+        assert!(CsvOpts::try_parse_from(["csv", "-i", "-", "--comment", "//"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_on_error() -> Result<()> {
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "fixtures/ragged.csv"])?;
+        let input = File::open(&opts.input)?;
+        assert!(process_csv_reader(input, OutputFormat::Json, &opts).is_err());
+
+        let opts =
+            CsvOpts::try_parse_from(["csv", "-i", "fixtures/ragged.csv", "--on-error", "skip"])?;
+        let input = File::open(&opts.input)?;
+        let json = process_csv_reader(input, OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([{"name": "alice", "age": "30"}, {"name": "carol", "age": "41"}])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_error_report() -> Result<()> {
+        let report = std::env::temp_dir().join(format!("rcli_errors_{}.csv", std::process::id()));
+        let report_arg = report.display().to_string();
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/dirty.csv",
+            "--on-error",
+            "skip",
+            "--error-report",
+            &report_arg,
+        ])?;
+        let json = process_csv_reader(File::open(&opts.input)?, OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows.as_array().map(Vec::len), Some(3));
+
+        let mut report_reader = ReaderBuilder::new().from_path(&report)?;
+        let bad = report_reader
+            .records()
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        fs::remove_file(&report)?;
+        assert_eq!(bad.len(), 2);
+        assert_eq!((&bad[0][0], &bad[0][2]), ("3", "bob,25,extra"));
+        assert_eq!((&bad[1][0], &bad[1][2]), ("5", "dave"));
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--error-report", &report_arg])?;
+        assert!(process_csv_reader("a\n1\n".as_bytes(), OutputFormat::Json, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_msgpack() -> Result<()> {
+        let input = "name,age\nalice,30\nbob,\n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--empty-as-null"])?;
+        let bytes = process_csv_bytes(input.as_bytes(), OutputFormat::Msgpack, &opts)?;
+        let rows: Value = rmp_serde::from_slice(&bytes)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([{"name": "alice", "age": "30"}, {"name": "bob", "age": null}])
+        );
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Msgpack, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_explode() -> Result<()> {
+        let input = "id,tags,owner\n1,a;b;c,alice\n2,,bob\n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--explode", "tags"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(
+            rows,
+            serde_json::json!([
+                {"id": "1", "tags": "a", "owner": "alice"},
+                {"id": "1", "tags": "b", "owner": "alice"},
+                {"id": "1", "tags": "c", "owner": "alice"},
+                {"id": "2", "tags": "", "owner": "bob"}
+            ])
+        );
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--explode", "missing"])?;
+        assert!(process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_cbor() -> Result<()> {
+        let input = File::open("fixtures/status.csv")?;
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "fixtures/status.csv"])?;
+        let bytes = process_csv_bytes(input, OutputFormat::Cbor, &opts)?;
+        let rows: Vec<Value> = ciborium::from_reader(bytes.as_slice())?;
+
+        let input = File::open("fixtures/status.csv")?;
+        let json = process_csv_reader(input, OutputFormat::Json, &opts)?;
+        assert_eq!(rows, serde_json::from_str::<Vec<Value>>(&json)?);
+        assert_eq!(rows[3], serde_json::json!({"id": "4", "status": "blocked"}));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_ndjson_gzip() -> Result<()> {
+        use std::io::BufRead;
+
+        let opts = CsvOpts::try_parse_from([
+            "csv",
+            "-i",
+            "fixtures/status.csv",
+            "--format",
+            "ndjson",
+            "--gzip",
+        ])?;
+        let input = File::open("fixtures/status.csv")?;
+        let (bytes, stats) = process_csv_with_stats(input, OutputFormat::Ndjson, &opts)?;
+        assert_eq!(stats.bytes_out, bytes.len());
+
+        let decoder = BufReader::new(flate2::read::GzDecoder::new(bytes.as_slice()));
+        let lines = decoder.lines().collect::<std::io::Result<Vec<_>>>()?;
+        assert_eq!(lines.len(), stats.rows);
+        assert_eq!(
+            serde_json::from_str::<Value>(&lines[3])?,
+            serde_json::json!({"id": "4", "status": "blocked"})
+        );
+
+        let input = File::open("fixtures/status.csv")?;
+        assert!(process_csv_reader(input, OutputFormat::Ndjson, &opts).is_err());
+
+        // written to a file, the encoder streams into it and yields the same bytes
+        let output = std::env::temp_dir().join(format!("rcli_gzip_{}.gz", std::process::id()));
+        let file_stats = process_csv(&opts, output.display().to_string())?;
+        let written = fs::read(&output)?;
+        fs::remove_file(&output)?;
+        assert_eq!(file_stats, stats);
+        assert_eq!(written, bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_trim() -> Result<()> {
+        let input = "name,age\n alice ,30 \n";
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-", "--trim"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows, serde_json::json!([{"name": "alice", "age": "30"}]));
+
+        let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+        let json = process_csv_reader(input.as_bytes(), OutputFormat::Json, &opts)?;
+        let rows: Value = serde_json::from_str(&json)?;
+        assert_eq!(rows[0]["name"], " alice ");
+        Ok(())
+    }
+}
@@ -1,18 +1,21 @@
-use anyhow::Ok;
+use std::collections::{BTreeMap, BTreeSet};
+
 use rand::seq::SliceRandom;
 
+use crate::{GenPassFormat, RcliResult};
+
 const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
-const LOWER: &[u8] = b"abcdefjhijkmnopqrstuvwxyz";
+const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
 const NUMBER: &[u8] = b"123456789";
 const SYMBOL: &[u8] = b"!@#$%^&*_";
 
 pub fn process_genpass(
-    length: u8,
+    length: u16,
     uppercase: bool,
     lowercase: bool,
     number: bool,
     symbol: bool,
-) -> anyhow::Result<String> {
+) -> RcliResult<String> {
     let mut rng = rand::thread_rng();
     let mut password = Vec::new();
     let mut chars = Vec::new();
@@ -37,7 +40,19 @@ pub fn process_genpass(
         password.push(*SYMBOL.choose(&mut rng).expect("SYMBOL wont't be empty"));
     }
 
-    for _ in 0..(length - password.len() as u8) {
+    if chars.is_empty() {
+        return Err(anyhow::anyhow!("select at least one character class").into());
+    }
+
+    if (length as usize) < password.len() {
+        return Err(anyhow::anyhow!(
+            "length must be at least {} for the selected character classes",
+            password.len()
+        )
+        .into());
+    }
+
+    for _ in 0..(length as usize - password.len()) {
         let c = chars
             .choose(&mut rng)
             .expect("chars won't empty in this context");
@@ -50,3 +65,253 @@ pub fn process_genpass(
 
     Ok(password)
 }
+
+/// Character counts over `samples` passwords, and Pearson's chi-squared statistic against the
+/// generator's model (`alphabet_len - 1` degrees of freedom): one character drawn uniformly from
+/// each enabled class, the rest uniformly from their union.
+#[derive(Debug, Clone)]
+pub struct GenPassAudit {
+    pub counts: BTreeMap<char, u64>,
+    pub alphabet_len: usize,
+    pub chi_squared: f64,
+}
+
+pub fn process_genpass_audit(
+    samples: u32,
+    length: u16,
+    uppercase: bool,
+    lowercase: bool,
+    number: bool,
+    symbol: bool,
+) -> RcliResult<GenPassAudit> {
+    let classes = [
+        (uppercase, UPPER),
+        (lowercase, LOWER),
+        (number, NUMBER),
+        (symbol, SYMBOL),
+    ]
+    .into_iter()
+    .filter_map(|(enabled, class)| enabled.then_some(class))
+    .collect::<Vec<_>>();
+    let mut counts = BTreeMap::new();
+    for &c in classes.concat().iter() {
+        counts.insert(c as char, 0u64);
+    }
+    if counts.is_empty() {
+        return Err(anyhow::anyhow!("enable at least one character class").into());
+    }
+    for _ in 0..samples {
+        let password = process_genpass(length, uppercase, lowercase, number, symbol)?;
+        for c in password.chars() {
+            *counts.entry(c).or_default() += 1;
+        }
+    }
+    // the model assumes distinct characters, so a class constant that repeats one shows up
+    // as that character being drawn too often
+    let samples = samples as f64;
+    let fill = length as f64 - classes.len() as f64;
+    let mut chi_squared = 0.0;
+    for class in &classes {
+        let chars = class.iter().collect::<BTreeSet<_>>();
+        let expected = samples * (1.0 / chars.len() as f64 + fill / counts.len() as f64);
+        for &&c in &chars {
+            let count = counts[&(c as char)] as f64;
+            chi_squared += (count - expected).powi(2) / expected;
+        }
+    }
+    Ok(GenPassAudit {
+        alphabet_len: counts.len(),
+        counts,
+        chi_squared,
+    })
+}
+
+/// A preset for `genpass compare`.
+#[derive(Debug, Clone, Copy)]
+pub struct GenPassPolicy {
+    pub name: &'static str,
+    pub length: u16,
+    pub uppercase: bool,
+    pub lowercase: bool,
+    pub number: bool,
+    pub symbol: bool,
+}
+
+pub const GENPASS_POLICIES: &[GenPassPolicy] = &[
+    GenPassPolicy {
+        name: "alnum-12",
+        length: 12,
+        uppercase: true,
+        lowercase: true,
+        number: true,
+        symbol: false,
+    },
+    GenPassPolicy {
+        name: "all-classes-16",
+        length: 16,
+        uppercase: true,
+        lowercase: true,
+        number: true,
+        symbol: true,
+    },
+];
+
+/// Median zxcvbn score and entropy over the samples generated for one policy.
+#[derive(Debug, Clone)]
+pub struct PolicyScore {
+    pub name: &'static str,
+    pub median_score: u8,
+    pub median_bits: f64,
+}
+
+pub fn process_genpass_compare(
+    policies: &[GenPassPolicy],
+    samples: u32,
+) -> RcliResult<Vec<PolicyScore>> {
+    let mut ret = Vec::with_capacity(policies.len());
+    for policy in policies {
+        let mut scores = Vec::with_capacity(samples as usize);
+        let mut bits = Vec::with_capacity(samples as usize);
+        for _ in 0..samples {
+            let password = process_genpass(
+                policy.length,
+                policy.uppercase,
+                policy.lowercase,
+                policy.number,
+                policy.symbol,
+            )?;
+            let estimate = zxcvbn::zxcvbn(&password, &[]).map_err(anyhow::Error::from)?;
+            scores.push(estimate.score());
+            bits.push(entropy_bits(&estimate));
+        }
+        scores.sort_unstable();
+        bits.sort_by(f64::total_cmp);
+        ret.push(PolicyScore {
+            name: policy.name,
+            median_score: scores[scores.len() / 2],
+            median_bits: bits[bits.len() / 2],
+        });
+    }
+    Ok(ret)
+}
+
+/// Estimated entropy of a password in bits, from zxcvbn's log10 guess count.
+pub fn entropy_bits(estimate: &zxcvbn::Entropy) -> f64 {
+    estimate.guesses_log10() * 10f64.log2()
+}
+
+/// Render a password for output; `score` is the zxcvbn score and `name` the `env` variable name.
+/// `bits`, from `--show-bits`, is added to the json output.
+pub fn format_genpass(
+    password: &str,
+    score: u8,
+    bits: Option<f64>,
+    format: GenPassFormat,
+    name: &str,
+) -> anyhow::Result<String> {
+    let formatted = match format {
+        GenPassFormat::Plain => password.to_string(),
+        GenPassFormat::Json => {
+            let mut json = serde_json::json!({ "password": password, "score": score });
+            if let Some(bits) = bits {
+                json["bits"] = bits.into();
+            }
+            json.to_string()
+        }
+        GenPassFormat::Env => format!("{}={}", name, shlex::try_quote(password)?),
+    };
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_genpass_max_length() -> anyhow::Result<()> {
+        let password = process_genpass(1024, true, true, true, true)?;
+        assert_eq!(password.len(), 1024);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpass_audit() -> anyhow::Result<()> {
+        let audit = process_genpass_audit(2_000, 16, true, true, true, false)?;
+        let expected = [UPPER, LOWER, NUMBER]
+            .concat()
+            .into_iter()
+            .map(char::from)
+            .collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(
+            audit
+                .counts
+                .keys()
+                .copied()
+                .collect::<std::collections::BTreeSet<_>>(),
+            expected
+        );
+        assert!(audit.counts.values().all(|&count| count > 0));
+        assert_eq!(audit.counts.values().sum::<u64>(), 2_000 * 16);
+        // well within the chi-squared distribution for an unbiased generator
+        assert!(audit.chi_squared < 2.0 * (audit.alphabet_len - 1) as f64);
+        assert!(process_genpass_audit(1, 16, false, false, false, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_character_classes_distinct() {
+        let all = [UPPER, LOWER, NUMBER, SYMBOL].concat();
+        let distinct = all.iter().collect::<BTreeSet<_>>();
+        assert_eq!(distinct.len(), all.len(), "a class repeats a character");
+    }
+
+    #[test]
+    fn test_process_genpass_compare() -> anyhow::Result<()> {
+        let scores = process_genpass_compare(GENPASS_POLICIES, 50)?;
+        let alnum = scores.iter().find(|s| s.name == "alnum-12").unwrap();
+        let all = scores.iter().find(|s| s.name == "all-classes-16").unwrap();
+        assert!(all.median_score >= alnum.median_score);
+        assert!(all.median_bits >= alnum.median_bits);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_genpass_too_short() {
+        assert!(process_genpass(3, true, true, true, true).is_err());
+    }
+
+    #[test]
+    fn test_process_genpass_no_classes() {
+        let ret = process_genpass(8, false, false, false, false);
+        assert_eq!(
+            ret.unwrap_err().to_string(),
+            "select at least one character class"
+        );
+    }
+
+    #[test]
+    fn test_entropy_bits() -> anyhow::Result<()> {
+        let short = entropy_bits(&zxcvbn::zxcvbn("kX9mQ2vR", &[])?);
+        let long = entropy_bits(&zxcvbn::zxcvbn("kX9mQ2vRp7Lw4ZtB", &[])?);
+        assert!(short > 0.0);
+        assert!(long > short);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_genpass() -> anyhow::Result<()> {
+        let plain = format_genpass("s3cret", 3, None, GenPassFormat::Plain, "PASSWORD")?;
+        assert_eq!(plain, "s3cret");
+        let json = format_genpass("s3cret", 3, None, GenPassFormat::Json, "PASSWORD")?;
+        assert_eq!(json, r#"{"password":"s3cret","score":3}"#);
+        let json = format_genpass("s3cret", 3, Some(12.5), GenPassFormat::Json, "PASSWORD")?;
+        let json: serde_json::Value = serde_json::from_str(&json)?;
+        assert_eq!(json["bits"], 12.5);
+        let env = format_genpass("s3cret", 3, None, GenPassFormat::Env, "DB_PASSWORD")?;
+        assert_eq!(env, "DB_PASSWORD=s3cret");
+        let env = format_genpass("a$b&c", 1, None, GenPassFormat::Env, "DB_PASSWORD")?;
+        let quoted = env.strip_prefix("DB_PASSWORD=").unwrap_or_default();
+        assert_eq!(shlex::split(quoted), Some(vec!["a$b&c".to_string()]));
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::{check_interrupted, copy_buffered, HashAlgo, RcliResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestStatus {
+    Ok,
+    Mismatch,
+    Missing,
+}
+
+pub fn process_hash(reader: &mut dyn Read, algo: HashAlgo) -> RcliResult<Vec<u8>> {
+    let digest = match algo {
+        HashAlgo::Sha256 => {
+            let mut hasher = Sha256::new();
+            copy_buffered(reader, &mut hasher)?;
+            hasher.finalize().to_vec()
+        }
+        HashAlgo::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            copy_buffered(reader, &mut hasher)?;
+            hasher.finalize().as_bytes().to_vec()
+        }
+    };
+    Ok(digest)
+}
+
+/// Hash the input, then re-hash the digest until `rounds` hashes have been applied in total,
+/// i.e. `H(H(...H(x)))`. A toy KDF for getting a feel for work factors, not a real one.
+pub fn process_hash_repeat(
+    reader: &mut dyn Read,
+    algo: HashAlgo,
+    rounds: u32,
+) -> RcliResult<Vec<u8>> {
+    if rounds == 0 {
+        return Err(anyhow::anyhow!("--repeat must be at least 1").into());
+    }
+    let mut digest = process_hash(reader, algo)?;
+    for _ in 1..rounds {
+        digest = process_hash(&mut digest.as_slice(), algo)?;
+    }
+    Ok(digest)
+}
+
+/// Hash every file under `dir` into `<hex>  <relpath>` lines, sorted by path.
+pub fn process_hash_manifest(dir: &Path) -> RcliResult<String> {
+    let mut manifest = String::new();
+    for (path, digest) in hash_dir(dir)? {
+        manifest.push_str(&format!("{}  {}\n", digest, path));
+    }
+    Ok(manifest)
+}
+
+/// Check the files under `dir` against a manifest produced by `process_hash_manifest`.
+pub fn process_hash_check(dir: &Path, manifest: &str) -> RcliResult<Vec<(String, ManifestStatus)>> {
+    let mut ret = Vec::new();
+    for line in manifest.lines().filter(|line| !line.trim().is_empty()) {
+        check_interrupted()?;
+        let (expected, path) = line
+            .split_once("  ")
+            .ok_or_else(|| anyhow::anyhow!("invalid manifest line: {}", line))?;
+        let status = match File::open(dir.join(path)) {
+            Ok(mut file) => {
+                let digest = hex::encode(process_hash(&mut file, HashAlgo::Sha256)?);
+                if digest.eq_ignore_ascii_case(expected) {
+                    ManifestStatus::Ok
+                } else {
+                    ManifestStatus::Mismatch
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => ManifestStatus::Missing,
+            Err(e) => return Err(e.into()),
+        };
+        ret.push((path.to_string(), status));
+    }
+    Ok(ret)
+}
+
+fn hash_dir(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut ret = BTreeMap::new();
+    for entry in WalkDir::new(dir) {
+        check_interrupted()?;
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().strip_prefix(dir)?;
+        let path = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut file = File::open(entry.path())?;
+        let digest = hex::encode(process_hash(&mut file, HashAlgo::Sha256)?);
+        ret.insert(path, digest);
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIR: &str = "fixtures/manifest";
+
+    #[test]
+    fn test_process_hash() -> Result<()> {
+        let mut reader = std::io::Cursor::new("hello world");
+        let digest = process_hash(&mut reader, HashAlgo::Sha256)?;
+        assert_eq!(
+            hex::encode(digest),
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_hash_repeat() -> Result<()> {
+        for algo in [HashAlgo::Sha256, HashAlgo::Blake3] {
+            let once = process_hash(&mut "hello world".as_bytes(), algo)?;
+            let twice = process_hash(&mut once.as_slice(), algo)?;
+            assert_eq!(
+                process_hash_repeat(&mut "hello world".as_bytes(), algo, 2)?,
+                twice
+            );
+            assert_eq!(
+                process_hash_repeat(&mut "hello world".as_bytes(), algo, 1)?,
+                once
+            );
+        }
+        assert!(process_hash_repeat(&mut "hello world".as_bytes(), HashAlgo::Sha256, 0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_hash_manifest() -> Result<()> {
+        let manifest = process_hash_manifest(Path::new(DIR))?;
+        let paths = manifest
+            .lines()
+            .map(|line| line.split_once("  ").unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["a.txt", "b.txt", "sub/c.txt"]);
+
+        let ret = process_hash_check(Path::new(DIR), &manifest)?;
+        assert!(ret.iter().all(|(_, status)| *status == ManifestStatus::Ok));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_hash_check_failures() -> Result<()> {
+        let manifest = process_hash_manifest(Path::new(DIR))?;
+        let manifest = manifest.replacen(&manifest[..8], "00000000", 1);
+        let manifest = format!("{}{}  missing.txt\n", manifest, "0".repeat(64));
+        let ret = process_hash_check(Path::new(DIR), &manifest)?;
+        assert_eq!(ret[0], ("a.txt".to_string(), ManifestStatus::Mismatch));
+        assert_eq!(ret[1].1, ManifestStatus::Ok);
+        assert_eq!(ret[3], ("missing.txt".to_string(), ManifestStatus::Missing));
+        Ok(())
+    }
+}
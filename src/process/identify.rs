@@ -0,0 +1,80 @@
+use std::{cmp::Reverse, io::Read};
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+
+use crate::RcliResult;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identification {
+    pub encoding: &'static str,
+    pub decoded_len: usize,
+    pub score: u8,
+}
+
+pub fn process_identify(reader: &mut dyn Read) -> RcliResult<Vec<Identification>> {
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    let text = std::str::from_utf8(&buf).map(str::trim);
+
+    let mut guesses = Vec::new();
+    if let Ok(text) = text {
+        if let Ok(decoded) = hex::decode(text) {
+            guesses.push(Identification {
+                encoding: "hex",
+                decoded_len: decoded.len(),
+                score: 3,
+            });
+        }
+        if let Ok(decoded) = STANDARD.decode(text) {
+            let specific = text.contains(['+', '/', '=']);
+            guesses.push(Identification {
+                encoding: "base64-standard",
+                decoded_len: decoded.len(),
+                score: 2 + specific as u8,
+            });
+        }
+        if let Ok(decoded) = URL_SAFE_NO_PAD.decode(text) {
+            let specific = text.contains(['-', '_']);
+            guesses.push(Identification {
+                encoding: "base64-urlsafe",
+                decoded_len: decoded.len(),
+                score: 2 + specific as u8,
+            });
+        }
+    }
+    guesses.push(Identification {
+        encoding: if text.is_ok() { "text" } else { "binary" },
+        decoded_len: buf.len(),
+        score: text.is_ok() as u8,
+    });
+    guesses.sort_by_key(|guess| Reverse(guess.score));
+    Ok(guesses)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_process_identify_base64() -> Result<()> {
+        let mut reader = std::io::Cursor::new("aGVsbG8gd29ybGQ=\n");
+        let guesses = process_identify(&mut reader)?;
+        assert_eq!(guesses[0].encoding, "base64-standard");
+        assert_eq!(guesses[0].decoded_len, 11);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_identify_hex() -> Result<()> {
+        let mut reader = std::io::Cursor::new("68656c6c6f");
+        let guesses = process_identify(&mut reader)?;
+        assert_eq!(guesses[0].encoding, "hex");
+        assert_eq!(guesses[0].decoded_len, 5);
+        Ok(())
+    }
+}
@@ -1,12 +1,47 @@
 mod b64;
+mod bench;
+mod compare;
+mod count;
 mod csv_process;
 mod generate_pass;
+mod hash;
+mod identify;
+mod pipe;
 mod text;
+mod version;
 
-pub use b64::{process_decode, process_encode};
-pub use csv_process::process_csv;
-pub use generate_pass::process_genpass;
+pub use b64::{
+    detect_format, format_decoded, hexdump, process_decode, process_decode_check,
+    process_decode_custom, process_decode_to_writer, process_decode_window, process_encode,
+    process_encode_custom, process_encode_stream, process_join, process_split, process_transcode,
+    CustomAlphabet,
+};
+pub use bench::process_bench;
+pub use compare::process_compare;
+pub use count::process_count;
+pub use csv_process::{
+    detect_delimiter_report, format_value_counts, process_csv, process_csv_bytes, process_csv_dir,
+    process_csv_reader, process_csv_split, process_csv_value_counts, process_csv_with_stats,
+    process_json_to_csv, CsvStats, DelimiterScore,
+};
+pub use generate_pass::{
+    entropy_bits, format_genpass, process_genpass, process_genpass_audit, process_genpass_compare,
+    GenPassAudit, GenPassPolicy, PolicyScore, GENPASS_POLICIES,
+};
+pub use hash::{
+    process_hash, process_hash_check, process_hash_manifest, process_hash_repeat, ManifestStatus,
+};
+pub use identify::{process_identify, Identification};
+pub use pipe::process_pipe;
 pub use text::{
-    process_text_decrypt, process_text_encrypt, process_text_key_generate,
-    process_text_nonce_generate, process_text_sign, process_text_verify,
+    build_signer, build_verifier, decode_sig, encode_sig, fingerprint, fingerprint_key_file,
+    format_combined_sig, parse_combined_sig, process_key_convert, process_key_info, process_peek,
+    process_sign_frames, process_sign_lines, process_test_vectors, process_text_decrypt,
+    process_text_decrypt_identity, process_text_decrypt_stream, process_text_encrypt,
+    process_text_encrypt_recipient, process_text_key_generate, process_text_keys_generate,
+    process_text_nonce_generate, process_text_open, process_text_rekey, process_text_seal,
+    process_text_sign, process_text_verify, process_text_verify_dir, process_verify_frames,
+    process_verify_lines, read_chacha_key, KeyInfo, NonceSource, SigStatus, SignOptions,
+    TestVector, TextSigner, TextVerifier,
 };
+pub use version::{process_version, BuildInfo};
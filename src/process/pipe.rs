@@ -0,0 +1,74 @@
+use std::io::{Read, Write};
+
+use anyhow::Result;
+use clap::Parser;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{
+    process_csv_reader, process_decode_to_writer, process_encode, process_json_to_csv,
+    Base64Format, CsvOpts, OutputFormat, PipeStep, RcliResult,
+};
+
+/// Run `input` through each step in order, returning the result and the size after each step.
+pub fn process_pipe(input: Vec<u8>, steps: &[PipeStep]) -> RcliResult<(Vec<u8>, Vec<usize>)> {
+    let mut data = input;
+    let mut sizes = Vec::with_capacity(steps.len());
+    for step in steps {
+        data = apply_step(&data, *step)?;
+        sizes.push(data.len());
+    }
+    Ok((data, sizes))
+}
+
+fn apply_step(data: &[u8], step: PipeStep) -> Result<Vec<u8>> {
+    let out = match step {
+        PipeStep::Csv2Json => {
+            let opts = CsvOpts::try_parse_from(["csv", "--input", "-"])?;
+            process_csv_reader(data, OutputFormat::Json, &opts)?.into_bytes()
+        }
+        PipeStep::Json2Csv => process_json_to_csv(data)?,
+        PipeStep::Minify => {
+            let value: serde_json::Value = serde_json::from_slice(data)?;
+            serde_json::to_vec(&value)?
+        }
+        PipeStep::B64Encode => process_encode(&mut &*data, Base64Format::Standard)?.into_bytes(),
+        PipeStep::B64Decode => {
+            let mut out = Vec::new();
+            process_decode_to_writer(&mut &*data, &mut out, Base64Format::Standard)?;
+            out
+        }
+        PipeStep::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()?
+        }
+        PipeStep::Gunzip => {
+            let mut out = Vec::new();
+            GzDecoder::new(data).read_to_end(&mut out)?;
+            out
+        }
+    };
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_pipe() -> Result<()> {
+        let csv = b"name,age\nalice,30\n".to_vec();
+        let steps = [PipeStep::Csv2Json, PipeStep::Minify, PipeStep::B64Encode];
+        let (encoded, sizes) = process_pipe(csv.clone(), &steps)?;
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes[2], encoded.len());
+
+        let steps = [PipeStep::B64Decode, PipeStep::Json2Csv];
+        let (decoded, _) = process_pipe(encoded, &steps)?;
+        assert_eq!(decoded, csv);
+
+        let (gzipped, _) = process_pipe(csv.clone(), &[PipeStep::Gzip, PipeStep::Gunzip])?;
+        assert_eq!(gzipped, csv);
+        Ok(())
+    }
+}
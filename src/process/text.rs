@@ -1,12 +1,48 @@
-use anyhow::{Ok, Result};
+use anyhow::Result;
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    read::DecoderReader,
+    Engine,
+};
 use chacha20poly1305::{
-    aead::{generic_array::GenericArray, Aead, KeyInit, OsRng},
+    aead::{generic_array::GenericArray, Aead, KeyInit, OsRng, Payload},
     AeadCore, ChaCha20Poly1305,
 };
-use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
-use std::{collections::HashMap, io::Read};
+use ed25519_dalek::{
+    pkcs8::{DecodePrivateKey, DecodePublicKey},
+    Signature, Signer, SigningKey, Verifier, VerifyingKey,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha512};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+    sync::Mutex,
+};
+use subtle::ConstantTimeEq;
+use walkdir::WalkDir;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::{
+    get_content, process_genpass, read_sig, AppError, KeyFormat, RcliError, RcliResult,
+    SigEncoding, SkipWhitespace, TextSignFormat,
+};
+
+const KEY_HEADER: &str = "rcli-key:";
+
+const BLAKE3_MAC_LEN: usize = 32;
+
+const ED25519_SIG_LEN: usize = 64;
 
-use crate::{get_content, process_genpass, TextSignFormat};
+const CHACHA_TAG_LEN: usize = 16;
+
+const CHACHA_NONCE_LEN: usize = 12;
+
+const ENCRYPT_LABEL: &[u8] = b"encrypt:";
+
+const X25519_KEY_CONTEXT: &str = "rcli 2024 x25519 chacha20poly1305 key";
 
 pub trait TextSigner {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>>;
@@ -27,40 +63,175 @@ pub trait TextDecrypt {
 pub struct Chacha2 {
     key: [u8; 32],
     nonce: Vec<u8>,
+    aad: Vec<u8>,
+}
+
+/// Where the ChaCha20-Poly1305 nonce comes from.
+///
+/// `Counter` builds the 12-byte nonce deterministically as `base || index` (index big-endian),
+/// so a sequence of messages under one key gets unique nonces without randomness.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NonceSource {
+    File(String),
+    Counter { base: [u8; 8], index: u32 },
+}
+
+/// The fixed 32 bytes every supported key type takes.
+fn key_array(key: &[u8]) -> RcliResult<[u8; 32]> {
+    key.try_into().map_err(|_| RcliError::BadKeyLength {
+        expected: 32,
+        actual: key.len(),
+    })
+}
+
+impl NonceSource {
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let nonce = match self {
+            NonceSource::File(path) => raw_or_base64(&get_content(path)?, CHACHA_NONCE_LEN),
+            NonceSource::Counter { base, index } => [&base[..], &index.to_be_bytes()].concat(),
+        };
+        if nonce.len() != CHACHA_NONCE_LEN {
+            return Err(AppError::Input(format!(
+                "nonce must be {} bytes, got {}",
+                CHACHA_NONCE_LEN,
+                nonce.len()
+            ))
+            .into());
+        }
+        Ok(nonce)
+    }
+}
+
+/// Chacha key and nonce files are accepted raw or base64-encoded, as `text generate` writes
+/// them; input of exactly `len` bytes is taken as raw.
+fn raw_or_base64(bytes: &[u8], len: usize) -> Vec<u8> {
+    if bytes.len() != len {
+        if let Ok(decoded) = STANDARD.decode(bytes.trim_ascii()) {
+            if decoded.len() == len {
+                return decoded;
+            }
+        }
+    }
+    bytes.to_vec()
+}
+
+/// The key in a chacha key file, which holds it raw or base64-encoded. Keys given any other
+/// way (e.g. `--key-literal`) are used byte for byte.
+pub fn read_chacha_key(content: &[u8]) -> Vec<u8> {
+    raw_or_base64(content, 32)
+}
+
+static USED_NONCES: Mutex<Vec<([u8; 8], u32)>> = Mutex::new(Vec::new());
+
+/// Refuse to encrypt twice with the same counter nonce in one process.
+fn claim_nonce(nonce: &NonceSource) -> Result<()> {
+    if let NonceSource::Counter { base, index } = nonce {
+        let mut used = USED_NONCES.lock().unwrap_or_else(|e| e.into_inner());
+        if used.contains(&(*base, *index)) {
+            return Err(AppError::Crypto(format!(
+                "nonce reuse: message index {} was already used with this base nonce",
+                index
+            ))
+            .into());
+        }
+        used.push((*base, *index));
+    }
+    Ok(())
 }
 
 pub struct Blake3 {
     key: [u8; 32],
+    mac_len: usize,
+    prehash: bool,
+}
+
+/// How `process_text_sign`/`process_text_verify` should interpret the key and shape the signature.
+#[derive(Debug, Clone)]
+pub struct SignOptions {
+    pub format: TextSignFormat,
+    pub key_format: KeyFormat,
+    pub strict_key: bool,
+    /// Blake3 MAC length in bytes; lengths other than 32 use the extendable output.
+    pub mac_len: usize,
+    /// Sign a streamed digest instead of the whole message: SHA-512 for Ed25519 (Ed25519ph),
+    /// plain blake3 for Blake3, which then keys a MAC over the 32-byte hash. Either way the
+    /// signatures do not verify as the direct ones, and vice versa.
+    pub prehash: bool,
+    /// Use `blake3::derive_key(context, key)` as the blake3 key, so one master secret can
+    /// serve several purposes; verification needs the same context.
+    pub derive_context: Option<String>,
+    /// Ed25519 domain separation: the length-prefixed context is signed ahead of the message,
+    /// so a signature made under one context never verifies under another (or none).
+    pub context: Option<String>,
+    /// Read `\r\n` and lone `\r` as `\n`, so a file signed on Windows verifies on Unix.
+    pub normalize_newlines: bool,
+}
+
+impl SignOptions {
+    pub fn new(format: TextSignFormat) -> Self {
+        Self {
+            format,
+            key_format: KeyFormat::Raw,
+            strict_key: false,
+            mac_len: BLAKE3_MAC_LEN,
+            prehash: false,
+            derive_context: None,
+            context: None,
+            normalize_newlines: false,
+        }
+    }
+
+    fn blake3(&self, key: &[u8]) -> Result<Blake3> {
+        let blake3 = match &self.derive_context {
+            Some(context) => Blake3::new(blake3::derive_key(context, key)),
+            None => Blake3::try_new(key)?,
+        };
+        Ok(blake3.with_mac_len(self.mac_len).with_prehash(self.prehash))
+    }
+}
+
+/// Encrypts to an x25519 public key: a fresh ephemeral key agrees a secret with the recipient,
+/// and the ciphertext is prefixed with the ephemeral public key so the recipient can do the same.
+pub struct X25519Recipient {
+    key: PublicKey,
+}
+
+pub struct X25519Identity {
+    key: StaticSecret,
 }
 
 pub struct Ed25519Signer {
     key: SigningKey,
+    prehash: bool,
+    context: Vec<u8>,
 }
 
 pub struct Ed25519Verifier {
     key: VerifyingKey,
+    prehash: bool,
+    context: Vec<u8>,
 }
 
 impl TextSigner for Blake3 {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-        let ret = blake3::keyed_hash(&self.key, &buf);
-        Ok(ret.as_bytes().to_vec())
+        self.mac_reader(reader)
     }
 }
 
 impl TextVerifier for Blake3 {
     fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
-        let mut buf = Vec::new();
-        reader.read_to_end(&mut buf)?;
-        let ret = blake3::keyed_hash(&self.key, &buf);
-        Ok(ret.as_bytes() == sig)
+        Ok(self.mac_reader(reader)?.ct_eq(sig).into())
     }
 }
 
 impl TextSigner for Ed25519Signer {
     fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        let mut reader = self.context.as_slice().chain(reader);
+        if self.prehash {
+            let digest = sha512_digest(&mut reader)?;
+            let ret = self.key.sign_prehashed(digest, None)?;
+            return Ok(ret.to_bytes().to_vec());
+        }
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
         let ret = self.key.sign(&buf);
@@ -70,28 +241,54 @@ impl TextSigner for Ed25519Signer {
 
 impl TextVerifier for Ed25519Verifier {
     fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        // a signature of the wrong length can't verify, same as a wrong one
+        let Some(ret) = Signature::from_slice(sig).ok() else {
+            return Ok(false);
+        };
+        let mut reader = self.context.as_slice().chain(reader);
+        if self.prehash {
+            let digest = sha512_digest(&mut reader)?;
+            return Ok(self.key.verify_prehashed(digest, None, &ret).is_ok());
+        }
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
-        let sig = (&sig[..64]).try_into()?;
-        let ret = Signature::from_bytes(sig);
         Ok(self.key.verify(&buf, &ret).is_ok())
     }
 }
 
+/// The bytes signed ahead of the message for `context`: its length as a u64 BE, then the
+/// context itself, so no context is a prefix of another. Empty without a context.
+fn context_prefix(context: Option<&str>) -> Vec<u8> {
+    match context {
+        Some(context) => [&(context.len() as u64).to_be_bytes(), context.as_bytes()].concat(),
+        None => Vec::new(),
+    }
+}
+
+fn sha512_digest(reader: &mut dyn Read) -> Result<Sha512> {
+    let mut digest = Sha512::new();
+    io::copy(reader, &mut digest)?;
+    Ok(digest)
+}
+
 impl TextEncrypt for Chacha2 {
     fn text_encrypt(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
         let ci = ChaCha20Poly1305::new_from_slice(&self.key);
         let cipher = match ci {
-            std::result::Result::Ok(cipher) => cipher,
-            Err(e) => return Err(anyhow::anyhow!("encrypt error: {}", e)),
+            Ok(cipher) => cipher,
+            Err(e) => return Err(AppError::Crypto(format!("encrypt error: {}", e)).into()),
         };
         let nonce = GenericArray::from_slice(&self.nonce);
-        let ciphertext = cipher.encrypt(nonce, buf.as_ref());
+        let payload = Payload {
+            msg: &buf,
+            aad: &self.aad,
+        };
+        let ciphertext = cipher.encrypt(nonce, payload);
         let text = match ciphertext {
-            std::result::Result::Ok(ciphertext) => Ok(ciphertext),
-            Err(e) => Err(anyhow::anyhow!("encrypt error: {}", e)),
+            Ok(ciphertext) => Ok(ciphertext),
+            Err(e) => Err(AppError::Crypto(format!("encrypt error: {}", e)).into()),
         }?;
         Ok(text)
     }
@@ -101,32 +298,72 @@ impl TextDecrypt for Chacha2 {
     fn text_decrypt(&self, reader: &mut Vec<u8>) -> Result<Vec<u8>> {
         let ci = ChaCha20Poly1305::new_from_slice(&self.key);
         let cipher = match ci {
-            std::result::Result::Ok(cipher) => cipher,
-            Err(e) => return Err(anyhow::anyhow!("encrypt error: {}", e)),
+            Ok(cipher) => cipher,
+            Err(e) => return Err(AppError::Crypto(format!("encrypt error: {}", e)).into()),
         };
         let nonce = GenericArray::from_slice(&self.nonce); // 96-bits; unique per message
-        let ciphertext = cipher.decrypt(nonce, reader.as_ref());
+        let payload = Payload {
+            msg: reader.as_slice(),
+            aad: &self.aad,
+        };
+        let ciphertext = cipher.decrypt(nonce, payload);
         let decrypt = match ciphertext {
-            std::result::Result::Ok(ciphertext) => Ok(ciphertext),
-            Err(e) => Err(anyhow::anyhow!("decrypt error: {}", e)),
+            Ok(ciphertext) => Ok(ciphertext),
+            // the AEAD error is opaque on purpose, so this is all that can be said
+            Err(_) => Err(AppError::Crypto(
+                "authentication failed — wrong key or corrupted data".into(),
+            )
+            .into()),
         }?;
         Ok(decrypt)
     }
 }
 
 impl Blake3 {
-    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
-        let key = key.as_ref();
-        if key.len() != 32 {
-            return Err(anyhow::anyhow!("key length must be 32 bytes"));
-        }
-        let key = (&key[..32]).try_into()?;
-        let ret = Blake3::new(key);
-        Ok(ret)
+    pub fn try_new(key: impl AsRef<[u8]>) -> RcliResult<Self> {
+        Ok(Blake3::new(key_array(key.as_ref())?))
     }
 
     pub fn new(key: [u8; 32]) -> Self {
-        Self { key }
+        Self {
+            key,
+            mac_len: BLAKE3_MAC_LEN,
+            prehash: false,
+        }
+    }
+
+    pub fn with_mac_len(mut self, mac_len: usize) -> Self {
+        self.mac_len = mac_len;
+        self
+    }
+
+    pub fn with_prehash(mut self, prehash: bool) -> Self {
+        self.prehash = prehash;
+        self
+    }
+
+    /// With `prehash` the input is streamed through an unkeyed hasher, so memory stays constant.
+    fn mac_reader(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        if self.prehash {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(reader, &mut hasher)?;
+            return Ok(self.mac(hasher.finalize().as_bytes()));
+        }
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        Ok(self.mac(&buf))
+    }
+
+    fn mac(&self, buf: &[u8]) -> Vec<u8> {
+        if self.mac_len == BLAKE3_MAC_LEN {
+            return blake3::keyed_hash(&self.key, buf).as_bytes().to_vec();
+        }
+        let mut out = vec![0; self.mac_len];
+        blake3::Hasher::new_keyed(&self.key)
+            .update(buf)
+            .finalize_xof()
+            .fill(&mut out);
+        out
     }
 
     fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
@@ -138,24 +375,45 @@ impl Blake3 {
 }
 
 impl Ed25519Signer {
-    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
-        let key = key.as_ref();
-        if key.len() != 32 {
-            return Err(anyhow::anyhow!("key length must be 32 bytes"));
-        }
-        let key = (&key[..32]).try_into()?;
-        let ret = Ed25519Signer::new(key);
-        Ok(ret)
+    pub fn try_new(key: impl AsRef<[u8]>) -> RcliResult<Self> {
+        Ok(Ed25519Signer::new(&key_array(key.as_ref())?))
     }
 
     pub fn new(key: &[u8; 32]) -> Self {
         let key = SigningKey::from_bytes(key);
-        Self { key }
+        Self {
+            key,
+            prehash: false,
+            context: Vec::new(),
+        }
     }
 
-    fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-        let mut csprng = OsRng;
-        let sk = SigningKey::generate(&mut csprng);
+    pub fn from_pkcs8_pem(pem: &str) -> Result<Self> {
+        let key = SigningKey::from_pkcs8_pem(pem)
+            .map_err(|e| anyhow::anyhow!("invalid pkcs8 private key: {}", e))?;
+        Ok(Self {
+            key,
+            prehash: false,
+            context: Vec::new(),
+        })
+    }
+
+    pub fn with_prehash(mut self, prehash: bool) -> Self {
+        self.prehash = prehash;
+        self
+    }
+
+    pub fn with_context(mut self, context: Option<&str>) -> Self {
+        self.context = context_prefix(context);
+        self
+    }
+
+    /// A random keypair, or with `seed` the same keypair every time, for reproducible vectors.
+    fn generate(seed: Option<[u8; 32]>) -> Result<HashMap<&'static str, Vec<u8>>> {
+        let sk = match seed {
+            Some(seed) => SigningKey::from_bytes(&seed),
+            None => SigningKey::generate(&mut OsRng),
+        };
         let pk = sk.verifying_key();
         let mut map = HashMap::new();
         map.insert("ed25519.sk", sk.as_bytes().to_vec());
@@ -165,18 +423,23 @@ impl Ed25519Signer {
 }
 
 impl Chacha2 {
-    pub fn try_new(key: impl AsRef<[u8]>, nonce: &str) -> Result<Self> {
-        let key = key.as_ref();
-        if key.len() != 32 {
-            return Err(anyhow::anyhow!("key length must be 32 bytes"));
-        }
-        let key = (&key[..32]).try_into()?;
-        let nonce = get_content(nonce)?;
-        let ret = Chacha2::new(key, nonce);
-        Ok(ret)
+    pub fn try_new(key: impl AsRef<[u8]>, nonce: &NonceSource) -> RcliResult<Self> {
+        let key = key_array(key.as_ref())?;
+        let nonce = nonce.to_bytes()?;
+        Ok(Chacha2::new(key, nonce))
     }
     pub fn new(key: [u8; 32], nonce: Vec<u8>) -> Self {
-        Self { key, nonce }
+        Self {
+            key,
+            nonce,
+            aad: Vec::new(),
+        }
+    }
+
+    /// Authenticate `aad` alongside the ciphertext without encrypting it.
+    pub fn with_aad(mut self, aad: &[u8]) -> Self {
+        self.aad = aad.to_vec();
+        self
     }
 
     fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
@@ -186,71 +449,858 @@ impl Chacha2 {
         map.insert("chacha2.nonce", nonce.as_slice().to_vec());
         Ok(map)
     }
+
+    fn generate_key() -> Result<HashMap<&'static str, Vec<u8>>> {
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut map = HashMap::new();
+        map.insert("chacha.key", key.to_vec());
+        map.insert("chacha.nonce", nonce.to_vec());
+        Ok(map)
+    }
 }
 
-impl Ed25519Verifier {
-    pub fn try_new(key: impl AsRef<[u8]>) -> Result<Self> {
-        let key = key.as_ref();
-        if key.len() != 32 {
-            return Err(anyhow::anyhow!("key length must be 32 bytes"));
+impl X25519Recipient {
+    pub fn try_new(key: impl AsRef<[u8]>) -> RcliResult<Self> {
+        Ok(Self {
+            key: PublicKey::from(key_array(key.as_ref())?),
+        })
+    }
+
+    pub fn encrypt(&self, reader: &mut dyn Read, aad: &[u8]) -> Result<Vec<u8>> {
+        let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+        let ephemeral_pk = PublicKey::from(&ephemeral);
+        let shared = ephemeral.diffie_hellman(&self.key);
+        if !shared.was_contributory() {
+            return Err(AppError::Crypto("invalid x25519 recipient key".into()).into());
         }
-        let key = (&key[..32]).try_into()?;
-        let key = VerifyingKey::from_bytes(key)?;
-        Ok(Self { key })
+        let key = x25519_chacha_key(shared.as_bytes(), &ephemeral_pk, &self.key);
+        let ciphertext = Chacha2::new(key, vec![0; 12])
+            .with_aad(aad)
+            .text_encrypt(reader)?;
+        Ok([ephemeral_pk.as_bytes().as_slice(), ciphertext.as_slice()].concat())
+    }
+}
+
+impl X25519Identity {
+    pub fn try_new(key: impl AsRef<[u8]>) -> RcliResult<Self> {
+        Ok(Self {
+            key: StaticSecret::from(key_array(key.as_ref())?),
+        })
+    }
+
+    pub fn decrypt(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if ciphertext.len() < 32 {
+            return Err(AppError::Crypto("ciphertext is missing the ephemeral key".into()).into());
+        }
+        let (ephemeral_pk, ciphertext) = ciphertext.split_at(32);
+        let ephemeral_pk = PublicKey::from(<[u8; 32]>::try_from(ephemeral_pk)?);
+        let shared = self.key.diffie_hellman(&ephemeral_pk);
+        let key = x25519_chacha_key(
+            shared.as_bytes(),
+            &ephemeral_pk,
+            &PublicKey::from(&self.key),
+        );
+        Chacha2::new(key, vec![0; 12])
+            .with_aad(aad)
+            .text_decrypt(&mut ciphertext.to_vec())
+    }
+
+    fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
+        let sk = StaticSecret::random_from_rng(OsRng);
+        let pk = PublicKey::from(&sk);
+        let mut map = HashMap::new();
+        map.insert("x25519.sk", sk.to_bytes().to_vec());
+        map.insert("x25519.pk", pk.as_bytes().to_vec());
+        Ok(map)
+    }
+}
+
+/// Each message gets a fresh ephemeral key, so the derived key is never reused and a zero
+/// nonce is safe.
+fn x25519_chacha_key(
+    shared: &[u8; 32],
+    ephemeral_pk: &PublicKey,
+    recipient: &PublicKey,
+) -> [u8; 32] {
+    let material = [
+        shared.as_slice(),
+        ephemeral_pk.as_bytes().as_slice(),
+        recipient.as_bytes().as_slice(),
+    ]
+    .concat();
+    blake3::derive_key(X25519_KEY_CONTEXT, &material)
+}
+
+impl Ed25519Verifier {
+    pub fn try_new(key: impl AsRef<[u8]>) -> RcliResult<Self> {
+        let key = VerifyingKey::from_bytes(&key_array(key.as_ref())?)
+            .map_err(|e| RcliError::Crypto(format!("invalid ed25519 public key: {}", e)))?;
+        Ok(Self {
+            key,
+            prehash: false,
+            context: Vec::new(),
+        })
+    }
+
+    pub fn from_public_key_pem(pem: &str) -> Result<Self> {
+        let key = VerifyingKey::from_public_key_pem(pem)
+            .map_err(|e| anyhow::anyhow!("invalid pkcs8 public key: {}", e))?;
+        Ok(Self {
+            key,
+            prehash: false,
+            context: Vec::new(),
+        })
+    }
+
+    pub fn with_prehash(mut self, prehash: bool) -> Self {
+        self.prehash = prehash;
+        self
+    }
+
+    pub fn with_context(mut self, context: Option<&str>) -> Self {
+        self.context = context_prefix(context);
+        self
     }
 }
 
 pub fn process_text_sign(
     reader: &mut dyn Read,
     key: &[u8],
-    format: TextSignFormat,
-) -> Result<Vec<u8>> {
-    let signer: Box<dyn TextSigner> = match format {
-        TextSignFormat::Blake3 => Box::new(Blake3::try_new(key)?),
-        TextSignFormat::Ed25519 => Box::new(Ed25519Signer::try_new(key)?),
+    options: &SignOptions,
+) -> RcliResult<Vec<u8>> {
+    Ok(build_signer(key, options)?.sign(reader)?)
+}
+
+/// Parse `key` once into a signer for `options`, to sign many inputs with the same key.
+pub fn build_signer(key: &[u8], options: &SignOptions) -> Result<Box<dyn TextSigner>> {
+    check_options(options)?;
+    let key = check_key_header(key, options.format, options.strict_key)?;
+    let key = decode_key(key, options.key_format)?;
+    let signer: Box<dyn TextSigner> = match (options.format, options.key_format) {
+        (TextSignFormat::Blake3, KeyFormat::Pkcs8) => {
+            return Err(anyhow::anyhow!("pkcs8 keys are only supported for ed25519"))
+        }
+        (TextSignFormat::Blake3, _) => Box::new(options.blake3(&key)?),
+        (TextSignFormat::Ed25519, KeyFormat::Pkcs8) => Box::new(
+            Ed25519Signer::from_pkcs8_pem(std::str::from_utf8(&key)?)?
+                .with_prehash(options.prehash)
+                .with_context(options.context.as_deref()),
+        ),
+        (TextSignFormat::Ed25519, _) => Box::new(
+            Ed25519Signer::try_new(&key)?
+                .with_prehash(options.prehash)
+                .with_context(options.context.as_deref()),
+        ),
+        (TextSignFormat::X25519 | TextSignFormat::Chacha, _) => {
+            return Err(anyhow::anyhow!(
+                "{} keys can only be used to encrypt",
+                options.format
+            ))
+        }
     };
-    signer.sign(reader)
+    if options.normalize_newlines {
+        return Ok(Box::new(NormalizedSigner(signer)));
+    }
+    Ok(signer)
+}
+
+/// Signs the input with `\r\n` and `\r` line endings rewritten to `\n`.
+struct NormalizedSigner(Box<dyn TextSigner>);
+
+impl TextSigner for NormalizedSigner {
+    fn sign(&self, reader: &mut dyn Read) -> Result<Vec<u8>> {
+        self.0.sign(&mut NormalizeNewlines::new(reader))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigStatus {
+    Verified,
+    Failed,
+    /// No `<file>.sig` sidecar next to the file
+    Missing,
+}
+
+/// Verify every file under `dir` against its `<file>.sig` sidecar, in path order.
+pub fn process_text_verify_dir(
+    dir: &Path,
+    key: &[u8],
+    encoding: SigEncoding,
+    options: &SignOptions,
+) -> RcliResult<Vec<(String, SigStatus)>> {
+    let verifier = build_verifier(key, options)?;
+    let mut ret = Vec::new();
+    for entry in WalkDir::new(dir).sort_by_file_name() {
+        let entry = entry.map_err(anyhow::Error::from)?;
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_some_and(|ext| ext == "sig") {
+            continue;
+        }
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".sig");
+        let status = if !Path::new(&sidecar).exists() {
+            SigStatus::Missing
+        } else {
+            match decode_sig(&read_sig(&mut File::open(&sidecar)?)?, encoding).ok() {
+                Some(sig) if verifier.verify(&mut File::open(path)?, &sig)? => SigStatus::Verified,
+                _ => SigStatus::Failed,
+            }
+        };
+        let name = path
+            .strip_prefix(dir)
+            .map_err(anyhow::Error::from)?
+            .to_string_lossy()
+            .into_owned();
+        ret.push((name, status));
+    }
+    Ok(ret)
 }
 
 pub fn process_text_verify(
     reader: &mut dyn Read,
     key: &[u8],
     sig: &[u8],
+    options: &SignOptions,
+) -> RcliResult<bool> {
+    Ok(build_verifier(key, options)?.verify(reader, sig)?)
+}
+
+/// Parse `key` once into a verifier for `options`, to check many inputs against the same key.
+pub fn build_verifier(key: &[u8], options: &SignOptions) -> Result<Box<dyn TextVerifier>> {
+    check_options(options)?;
+    let key = check_key_header(key, options.format, options.strict_key)?;
+    let key = decode_key(key, options.key_format)?;
+    let verifier: Box<dyn TextVerifier> = match (options.format, options.key_format) {
+        (TextSignFormat::Blake3, KeyFormat::Pkcs8) => {
+            return Err(anyhow::anyhow!("pkcs8 keys are only supported for ed25519"))
+        }
+        (TextSignFormat::Blake3, _) => Box::new(options.blake3(&key)?),
+        (TextSignFormat::Ed25519, KeyFormat::Pkcs8) => Box::new(
+            Ed25519Verifier::from_public_key_pem(std::str::from_utf8(&key)?)?
+                .with_prehash(options.prehash)
+                .with_context(options.context.as_deref()),
+        ),
+        (TextSignFormat::Ed25519, _) => Box::new(
+            Ed25519Verifier::try_new(&key)?
+                .with_prehash(options.prehash)
+                .with_context(options.context.as_deref()),
+        ),
+        (TextSignFormat::X25519 | TextSignFormat::Chacha, _) => {
+            return Err(anyhow::anyhow!(
+                "{} keys can only be used to encrypt",
+                options.format
+            ))
+        }
+    };
+    if options.normalize_newlines {
+        return Ok(Box::new(NormalizedVerifier(verifier)));
+    }
+    Ok(verifier)
+}
+
+/// Verifies the input with `\r\n` and `\r` line endings rewritten to `\n`.
+struct NormalizedVerifier(Box<dyn TextVerifier>);
+
+impl TextVerifier for NormalizedVerifier {
+    fn verify(&self, reader: &mut dyn Read, sig: &[u8]) -> Result<bool> {
+        self.0.verify(&mut NormalizeNewlines::new(reader), sig)
+    }
+}
+
+/// Yields the bytes of `inner` with every `\r\n` and lone `\r` replaced by `\n`.
+struct NormalizeNewlines<R> {
+    inner: R,
+    after_cr: bool,
+}
+
+impl<R: Read> NormalizeNewlines<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            after_cr: false,
+        }
+    }
+}
+
+impl<R: Read> Read for NormalizeNewlines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                return Ok(0);
+            }
+            let mut len = 0;
+            for i in 0..n {
+                let b = buf[i];
+                if self.after_cr && b == b'\n' {
+                    self.after_cr = false;
+                    continue;
+                }
+                self.after_cr = b == b'\r';
+                buf[len] = if b == b'\r' { b'\n' } else { b };
+                len += 1;
+            }
+            // a read of just the `\n` of a split `\r\n` leaves nothing to return yet
+            if len > 0 {
+                return Ok(len);
+            }
+        }
+    }
+}
+
+/// Write each input line followed by a tab and the base64url Blake3 MAC of that line.
+pub fn process_sign_lines(
+    reader: &mut dyn BufRead,
+    key: &[u8],
+    writer: &mut dyn Write,
+) -> RcliResult<()> {
+    let key = check_key_header(key, TextSignFormat::Blake3, false)?;
+    let signer = Blake3::try_new(key)?;
+    for line in reader.lines() {
+        let line = line?;
+        let mac = URL_SAFE_NO_PAD.encode(signer.mac(line.as_bytes()));
+        writeln!(writer, "{}\t{}", line, mac)?;
+    }
+    Ok(())
+}
+
+/// Check the output of `process_sign_lines`, returning the 1-based numbers of the lines that fail.
+pub fn process_verify_lines(reader: &mut dyn BufRead, key: &[u8]) -> RcliResult<Vec<usize>> {
+    let key = check_key_header(key, TextSignFormat::Blake3, false)?;
+    let verifier = Blake3::try_new(key)?;
+    let mut failed = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let verified = line.rsplit_once('\t').is_some_and(|(text, mac)| {
+            URL_SAFE_NO_PAD
+                .decode(mac)
+                .is_ok_and(|mac| verifier.mac(text.as_bytes()).ct_eq(&mac).into())
+        });
+        if !verified {
+            failed.push(index + 1);
+        }
+    }
+    Ok(failed)
+}
+
+/// Sign each `u32` big-endian length-prefixed frame of `reader` on its own, writing
+/// `<index>:<base64url sig>` lines with 0-based frame indexes.
+pub fn process_sign_frames(
+    reader: &mut dyn Read,
+    key: &[u8],
+    options: &SignOptions,
+    writer: &mut dyn Write,
+) -> RcliResult<()> {
+    let signer = build_signer(key, options)?;
+    let mut index = 0;
+    while let Some(payload) = read_frame(reader, index)? {
+        let sig = signer.sign(&mut payload.as_slice())?;
+        writeln!(writer, "{}:{}", index, URL_SAFE_NO_PAD.encode(sig))?;
+        index += 1;
+    }
+    Ok(())
+}
+
+/// Check the frames of `reader` against the output of `process_sign_frames`, returning the
+/// indexes of frames that fail or have no signature, and of signatures that have no frame.
+pub fn process_verify_frames(
+    reader: &mut dyn Read,
+    sigs: &mut dyn BufRead,
+    key: &[u8],
+    options: &SignOptions,
+) -> RcliResult<Vec<usize>> {
+    let verifier = build_verifier(key, options)?;
+    let mut expected = BTreeMap::new();
+    for (n, line) in sigs.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (index, sig) = line
+            .trim()
+            .split_once(':')
+            .and_then(|(index, sig)| Some((index.parse::<usize>().ok()?, sig)))
+            .ok_or_else(|| anyhow::anyhow!("line {}: expected <index>:<signature>", n + 1))?;
+        expected.insert(index, URL_SAFE_NO_PAD.decode(sig).ok());
+    }
+    let mut failed = Vec::new();
+    let mut index = 0;
+    while let Some(payload) = read_frame(reader, index)? {
+        let verified = match expected.remove(&index) {
+            Some(Some(sig)) => verifier.verify(&mut payload.as_slice(), &sig)?,
+            _ => false,
+        };
+        if !verified {
+            failed.push(index);
+        }
+        index += 1;
+    }
+    failed.extend(expected.into_keys());
+    Ok(failed)
+}
+
+/// Read one length-prefixed frame, or `None` at a clean end of input.
+fn read_frame(reader: &mut dyn Read, index: usize) -> Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    match filled {
+        0 => return Ok(None),
+        4 => {}
+        n => anyhow::bail!(
+            "frame {}: truncated length prefix ({} of 4 bytes)",
+            index,
+            n
+        ),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    // read through `take` so a bogus length cannot force a huge allocation up front
+    let mut payload = Vec::new();
+    reader.take(len as u64).read_to_end(&mut payload)?;
+    if payload.len() < len {
+        anyhow::bail!(
+            "frame {}: truncated payload ({} of {} bytes)",
+            index,
+            payload.len(),
+            len
+        );
+    }
+    Ok(Some(payload))
+}
+
+fn check_options(options: &SignOptions) -> Result<()> {
+    match options.format {
+        TextSignFormat::Ed25519 if options.mac_len != BLAKE3_MAC_LEN => {
+            Err(anyhow::anyhow!("--mac-len only applies to blake3"))
+        }
+        TextSignFormat::Ed25519 | TextSignFormat::X25519 if options.derive_context.is_some() => {
+            Err(anyhow::anyhow!("--derive-context only applies to blake3"))
+        }
+        TextSignFormat::Blake3 | TextSignFormat::X25519 | TextSignFormat::Chacha
+            if options.context.is_some() =>
+        {
+            Err(anyhow::anyhow!(
+                "--context only applies to ed25519; blake3 keys take --derive-context"
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Render a signature as `<format>.<base64url sig>` so it can be verified on its own.
+pub fn format_combined_sig(format: TextSignFormat, sig: &[u8]) -> String {
+    format!("{}.{}", format, URL_SAFE_NO_PAD.encode(sig))
+}
+
+pub fn parse_combined_sig(combined: &str) -> Result<(TextSignFormat, Vec<u8>)> {
+    let (format, sig) = combined
+        .trim()
+        .split_once('.')
+        .ok_or_else(|| anyhow::anyhow!("combined signature must look like <format>.<sig>"))?;
+    Ok((format.parse()?, URL_SAFE_NO_PAD.decode(sig)?))
+}
+
+pub fn encode_sig(sig: &[u8], encoding: SigEncoding) -> String {
+    match encoding {
+        SigEncoding::UrlSafe => URL_SAFE_NO_PAD.encode(sig),
+        SigEncoding::Standard => STANDARD.encode(sig),
+        SigEncoding::Hex => hex::encode(sig),
+    }
+}
+
+/// Decode a `--sig` value, reporting which encoding was expected when it doesn't parse.
+pub fn decode_sig(sig: &str, encoding: SigEncoding) -> Result<Vec<u8>> {
+    let sig = sig.trim();
+    let decoded = match encoding {
+        SigEncoding::UrlSafe => URL_SAFE_NO_PAD.decode(sig).map_err(anyhow::Error::from),
+        SigEncoding::Standard => STANDARD.decode(sig).map_err(anyhow::Error::from),
+        SigEncoding::Hex => hex::decode(sig).map_err(anyhow::Error::from),
+    };
+    decoded
+        .map_err(|e| AppError::Input(format!("signature is not valid {}: {}", encoding, e)).into())
+}
+
+pub fn process_text_key_generate(
+    format: TextSignFormat,
+    encoding: KeyFormat,
+) -> RcliResult<HashMap<&'static str, Vec<u8>>> {
+    Ok(key_generate(format, encoding, None)?)
+}
+
+fn key_generate(
     format: TextSignFormat,
-) -> Result<bool> {
-    let verifier: Box<dyn TextVerifier> = match format {
-        TextSignFormat::Blake3 => Box::new(Blake3::try_new(key)?),
-        TextSignFormat::Ed25519 => Box::new(Ed25519Verifier::try_new(key)?),
+    encoding: KeyFormat,
+    seed: Option<[u8; 32]>,
+) -> Result<HashMap<&'static str, Vec<u8>>> {
+    let keys = match (format, seed) {
+        (TextSignFormat::Ed25519, seed) => Ed25519Signer::generate(seed),
+        (_, Some(_)) => return Err(anyhow::anyhow!("--seed only applies to ed25519")),
+        (TextSignFormat::Blake3, None) => Blake3::generate(),
+        (TextSignFormat::X25519, None) => X25519Identity::generate(),
+        (TextSignFormat::Chacha, None) => Chacha2::generate_key(),
+    }?;
+    // encrypt reads the chacha key and nonce without a header, raw or base64
+    let header = match format {
+        TextSignFormat::Chacha => String::new(),
+        _ => format!("{}{}\n", KEY_HEADER, format),
     };
-    verifier.verify(reader, sig)
+    let mut ret = HashMap::new();
+    for (name, key) in keys {
+        let key = encode_key(&key, encoding)?;
+        ret.insert(name, [header.as_bytes(), key.as_slice()].concat());
+    }
+    Ok(ret)
+}
+
+/// What `text key-info` reports about a key.
+#[derive(Debug, Clone)]
+pub struct KeyInfo {
+    pub format: TextSignFormat,
+    pub len: usize,
+    /// For an ed25519 public key, whether the bytes decompress to a curve point. Secret keys
+    /// are 32-byte seeds rather than points, so they are not checked.
+    pub valid_point: Option<bool>,
+    pub fingerprint: String,
+}
+
+/// Inspect a key as written by `text generate`, failing if it is not 32 bytes once decoded.
+/// The `rcli-key:` header doesn't say whether a key is secret, so the caller does.
+pub fn process_key_info(
+    content: &[u8],
+    format: TextSignFormat,
+    key_format: KeyFormat,
+    secret: bool,
+) -> RcliResult<KeyInfo> {
+    let key = check_key_header(content, format, false)?;
+    let key = decode_key(key, key_format)?;
+    let bytes = key_array(&key)?;
+    let valid_point = match format {
+        TextSignFormat::Ed25519 if !secret => Some(VerifyingKey::from_bytes(&bytes).is_ok()),
+        _ => None,
+    };
+    Ok(KeyInfo {
+        format,
+        len: key.len(),
+        valid_point,
+        fingerprint: fingerprint(&key),
+    })
 }
 
-pub fn process_text_key_generate(format: TextSignFormat) -> Result<HashMap<&'static str, Vec<u8>>> {
-    match format {
-        TextSignFormat::Blake3 => Blake3::generate(),
-        TextSignFormat::Ed25519 => Ed25519Signer::generate(),
+/// Re-encode a 32-byte key from `from` to `to`, keeping any `rcli-key:` header line.
+pub fn process_key_convert(content: &[u8], from: KeyFormat, to: KeyFormat) -> RcliResult<Vec<u8>> {
+    let (header, key) = match content.strip_prefix(KEY_HEADER.as_bytes()) {
+        Some(rest) => {
+            let end = rest
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(rest.len(), |i| i + 1);
+            content.split_at(KEY_HEADER.len() + end)
+        }
+        None => (&content[..0], content),
+    };
+    let key = decode_key(key, from)?;
+    if key.len() != 32 {
+        return Err(AppError::Input(format!("key must be 32 bytes, got {}", key.len())).into());
     }
+    Ok([header, encode_key(&key, to)?.as_slice()].concat())
+}
+
+/// A short, stable name for a key: the base64url blake3 hash of its raw bytes.
+pub fn fingerprint(key: &[u8]) -> String {
+    URL_SAFE_NO_PAD.encode(blake3::hash(key).as_bytes())
+}
+
+/// Count the bytes of `reader` and return them with a short blake3 fingerprint (the first
+/// 8 bytes of the hash, in hex), for `text --peek`.
+pub fn process_peek(reader: &mut dyn Read) -> RcliResult<(u64, String)> {
+    let mut hasher = blake3::Hasher::new();
+    let len = io::copy(reader, &mut hasher)?;
+    Ok((len, hex::encode(&hasher.finalize().as_bytes()[..8])))
 }
 
-pub fn process_text_nonce_generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-    Chacha2::generate()
+/// Fingerprint a key as written by `text generate`, ignoring its header and encoding.
+pub fn fingerprint_key_file(
+    content: &[u8],
+    format: TextSignFormat,
+    encoding: KeyFormat,
+) -> Result<String> {
+    let key = check_key_header(content, format, false)?;
+    Ok(fingerprint(&decode_key(key, encoding)?))
 }
 
-pub fn process_text_encrypt(reader: &mut dyn Read, key: &[u8], nonce: &str) -> Result<Vec<u8>> {
-    let chacha2 = Chacha2::try_new(key, nonce)?;
+fn encode_key(key: &[u8], encoding: KeyFormat) -> Result<Vec<u8>> {
+    let key = match encoding {
+        KeyFormat::Raw => key.to_vec(),
+        KeyFormat::Hex => hex::encode(key).into_bytes(),
+        KeyFormat::Base64 => STANDARD.encode(key).into_bytes(),
+        KeyFormat::Pkcs8 => return Err(anyhow::anyhow!("cannot generate pkcs8 keys")),
+    };
+    Ok(key)
+}
+
+fn decode_key(key: &[u8], key_format: KeyFormat) -> Result<Vec<u8>> {
+    let key = match key_format {
+        KeyFormat::Raw | KeyFormat::Pkcs8 => key.to_vec(),
+        KeyFormat::Hex => hex::decode(key.trim_ascii())?,
+        KeyFormat::Base64 => STANDARD.decode(key.trim_ascii())?,
+    };
+    Ok(key)
+}
+
+/// Strip the optional `rcli-key:<format>` header written by `text generate`. With
+/// `strict` the header is required and must match `format`.
+fn check_key_header(key: &[u8], format: TextSignFormat, strict: bool) -> Result<&[u8]> {
+    let header = key.strip_prefix(KEY_HEADER.as_bytes()).and_then(|rest| {
+        rest.iter()
+            .position(|&b| b == b'\n')
+            .map(|pos| rest.split_at(pos))
+    });
+    match header {
+        Some((tag, rest)) => {
+            let tag = std::str::from_utf8(tag)?;
+            if strict && tag != Into::<&str>::into(format) {
+                return Err(anyhow::anyhow!(
+                    "key was generated for {}, but --format is {}",
+                    tag,
+                    format
+                ));
+            }
+            Ok(&rest[1..])
+        }
+        None if strict => Err(anyhow::anyhow!("key has no rcli-key header")),
+        None => Ok(key),
+    }
+}
+
+pub fn process_text_keys_generate(
+    format: TextSignFormat,
+    count: u32,
+    encoding: KeyFormat,
+    seed: Option<[u8; 32]>,
+) -> RcliResult<HashMap<String, Vec<u8>>> {
+    if seed.is_some() && count != 1 {
+        return Err(anyhow::anyhow!("--seed can only generate a single keypair").into());
+    }
+    let mut keys = HashMap::new();
+    for i in 0..count {
+        for (name, key) in key_generate(format, encoding, seed)? {
+            let name = if count == 1 {
+                name.to_string()
+            } else {
+                indexed_name(name, i)
+            };
+            keys.insert(name, key);
+        }
+    }
+    Ok(keys)
+}
+
+fn indexed_name(name: &str, index: u32) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}.{}.{}", stem, index, ext),
+        None => format!("{}.{}", name, index),
+    }
+}
+
+pub fn process_text_nonce_generate() -> RcliResult<HashMap<&'static str, Vec<u8>>> {
+    Ok(Chacha2::generate()?)
+}
+
+const TEST_VECTOR_INPUTS: [&str; 3] = ["", "abc", "rcli test vector"];
+
+/// One `test-vectors` entry; bytes are hex encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TestVector {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub input: String,
+    pub output: String,
+}
+
+/// Fixed key/input/output tuples for checking other implementations against rcli. The key is
+/// the bytes `00..1f` and the chacha nonce `00..0b`, so the output never changes.
+pub fn process_test_vectors(format: TextSignFormat) -> RcliResult<Vec<TestVector>> {
+    let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+    // the same nonce for every input on purpose; this bypasses the nonce reuse guard
+    let nonce = NonceSource::Counter {
+        base: [0, 1, 2, 3, 4, 5, 6, 7],
+        index: u32::from_be_bytes([8, 9, 10, 11]),
+    };
+    let mut vectors = Vec::new();
+    for input in TEST_VECTOR_INPUTS {
+        let (public_key, nonce_hex, output) = match format {
+            TextSignFormat::Blake3 => (None, None, Blake3::new(key).mac(input.as_bytes())),
+            TextSignFormat::Ed25519 => {
+                let pk = SigningKey::from_bytes(&key).verifying_key();
+                let sig = Ed25519Signer::try_new(key)?.sign(&mut input.as_bytes())?;
+                (Some(hex::encode(pk.as_bytes())), None, sig)
+            }
+            TextSignFormat::Chacha => {
+                let ciphertext =
+                    Chacha2::try_new(key, &nonce)?.text_encrypt(&mut input.as_bytes())?;
+                (None, Some(hex::encode(nonce.to_bytes()?)), ciphertext)
+            }
+            TextSignFormat::X25519 => {
+                return Err(
+                    anyhow::anyhow!("no test vectors for x25519, which is randomized").into(),
+                )
+            }
+        };
+        vectors.push(TestVector {
+            key: hex::encode(key),
+            public_key,
+            nonce: nonce_hex,
+            input: input.to_string(),
+            output: hex::encode(output),
+        });
+    }
+    Ok(vectors)
+}
+
+pub fn process_text_encrypt(
+    reader: &mut dyn Read,
+    key: &[u8],
+    nonce: &NonceSource,
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    let chacha2 = Chacha2::try_new(key, nonce)?.with_aad(aad);
+    claim_nonce(nonce)?;
     let encrypt = chacha2.text_encrypt(reader)?;
     Ok(encrypt)
 }
 
-pub fn process_text_decrypt(reader: &mut Vec<u8>, key: &[u8], nonce: &str) -> Result<Vec<u8>> {
-    let chacha2 = Chacha2::try_new(key, nonce)?;
+pub fn process_text_decrypt(
+    reader: &mut Vec<u8>,
+    key: &[u8],
+    nonce: &NonceSource,
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    if reader.len() < CHACHA_TAG_LEN {
+        return Err(RcliError::Crypto(format!(
+            "ciphertext is {} bytes, too short to hold the {}-byte authentication tag",
+            reader.len(),
+            CHACHA_TAG_LEN
+        )));
+    }
+    let chacha2 = Chacha2::try_new(key, nonce)?.with_aad(aad);
     let decrypt = chacha2.text_decrypt(reader)?;
     Ok(decrypt)
 }
 
+/// Sign-then-encrypt: the output is the ChaCha20-Poly1305 ciphertext of the plaintext followed
+/// by its 64-byte Ed25519 signature, so the signature is only visible to holders of `enc_key`.
+pub fn process_text_seal(
+    reader: &mut dyn Read,
+    sign_key: &[u8],
+    enc_key: &[u8],
+    nonce: &NonceSource,
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    let mut sealed = Vec::new();
+    reader.read_to_end(&mut sealed)?;
+    let options = SignOptions::new(TextSignFormat::Ed25519);
+    let sig = process_text_sign(&mut sealed.as_slice(), sign_key, &options)?;
+    sealed.extend_from_slice(&sig);
+    process_text_encrypt(&mut sealed.as_slice(), enc_key, nonce, aad)
+}
+
+/// Reverse `process_text_seal`: decrypt, split off the trailing signature and verify it.
+pub fn process_text_open(
+    ciphertext: &[u8],
+    verify_key: &[u8],
+    enc_key: &[u8],
+    nonce: &NonceSource,
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    let mut plaintext = process_text_decrypt(&mut ciphertext.to_vec(), enc_key, nonce, aad)?;
+    let Some(split) = plaintext.len().checked_sub(ED25519_SIG_LEN) else {
+        return Err(AppError::Verification("sealed message has no signature".into()).into());
+    };
+    let sig = plaintext.split_off(split);
+    let options = SignOptions::new(TextSignFormat::Ed25519);
+    if !process_text_verify(&mut plaintext.as_slice(), verify_key, &sig, &options)? {
+        return Err(
+            AppError::Verification("sealed message signature did not verify".into()).into(),
+        );
+    }
+    Ok(plaintext)
+}
+
+/// Decrypt `ciphertext` and encrypt the plaintext again under `new_key`; the plaintext only
+/// ever lives in memory.
+pub fn process_text_rekey(
+    ciphertext: &mut Vec<u8>,
+    old_key: &[u8],
+    old_nonce: &NonceSource,
+    new_key: &[u8],
+    new_nonce: &NonceSource,
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    let plaintext = process_text_decrypt(ciphertext, old_key, old_nonce, aad)?;
+    process_text_encrypt(&mut plaintext.as_slice(), new_key, new_nonce, aad)
+}
+
+/// Encrypt to the x25519 public key `recipient`; only its private key can decrypt the output.
+pub fn process_text_encrypt_recipient(
+    reader: &mut dyn Read,
+    recipient: &[u8],
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    let recipient = check_key_header(recipient, TextSignFormat::X25519, false)?;
+    Ok(X25519Recipient::try_new(recipient)?.encrypt(reader, aad)?)
+}
+
+pub fn process_text_decrypt_identity(
+    ciphertext: &[u8],
+    identity: &[u8],
+    aad: &[u8],
+) -> RcliResult<Vec<u8>> {
+    let identity = check_key_header(identity, TextSignFormat::X25519, false)?;
+    Ok(X25519Identity::try_new(identity)?.decrypt(ciphertext, aad)?)
+}
+
+/// Decrypt base64url ciphertext read from `reader` into `writer`, decoding the base64 as it is
+/// read instead of buffering the encoded text alongside the decoded bytes. The `encrypt:` label
+/// and whitespace that `text encrypt` prints are skipped. The ciphertext is a single AEAD
+/// message, not framed chunks, so it is still decrypted in one piece once fully read.
+pub fn process_text_decrypt_stream(
+    reader: &mut dyn Read,
+    key: &[u8],
+    nonce: &NonceSource,
+    aad: &[u8],
+    writer: &mut dyn Write,
+) -> RcliResult<()> {
+    let mut label = Vec::new();
+    (&mut *reader)
+        .take(ENCRYPT_LABEL.len() as u64)
+        .read_to_end(&mut label)?;
+    let label = match label.as_slice() {
+        ENCRYPT_LABEL => &[][..],
+        other => other,
+    };
+    let reader = SkipWhitespace(BufReader::new(label.chain(reader)));
+    let mut decoder = DecoderReader::new(reader, &URL_SAFE_NO_PAD);
+    let mut ciphertext = Vec::new();
+    decoder.read_to_end(&mut ciphertext)?;
+    let decrypt = process_text_decrypt(&mut ciphertext, key, nonce, aad)?;
+    writer.write_all(&decrypt)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use std::fs;
 
     use super::*;
 
@@ -258,6 +1308,10 @@ mod tests {
 
     const NONCE: &str = "fixtures/chacha2.nonce";
 
+    fn nonce() -> NonceSource {
+        NonceSource::File(NONCE.to_string())
+    }
+
     #[test]
     fn test_process_text_sign() -> Result<()> {
         let mut reader = std::io::Cursor::new("hello world");
@@ -265,12 +1319,468 @@ mod tests {
 
         let format = TextSignFormat::Blake3;
 
-        let sig = process_text_sign(&mut reader, KEY, format)?;
-        let ret = process_text_verify(&mut reader1, KEY, &sig, format)?;
+        let sig = process_text_sign(&mut reader, KEY, &SignOptions::new(format))?;
+        let ret = process_text_verify(&mut reader1, KEY, &sig, &SignOptions::new(format))?;
         assert!(ret);
         Ok(())
     }
 
+    #[test]
+    fn test_process_peek() -> Result<()> {
+        let mut reader = std::fs::File::open("fixtures/count.txt")?;
+        let (len, fingerprint) = process_peek(&mut reader)?;
+        assert_eq!(len, 34);
+        let expected = blake3::hash(&std::fs::read("fixtures/count.txt")?);
+        assert_eq!(fingerprint, hex::encode(&expected.as_bytes()[..8]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_rekey() -> Result<()> {
+        let new_key = [7u8; 32];
+        let mut ciphertext = process_text_encrypt(&mut "hello".as_bytes(), KEY, &nonce(), b"")?;
+        let mut rekeyed =
+            process_text_rekey(&mut ciphertext, KEY, &nonce(), &new_key, &nonce(), b"")?;
+        assert!(process_text_decrypt(&mut rekeyed.clone(), KEY, &nonce(), b"").is_err());
+        let plaintext = process_text_decrypt(&mut rekeyed, &new_key, &nonce(), b"")?;
+        assert_eq!(plaintext, b"hello");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_verify_dir() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli_verify_dir_{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let options = SignOptions::new(TextSignFormat::Blake3);
+        for name in ["good.txt", "tampered.txt"] {
+            let sig = process_text_sign(&mut "release".as_bytes(), KEY, &options)?;
+            fs::write(dir.join(name), "release")?;
+            // as `rcli text sign > file.sig` writes it
+            fs::write(
+                dir.join(format!("{}.sig", name)),
+                format!("sig:{}\n", encode_sig(&sig, SigEncoding::UrlSafe)),
+            )?;
+        }
+        fs::write(dir.join("tampered.txt"), "release!")?;
+        fs::write(dir.join("unsigned.txt"), "release")?;
+
+        let results = process_text_verify_dir(&dir, KEY, SigEncoding::UrlSafe, &options);
+        assert_eq!(
+            results?,
+            vec![
+                ("good.txt".to_string(), SigStatus::Verified),
+                ("tampered.txt".to_string(), SigStatus::Failed),
+                ("unsigned.txt".to_string(), SigStatus::Missing),
+            ]
+        );
+
+        // a truncated ed25519 sidecar fails that file instead of aborting the scan
+        let pk = get_content("fixtures/ed25519.pub.pem")?;
+        let options = SignOptions {
+            key_format: KeyFormat::Pkcs8,
+            ..SignOptions::new(TextSignFormat::Ed25519)
+        };
+        fs::write(dir.join("good.txt.sig"), "sig:AAAA\n")?;
+        let results = process_text_verify_dir(&dir, &pk, SigEncoding::UrlSafe, &options);
+        fs::remove_dir_all(&dir)?;
+        assert_eq!(
+            results?,
+            vec![
+                ("good.txt".to_string(), SigStatus::Failed),
+                ("tampered.txt".to_string(), SigStatus::Failed),
+                ("unsigned.txt".to_string(), SigStatus::Missing),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_test_vectors() -> Result<()> {
+        let blake3 = process_test_vectors(TextSignFormat::Blake3)?;
+        assert_eq!(blake3[1].input, "abc");
+        assert_eq!(
+            blake3[1].output,
+            "6da54495d8152f2bcba87bd7282df70901cdb66b4448ed5f4c7bd2852b8b5532"
+        );
+
+        let chacha = process_test_vectors(TextSignFormat::Chacha)?;
+        assert_eq!(chacha, process_test_vectors(TextSignFormat::Chacha)?);
+        let mut ciphertext = hex::decode(&chacha[2].output)?;
+        let nonce = NonceSource::Counter {
+            base: [0, 1, 2, 3, 4, 5, 6, 7],
+            index: u32::from_be_bytes([8, 9, 10, 11]),
+        };
+        let key: Vec<u8> = (0..32).collect();
+        let plaintext = process_text_decrypt(&mut ciphertext, &key, &nonce, b"")?;
+        assert_eq!(plaintext, b"rcli test vector");
+
+        assert!(process_test_vectors(TextSignFormat::X25519).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_decrypt_errors() -> Result<()> {
+        let mut ciphertext = process_text_encrypt(&mut "hello".as_bytes(), KEY, &nonce(), b"")?;
+
+        let mut truncated = ciphertext[..CHACHA_TAG_LEN - 1].to_vec();
+        let err = process_text_decrypt(&mut truncated, KEY, &nonce(), b"").unwrap_err();
+        assert!(err.to_string().contains("too short"));
+
+        let wrong_key = [0u8; 32];
+        let err = process_text_decrypt(&mut ciphertext, &wrong_key, &nonce(), b"").unwrap_err();
+        assert!(err.to_string().contains("authentication failed"));
+
+        let path = std::env::temp_dir().join(format!("rcli_short_nonce_{}", std::process::id()));
+        fs::write(&path, b"abc")?;
+        let short = NonceSource::File(path.display().to_string());
+        let err = process_text_decrypt(&mut ciphertext, KEY, &short, b"").unwrap_err();
+        fs::remove_file(&path)?;
+        assert_eq!(err.to_string(), "nonce must be 12 bytes, got 3");
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_verifier_reused() -> Result<()> {
+        let options = SignOptions::new(TextSignFormat::Blake3);
+        let messages = (0..1000)
+            .map(|i| format!("message {}", i))
+            .collect::<Vec<_>>();
+        let sigs = messages
+            .iter()
+            .map(|msg| process_text_sign(&mut msg.as_bytes(), KEY, &options))
+            .collect::<RcliResult<Vec<_>>>()?;
+
+        let verifier = build_verifier(KEY, &options)?;
+        for (msg, sig) in messages.iter().zip(&sigs) {
+            assert!(verifier.verify(&mut std::io::Cursor::new(msg), sig)?);
+        }
+        assert!(!verifier.verify(&mut std::io::Cursor::new("message 0"), &sigs[1])?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_verify_normalize_newlines() -> Result<()> {
+        let lf = SignOptions::new(TextSignFormat::Blake3);
+        let sig = process_text_sign(&mut "one\ntwo\n".as_bytes(), KEY, &lf)?;
+        assert!(!process_text_verify(
+            &mut "one\r\ntwo\r\n".as_bytes(),
+            KEY,
+            &sig,
+            &lf
+        )?);
+
+        let normalized = SignOptions {
+            normalize_newlines: true,
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        assert!(process_text_verify(
+            &mut "one\r\ntwo\r\n".as_bytes(),
+            KEY,
+            &sig,
+            &normalized
+        )?);
+        assert!(process_text_verify(
+            &mut "one\rtwo\r".as_bytes(),
+            KEY,
+            &sig,
+            &normalized
+        )?);
+        // a `\r\n` split across reads still counts as one newline
+        let mut split = "one\r".as_bytes().chain("\ntwo\n".as_bytes());
+        assert!(process_text_verify(&mut split, KEY, &sig, &normalized)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_signer_normalize_newlines() -> Result<()> {
+        let options = SignOptions {
+            normalize_newlines: true,
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        let sig = build_signer(KEY, &options)?.sign(&mut "one\r\ntwo\r\n".as_bytes())?;
+        let verifier = build_verifier(KEY, &options)?;
+        assert!(verifier.verify(&mut "one\r\ntwo\r\n".as_bytes(), &sig)?);
+        assert!(verifier.verify(&mut "one\ntwo\n".as_bytes(), &sig)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_seal_open() -> Result<()> {
+        let sender = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        let (sk, pk) = (&sender["ed25519.sk"], &sender["ed25519.pk"]);
+        let key = &process_text_key_generate(TextSignFormat::Chacha, KeyFormat::Raw)?["chacha.key"];
+        let nonce = NonceSource::File("fixtures/chacha2.nonce".into());
+
+        let sealed = process_text_seal(&mut "hello world".as_bytes(), sk, key, &nonce, b"")?;
+        let opened = process_text_open(&sealed, pk, key, &nonce, b"")?;
+        assert_eq!(opened, b"hello world");
+
+        let mut tampered = sealed.clone();
+        tampered[0] ^= 1;
+        assert!(process_text_open(&tampered, pk, key, &nonce, b"").is_err());
+
+        let other = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        let err = process_text_open(&sealed, &other["ed25519.pk"], key, &nonce, b"").unwrap_err();
+        assert!(matches!(
+            &err,
+            RcliError::VerifyFailed(msg) if msg == "sealed message signature did not verify"
+        ));
+
+        let unsigned = process_text_encrypt(&mut "short".as_bytes(), key, &nonce, b"")?;
+        let err = process_text_open(&unsigned, pk, key, &nonce, b"").unwrap_err();
+        assert_eq!(err.to_string(), "sealed message has no signature");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_sign_lines() -> Result<()> {
+        let mut signed = Vec::new();
+        process_sign_lines(&mut "first\nsecond\nthird".as_bytes(), KEY, &mut signed)?;
+        let signed = String::from_utf8(signed)?;
+        assert_eq!(signed.lines().count(), 3);
+        assert!(process_verify_lines(&mut signed.as_bytes(), KEY)?.is_empty());
+
+        let tampered = signed.replacen("second", "secomd", 1);
+        assert_eq!(
+            process_verify_lines(&mut tampered.as_bytes(), KEY)?,
+            vec![2]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_sign_frames() -> Result<()> {
+        let mut frames = Vec::new();
+        for payload in [&b"hello"[..], b"world!"] {
+            frames.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+            frames.extend_from_slice(payload);
+        }
+        let options = SignOptions::new(TextSignFormat::Blake3);
+        let mut sigs = Vec::new();
+        process_sign_frames(&mut frames.as_slice(), KEY, &options, &mut sigs)?;
+        let sigs = String::from_utf8(sigs)?;
+        assert_eq!(sigs.lines().count(), 2);
+        assert!(sigs.starts_with("0:"));
+        assert!(process_verify_frames(
+            &mut frames.as_slice(),
+            &mut sigs.as_bytes(),
+            KEY,
+            &options
+        )?
+        .is_empty());
+
+        let mut tampered = frames.clone();
+        *tampered.last_mut().unwrap() = b'?';
+        assert_eq!(
+            process_verify_frames(
+                &mut tampered.as_slice(),
+                &mut sigs.as_bytes(),
+                KEY,
+                &options
+            )?,
+            vec![1]
+        );
+
+        let truncated = &frames[..frames.len() - 2];
+        let err =
+            process_sign_frames(&mut &truncated[..], KEY, &options, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "frame 1: truncated payload (4 of 6 bytes)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_mac_len() -> Result<()> {
+        let options = SignOptions {
+            mac_len: 64,
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        let mut reader = std::io::Cursor::new("hello world");
+        let sig = process_text_sign(&mut reader, KEY, &options)?;
+        assert_eq!(sig.len(), 64);
+
+        let mut reader = std::io::Cursor::new("hello world");
+        let short = process_text_sign(&mut reader, KEY, &SignOptions::new(TextSignFormat::Blake3))?;
+        assert_eq!(&sig[..32], &short[..]);
+
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(process_text_verify(&mut reader, KEY, &sig, &options)?);
+        let mut reader = std::io::Cursor::new("hello world");
+        let default = SignOptions::new(TextSignFormat::Blake3);
+        assert!(!process_text_verify(&mut reader, KEY, &sig, &default)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_ed25519_context() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        let (sk, pk) = (&keys["ed25519.sk"], &keys["ed25519.pk"]);
+        let with_context = |context: Option<&str>, prehash| SignOptions {
+            context: context.map(String::from),
+            prehash,
+            ..SignOptions::new(TextSignFormat::Ed25519)
+        };
+        for prehash in [false, true] {
+            let release = with_context(Some("rcli-release-v1"), prehash);
+            let sig = process_text_sign(&mut "hello world".as_bytes(), sk, &release)?;
+            assert!(process_text_verify(
+                &mut "hello world".as_bytes(),
+                pk,
+                &sig,
+                &release
+            )?);
+
+            let other = with_context(Some("rcli-nightly-v1"), prehash);
+            assert!(!process_text_verify(
+                &mut "hello world".as_bytes(),
+                pk,
+                &sig,
+                &other
+            )?);
+            let none = with_context(None, prehash);
+            assert!(!process_text_verify(
+                &mut "hello world".as_bytes(),
+                pk,
+                &sig,
+                &none
+            )?);
+        }
+
+        let blake3 = SignOptions {
+            context: Some("rcli-release-v1".into()),
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        assert!(process_text_sign(&mut "hello world".as_bytes(), KEY, &blake3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_derive_context() -> Result<()> {
+        let with_context = |context: &str| SignOptions {
+            derive_context: Some(context.to_string()),
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        let signing = with_context("rcli test 2024 signing");
+        let sig = process_text_sign(&mut "hello world".as_bytes(), KEY, &signing)?;
+        let other = process_text_sign(
+            &mut "hello world".as_bytes(),
+            KEY,
+            &with_context("rcli test 2024 other"),
+        )?;
+        let plain = process_text_sign(
+            &mut "hello world".as_bytes(),
+            KEY,
+            &SignOptions::new(TextSignFormat::Blake3),
+        )?;
+        assert_ne!(sig, other);
+        assert_ne!(sig, plain);
+
+        assert!(process_text_verify(
+            &mut "hello world".as_bytes(),
+            KEY,
+            &sig,
+            &signing
+        )?);
+        let other = with_context("rcli test 2024 other");
+        assert!(!process_text_verify(
+            &mut "hello world".as_bytes(),
+            KEY,
+            &sig,
+            &other
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_prehash() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        let (sk, pk) = (&keys["ed25519.sk"], &keys["ed25519.pk"]);
+        let data = vec![0x5a; 16 * 1024 * 1024];
+        let options = SignOptions {
+            prehash: true,
+            ..SignOptions::new(TextSignFormat::Ed25519)
+        };
+
+        let sig = process_text_sign(&mut data.as_slice(), sk, &options)?;
+        assert!(process_text_verify(
+            &mut data.as_slice(),
+            pk,
+            &sig,
+            &options
+        )?);
+
+        let plain = SignOptions::new(TextSignFormat::Ed25519);
+        assert!(!process_text_verify(
+            &mut data.as_slice(),
+            pk,
+            &sig,
+            &plain
+        )?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_blake3_prehash() -> Result<()> {
+        let data = vec![0x5a; 16 * 1024 * 1024];
+        let options = SignOptions {
+            prehash: true,
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        let sig = process_text_sign(&mut data.as_slice(), KEY, &options)?;
+        assert!(process_text_verify(
+            &mut data.as_slice(),
+            KEY,
+            &sig,
+            &options
+        )?);
+
+        let digest = blake3::hash(&data);
+        assert_eq!(
+            sig,
+            blake3::keyed_hash(KEY.try_into()?, digest.as_bytes()).as_bytes()
+        );
+        let plain = SignOptions::new(TextSignFormat::Blake3);
+        assert!(!process_text_verify(
+            &mut data.as_slice(),
+            KEY,
+            &sig,
+            &plain
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_sig_hex() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        let options = SignOptions::new(TextSignFormat::Ed25519);
+        let sig = process_text_sign(&mut "hello world".as_bytes(), &keys["ed25519.sk"], &options)?;
+        let encoded = encode_sig(&sig, SigEncoding::Hex);
+        assert_eq!(encoded.len(), 128);
+        assert_eq!(decode_sig(&encoded, SigEncoding::Hex)?, sig);
+
+        let options = SignOptions::new(TextSignFormat::Blake3);
+        let sig = process_text_sign(&mut "hello world".as_bytes(), KEY, &options)?;
+        assert_eq!(encode_sig(&sig, SigEncoding::Hex).len(), 64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_sig_encodings() -> Result<()> {
+        let mut reader = std::io::Cursor::new("hello world");
+        let sig = process_text_sign(&mut reader, KEY, &SignOptions::new(TextSignFormat::Blake3))?;
+
+        let standard = STANDARD.encode(&sig);
+        assert_eq!(decode_sig(&standard, SigEncoding::Standard)?, sig);
+        let hex = hex::encode(&sig);
+        assert_eq!(decode_sig(&hex, SigEncoding::Hex)?, sig);
+        let url_safe = URL_SAFE_NO_PAD.encode(&sig);
+        assert_eq!(decode_sig(&url_safe, SigEncoding::UrlSafe)?, sig);
+
+        let err = decode_sig(&standard, SigEncoding::Hex).unwrap_err();
+        assert!(err.to_string().starts_with("signature is not valid hex"));
+        Ok(())
+    }
+
     #[test]
     fn test_process_text_verify() -> Result<()> {
         let mut reader = std::io::Cursor::new("hello world");
@@ -278,17 +1788,294 @@ mod tests {
 
         let format = TextSignFormat::Blake3;
 
-        let sig = process_text_sign(&mut reader1, KEY, format)?;
-        let ret = process_text_verify(&mut reader, KEY, &sig, format)?;
+        let sig = process_text_sign(&mut reader1, KEY, &SignOptions::new(format))?;
+        let ret = process_text_verify(&mut reader, KEY, &sig, &SignOptions::new(format))?;
+        assert!(ret);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_verify_ed25519_short_sig() -> Result<()> {
+        let pk = get_content("fixtures/ed25519.pub.pem")?;
+        let options = SignOptions {
+            key_format: KeyFormat::Pkcs8,
+            ..SignOptions::new(TextSignFormat::Ed25519)
+        };
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(!process_text_verify(&mut reader, &pk, &[0; 3], &options)?);
+
+        let frames = [&5u32.to_be_bytes()[..], b"hello"].concat();
+        let mut sigs = "0:AAAA\n".as_bytes();
+        let failed = process_verify_frames(&mut frames.as_slice(), &mut sigs, &pk, &options)?;
+        assert_eq!(failed, vec![0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_pkcs8() -> Result<()> {
+        let sk = get_content("fixtures/ed25519.pem")?;
+        let pk = get_content("fixtures/ed25519.pub.pem")?;
+        let mut reader = std::io::Cursor::new("hello world");
+        let mut reader1 = std::io::Cursor::new("hello world");
+
+        let options = SignOptions {
+            key_format: KeyFormat::Pkcs8,
+            ..SignOptions::new(TextSignFormat::Ed25519)
+        };
+
+        let sig = process_text_sign(&mut reader, &sk, &options)?;
+        let ret = process_text_verify(&mut reader1, &pk, &sig, &options)?;
         assert!(ret);
         Ok(())
     }
 
+    #[test]
+    fn test_combined_sig_round_trip() -> Result<()> {
+        let ed25519 = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        for (format, sk, pk) in [
+            (TextSignFormat::Blake3, KEY.to_vec(), KEY.to_vec()),
+            (
+                TextSignFormat::Ed25519,
+                ed25519["ed25519.sk"].clone(),
+                ed25519["ed25519.pk"].clone(),
+            ),
+        ] {
+            let mut reader = std::io::Cursor::new("hello world");
+            let sig = process_text_sign(&mut reader, &sk, &SignOptions::new(format))?;
+            let combined = format_combined_sig(format, &sig);
+            assert!(combined.starts_with(&format!("{}.", format)));
+
+            let (parsed, sig) = parse_combined_sig(&combined)?;
+            let mut reader = std::io::Cursor::new("hello world");
+            assert!(process_text_verify(
+                &mut reader,
+                &pk,
+                &sig,
+                &SignOptions::new(parsed)
+            )?);
+        }
+        assert!(parse_combined_sig("no-separator").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_key_info() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+        let info = process_key_info(
+            &keys["ed25519.pk"],
+            TextSignFormat::Ed25519,
+            KeyFormat::Raw,
+            false,
+        )?;
+        assert_eq!(info.len, 32);
+        assert_eq!(info.valid_point, Some(true));
+        assert_eq!(
+            info.fingerprint,
+            fingerprint(&keys["ed25519.pk"][b"rcli-key:ed25519\n".len()..])
+        );
+
+        // y = 2 has no x on the curve
+        let mut corrupted = [0u8; 32];
+        corrupted[0] = 2;
+        let info = process_key_info(&corrupted, TextSignFormat::Ed25519, KeyFormat::Raw, false)?;
+        assert_eq!(info.valid_point, Some(false));
+
+        // about half of all seeds are not valid points, so try a few generated secret keys
+        for _ in 0..16 {
+            let keys = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Raw)?;
+            let sk = &keys["ed25519.sk"];
+            let info = process_key_info(sk, TextSignFormat::Ed25519, KeyFormat::Raw, true)?;
+            assert_eq!(info.len, 32);
+            assert_eq!(info.valid_point, None);
+        }
+
+        let info = process_key_info(KEY, TextSignFormat::Blake3, KeyFormat::Raw, false)?;
+        assert_eq!(info.valid_point, None);
+        let short = &KEY[..31];
+        assert!(process_key_info(short, TextSignFormat::Blake3, KeyFormat::Raw, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_key_convert() -> Result<()> {
+        let raw = (0..32).collect::<Vec<u8>>();
+        let base64 = process_key_convert(&raw, KeyFormat::Raw, KeyFormat::Base64)?;
+        assert_eq!(base64, STANDARD.encode(&raw).into_bytes());
+        assert_eq!(
+            process_key_convert(&base64, KeyFormat::Base64, KeyFormat::Raw)?,
+            raw
+        );
+
+        let keys = process_text_key_generate(TextSignFormat::Blake3, KeyFormat::Hex)?;
+        let hex = &keys["blake3.txt"];
+        let converted = process_key_convert(hex, KeyFormat::Hex, KeyFormat::Raw)?;
+        assert!(converted.starts_with(b"rcli-key:blake3\n"));
+        assert_eq!(converted.len(), b"rcli-key:blake3\n".len() + 32);
+
+        assert!(process_key_convert(&raw[..31], KeyFormat::Raw, KeyFormat::Hex).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_key_generate_base64() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Ed25519, KeyFormat::Base64)?;
+        let (sk, pk) = (&keys["ed25519.sk"], &keys["ed25519.pk"]);
+        assert!(sk[b"rcli-key:ed25519\n".len()..].is_ascii());
+
+        let options = SignOptions {
+            key_format: KeyFormat::Base64,
+            strict_key: true,
+            ..SignOptions::new(TextSignFormat::Ed25519)
+        };
+        let mut reader = std::io::Cursor::new("hello world");
+        let sig = process_text_sign(&mut reader, sk, &options)?;
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(process_text_verify(&mut reader, pk, &sig, &options)?);
+
+        let keys = process_text_key_generate(TextSignFormat::Blake3, KeyFormat::Hex)?;
+        let key = &keys["blake3.txt"];
+        let options = SignOptions {
+            key_format: KeyFormat::Hex,
+            strict_key: true,
+            ..SignOptions::new(TextSignFormat::Blake3)
+        };
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(process_text_sign(&mut reader, key, &options).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_key_generate_chacha() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Chacha, KeyFormat::Base64)?;
+        let key = STANDARD.decode(&keys["chacha.key"])?;
+        let nonce = STANDARD.decode(&keys["chacha.nonce"])?;
+        assert_eq!(key.len(), 32);
+        assert_eq!(nonce.len(), 12);
+
+        // encrypt and decrypt take the base64 files as generated
+        let path = std::env::temp_dir().join(format!("rcli_chacha_nonce_{}", std::process::id()));
+        fs::write(&path, &keys["chacha.nonce"])?;
+        let nonce = NonceSource::File(path.display().to_string());
+        let file_key = read_chacha_key(&keys["chacha.key"]);
+        let ret = process_text_encrypt(&mut "hello".as_bytes(), &file_key, &nonce, b"").and_then(
+            |mut ciphertext| process_text_decrypt(&mut ciphertext, &file_key, &nonce, b""),
+        );
+        // the same base64 passed as a key directly is not decoded
+        let literal =
+            process_text_encrypt(&mut "hello".as_bytes(), &keys["chacha.key"], &nonce, b"");
+        fs::remove_file(&path)?;
+        assert_eq!(ret?, b"hello");
+        assert!(matches!(literal, Err(RcliError::BadKeyLength { .. })));
+        assert_eq!(read_chacha_key(&file_key), file_key);
+
+        let keys = process_text_key_generate(TextSignFormat::Chacha, KeyFormat::Raw)?;
+        let nonce = NonceSource::Counter {
+            base: keys["chacha.nonce"][..8].try_into()?,
+            index: u32::MAX,
+        };
+        assert!(Chacha2::try_new(&keys["chacha.key"], &nonce).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_chacha2_bad_key_length() {
+        let nonce = NonceSource::File("fixtures/chacha2.nonce".into());
+        match Chacha2::try_new([0u8; 10], &nonce) {
+            Err(RcliError::BadKeyLength { expected, actual }) => {
+                assert_eq!((expected, actual), (32, 10));
+            }
+            other => panic!("expected BadKeyLength, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_process_text_keys_generate() -> Result<()> {
+        let keys = process_text_keys_generate(TextSignFormat::Ed25519, 5, KeyFormat::Raw, None)?;
+        assert_eq!(keys.len(), 10);
+        for i in 0..5 {
+            assert!(keys.contains_key(&format!("ed25519.{}.sk", i)));
+            assert!(keys.contains_key(&format!("ed25519.{}.pk", i)));
+        }
+        assert_ne!(keys["ed25519.0.sk"], keys["ed25519.1.sk"]);
+
+        let keys = process_text_keys_generate(TextSignFormat::Blake3, 1, KeyFormat::Raw, None)?;
+        assert!(keys.contains_key("blake3.txt"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint() -> Result<()> {
+        assert_eq!(
+            fingerprint(b""),
+            "rxNJufX5oaagQE3qNtzJSZvLJcmtwRK3zJqTyuQfMmI"
+        );
+
+        let seed = Some([7; 32]);
+        let keys = process_text_keys_generate(TextSignFormat::Ed25519, 1, KeyFormat::Hex, seed)?;
+        let pk = &keys["ed25519.pk"];
+        let raw = hex::decode(&pk[b"rcli-key:ed25519\n".len()..])?;
+        assert_eq!(
+            fingerprint_key_file(pk, TextSignFormat::Ed25519, KeyFormat::Hex)?,
+            fingerprint(&raw)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_keys_generate_seed() -> Result<()> {
+        let seed = Some([7; 32]);
+        let keys = process_text_keys_generate(TextSignFormat::Ed25519, 1, KeyFormat::Hex, seed)?;
+        let again = process_text_keys_generate(TextSignFormat::Ed25519, 1, KeyFormat::Hex, seed)?;
+        assert_eq!(keys, again);
+        let other =
+            process_text_keys_generate(TextSignFormat::Ed25519, 1, KeyFormat::Hex, Some([8; 32]))?;
+        assert_ne!(keys["ed25519.sk"], other["ed25519.sk"]);
+
+        assert!(
+            process_text_keys_generate(TextSignFormat::Blake3, 1, KeyFormat::Raw, seed).is_err()
+        );
+        assert!(
+            process_text_keys_generate(TextSignFormat::Ed25519, 2, KeyFormat::Raw, seed).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_text_sign_strict_key() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::Blake3, KeyFormat::Raw)?;
+        let key = &keys["blake3.txt"];
+        assert!(key.starts_with(b"rcli-key:blake3\n"));
+
+        let strict = |format| SignOptions {
+            strict_key: true,
+            ..SignOptions::new(format)
+        };
+        let format = TextSignFormat::Blake3;
+        let mut reader = std::io::Cursor::new("hello world");
+        let sig = process_text_sign(&mut reader, key, &strict(format))?;
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(process_text_verify(
+            &mut reader,
+            key,
+            &sig,
+            &SignOptions::new(format)
+        )?);
+
+        let format = TextSignFormat::Ed25519;
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(process_text_sign(&mut reader, key, &strict(format)).is_err());
+
+        let format = TextSignFormat::Blake3;
+        let mut reader = std::io::Cursor::new("hello world");
+        assert!(process_text_sign(&mut reader, KEY, &strict(format)).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_process_encrypt() -> Result<()> {
         let mut content = std::io::Cursor::new("hello world");
         println!("key: {}", String::from_utf8(KEY.to_vec())?);
-        let ret = process_text_encrypt(&mut content, KEY, NONCE)?;
+        let ret = process_text_encrypt(&mut content, KEY, &nonce(), b"")?;
         let ret = URL_SAFE_NO_PAD.encode(ret);
         println!("encrypt:{:?}", ret);
         Ok(())
@@ -299,9 +2086,82 @@ mod tests {
         let encrypt = "pr_JZp0IXEYMYZrirfMUBSAhGRet_QAP2D7o".to_string();
         let mut content = URL_SAFE_NO_PAD.decode(encrypt)?;
         //let mut content = std::io::Cursor::new(content);
-        let ret = process_text_decrypt(&mut content, KEY, NONCE)?;
+        let ret = process_text_decrypt(&mut content, KEY, &nonce(), b"")?;
         let ret = String::from_utf8(ret)?;
         println!("{}", ret);
         Ok(())
     }
+
+    #[test]
+    fn test_process_decrypt_stream() -> Result<()> {
+        let plaintext = (0..4 * 1024 * 1024)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let encrypt = process_text_encrypt(&mut plaintext.as_slice(), KEY, &nonce(), b"")?;
+        let encoded = URL_SAFE_NO_PAD.encode(encrypt);
+
+        let mut decrypt = Vec::new();
+        process_text_decrypt_stream(&mut encoded.as_bytes(), KEY, &nonce(), b"", &mut decrypt)?;
+        assert_eq!(decrypt, plaintext);
+
+        // as `text encrypt` prints it
+        let printed = format!("encrypt:{}\n", encoded);
+        let mut decrypt = Vec::new();
+        process_text_decrypt_stream(&mut printed.as_bytes(), KEY, &nonce(), b"", &mut decrypt)?;
+        assert_eq!(decrypt, plaintext);
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_encrypt_recipient() -> Result<()> {
+        let keys = process_text_key_generate(TextSignFormat::X25519, KeyFormat::Raw)?;
+        let mut reader = "hello world".as_bytes();
+        let encrypted = process_text_encrypt_recipient(&mut reader, &keys["x25519.pk"], b"")?;
+        let decrypted = process_text_decrypt_identity(&encrypted, &keys["x25519.sk"], b"")?;
+        assert_eq!(decrypted, b"hello world");
+
+        let mut reader = "hello world".as_bytes();
+        let again = process_text_encrypt_recipient(&mut reader, &keys["x25519.pk"], b"")?;
+        assert_ne!(again, encrypted);
+
+        let other = process_text_key_generate(TextSignFormat::X25519, KeyFormat::Raw)?;
+        assert!(process_text_decrypt_identity(&encrypted, &other["x25519.sk"], b"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_encrypt_counter_nonce() -> Result<()> {
+        let base = *b"rcli-ctr";
+        let encrypt = |index| {
+            let nonce = NonceSource::Counter { base, index };
+            process_text_encrypt(&mut "hello world".as_bytes(), KEY, &nonce, b"")
+        };
+        let first = encrypt(0)?;
+        let second = encrypt(1)?;
+        assert_ne!(first, second);
+        assert!(encrypt(1).is_err());
+
+        let nonce = NonceSource::Counter { base, index: 1 };
+        let mut second = second;
+        assert_eq!(
+            process_text_decrypt(&mut second, KEY, &nonce, b"")?,
+            b"hello world"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_decrypt_aad() -> Result<()> {
+        let encrypt = process_text_encrypt(&mut "hello world".as_bytes(), KEY, &nonce(), b"a.txt")?;
+
+        let mut content = encrypt.clone();
+        let ret = process_text_decrypt(&mut content, KEY, &nonce(), b"a.txt")?;
+        assert_eq!(ret, b"hello world");
+
+        let mut content = encrypt.clone();
+        assert!(process_text_decrypt(&mut content, KEY, &nonce(), b"b.txt").is_err());
+        let mut content = encrypt;
+        assert!(process_text_decrypt(&mut content, KEY, &nonce(), b"").is_err());
+        Ok(())
+    }
 }
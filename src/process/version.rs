@@ -0,0 +1,31 @@
+use serde::Serialize;
+
+/// Version and build metadata, embedded at compile time by `build.rs`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git: &'static str,
+    pub rustc: &'static str,
+    pub target: &'static str,
+}
+
+pub fn process_version() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git: env!("VERGEN_GIT_SHA"),
+        rustc: env!("VERGEN_RUSTC_SEMVER"),
+        target: env!("VERGEN_CARGO_TARGET_TRIPLE"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_version() {
+        let info = process_version();
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.target.is_empty());
+    }
+}
@@ -1,18 +1,1127 @@
-use anyhow::{Ok, Result};
-use std::{fs::File, io::Read};
+use anyhow::Result;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Cursor, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
 
-pub fn get_reader(input: &str) -> Result<Box<dyn Read>> {
-    let reader: Box<dyn Read> = if input == "-" {
-        Box::new(std::io::stdin())
+use crate::{read_chacha_key, AppError, KeyArgs};
+
+static STDIN_TIMEOUT: Mutex<Option<Duration>> = Mutex::new(None);
+static TEE: Mutex<Option<File>> = Mutex::new(None);
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+static BUFFER_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_BUFFER_SIZE);
+
+/// With a timeout set, reading `-` from a terminal fails if nothing is typed in time, and a
+/// FIFO `--input` fails if no writer opens it and writes in time.
+pub fn set_stdin_timeout(timeout: Option<Duration>) {
+    *STDIN_TIMEOUT.lock().unwrap_or_else(|e| e.into_inner()) = timeout;
+}
+
+/// Size of the chunks the streaming encode, count and hash loops read at a time.
+pub fn set_buffer_size(size: usize) {
+    BUFFER_SIZE.store(size, Ordering::Relaxed);
+}
+
+pub fn buffer_size() -> usize {
+    BUFFER_SIZE.load(Ordering::Relaxed)
+}
+
+/// `io::copy`, but reading [`buffer_size`] bytes at a time.
+pub fn copy_buffered(reader: &mut dyn Read, writer: &mut dyn Write) -> io::Result<u64> {
+    let mut buf = vec![0u8; buffer_size()];
+    let mut total = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buf[..n])?;
+        total += n as u64;
+    }
+}
+
+/// Turn the first Ctrl-C into a flag the long loops poll with [`check_interrupted`], so they
+/// stop before writing anything; a second Ctrl-C exits at once.
+pub fn install_interrupt_handler() -> Result<()> {
+    let ret = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    });
+    match ret {
+        Ok(()) | Err(ctrlc::Error::MultipleHandlers) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn set_interrupted(interrupted: bool) {
+    INTERRUPTED.store(interrupted, Ordering::SeqCst);
+}
+
+pub fn check_interrupted() -> Result<()> {
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        return Err(AppError::Interrupted.into());
+    }
+    Ok(())
+}
+
+/// Copy everything later written through [`stdout`] into `path`, replacing its contents.
+pub fn set_tee(path: Option<&Path>) -> Result<()> {
+    let file = path.map(File::create).transpose()?;
+    *TEE.lock().unwrap_or_else(|e| e.into_inner()) = file;
+    Ok(())
+}
+
+/// Stdout, duplicated into the `--tee` file when one is set.
+pub fn stdout() -> TeeWriter<io::Stdout, TeeFile> {
+    TeeWriter::new(io::stdout(), TeeFile)
+}
+
+/// Writes everything to `primary` and then the same bytes to `copy`.
+pub struct TeeWriter<A, B> {
+    primary: A,
+    copy: B,
+}
+
+impl<A: Write, B: Write> TeeWriter<A, B> {
+    pub fn new(primary: A, copy: B) -> Self {
+        Self { primary, copy }
+    }
+}
+
+impl<A: Write, B: Write> Write for TeeWriter<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.primary.write(buf)?;
+        self.copy.write_all(&buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.primary.flush()?;
+        self.copy.flush()
+    }
+}
+
+/// The file set by [`set_tee`]; writes are dropped when there is none.
+pub struct TeeFile;
+
+impl Write for TeeFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match TEE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(file) => file.write(buf),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match TEE.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Per-input errors from a batch loop. Fail-fast unless `keep_going`, in which case failures
+/// are collected and reported together by [`BatchErrors::finish`].
+#[derive(Debug, Default)]
+pub struct BatchErrors {
+    keep_going: bool,
+    errors: Vec<(String, AppError)>,
+}
+
+impl BatchErrors {
+    pub fn new(keep_going: bool) -> Self {
+        Self {
+            keep_going,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Run one input's work; `None` means it failed and the error was kept for later.
+    pub fn run<T>(&mut self, input: &str, f: impl FnOnce() -> Result<T>) -> Result<Option<T>> {
+        match f() {
+            Ok(value) => Ok(Some(value)),
+            // an interrupt stops the whole batch, --keep-going or not
+            Err(e) if self.keep_going => match AppError::from(e) {
+                AppError::Interrupted => Err(AppError::Interrupted.into()),
+                e => {
+                    self.errors.push((input.to_string(), e));
+                    Ok(None)
+                }
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Print every collected error and fail, with the first error's kind, if there were any.
+    pub fn finish(self) -> Result<()> {
+        let Some((_, first)) = self.errors.first() else {
+            return Ok(());
+        };
+        for (input, e) in &self.errors {
+            eprintln!("{}: {}", input, e);
+        }
+        let msg = format!("{} input(s) failed", self.errors.len());
+        let e = match first {
+            AppError::Verification(_) => AppError::Verification(msg),
+            AppError::Input(_) => AppError::Input(msg),
+            AppError::Crypto(_) => AppError::Crypto(msg),
+            AppError::Other(_) => AppError::Other(msg),
+            AppError::Interrupted => AppError::Interrupted,
+        };
+        Err(e.into())
+    }
+}
+
+pub fn get_reader(input: &str) -> Result<Box<dyn BufRead>> {
+    let timeout = *STDIN_TIMEOUT.lock().unwrap_or_else(|e| e.into_inner());
+    get_reader_with_timeout(input, timeout)
+}
+
+fn get_reader_with_timeout(input: &str, timeout: Option<Duration>) -> Result<Box<dyn BufRead>> {
+    let reader: Box<dyn BufRead> = if input == "-" {
+        let stdin = BufReader::new(std::io::stdin());
+        match timeout {
+            Some(timeout) if std::io::stdin().is_terminal() => {
+                Box::new(wait_for_input(stdin, timeout)?)
+            }
+            _ => Box::new(stdin),
+        }
     } else {
-        Box::new(File::open(input)?)
+        match timeout {
+            Some(timeout) if is_fifo(input) => Box::new(open_fifo(input, timeout)?),
+            _ => Box::new(BufReader::new(File::open(input)?)),
+        }
     };
     Ok(reader)
 }
 
+#[cfg(unix)]
+fn is_fifo(path: &str) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    fs::metadata(path).is_ok_and(|meta| meta.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &str) -> bool {
+    false
+}
+
+/// Opening a FIFO blocks until a writer connects, so open it on a helper thread too.
+fn open_fifo(path: &str, timeout: Duration) -> Result<BufReader<File>> {
+    let (tx, rx) = mpsc::channel();
+    let owned = path.to_string();
+    thread::spawn(move || {
+        let _ = tx.send(File::open(owned).map(BufReader::new));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(reader) => wait_for_input(reader?, timeout),
+        Err(_) => Err(AppError::Input(format!("no writer opened {}", path)).into()),
+    }
+}
+
+/// Refuse to read `-` from an interactive terminal, where the command would sit waiting for
+/// input the user probably meant to pipe in. `--stdin-timeout` opts back into waiting.
+pub fn ensure_stdin_piped(input: &str, text: Option<&str>) -> Result<()> {
+    if STDIN_TIMEOUT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .is_some()
+    {
+        return Ok(());
+    }
+    check_stdin_piped(input, text, std::io::stdin().is_terminal())
+}
+
+fn check_stdin_piped(input: &str, text: Option<&str>, is_terminal: bool) -> Result<()> {
+    if input == "-" && text.is_none() && is_terminal {
+        return Err(AppError::Input(
+            "stdin is a terminal; pipe data in, or pass --input FILE or --text".into(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Wait up to `timeout` for the first bytes of `reader` on a helper thread. On timeout the thread
+/// stays blocked in `read`, which is fine since the command fails and the process exits.
+fn wait_for_input<R: BufRead + Send + 'static>(mut reader: R, timeout: Duration) -> Result<R> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let ret = reader.fill_buf().map(|_| ());
+        let _ = tx.send(ret.map(|_| reader));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(reader) => Ok(reader?),
+        Err(_) => Err(AppError::Input("no input on stdin".into()).into()),
+    }
+}
+
+/// Read from the `--text` literal when given, else from `input` (stdin when `None` or `-`).
+pub fn get_reader_for(input: Option<&str>, text: Option<&str>) -> Result<Box<dyn BufRead>> {
+    match text {
+        Some(text) => Ok(Box::new(Cursor::new(text.as_bytes().to_vec()))),
+        None => get_reader(input.unwrap_or("-")),
+    }
+}
+
+/// Read `R` with ASCII whitespace dropped, so wrapped or newline-terminated base64 can be
+/// decoded as it streams.
+pub struct SkipWhitespace<R>(pub R);
+
+impl<R: BufRead> Read for SkipWhitespace<R> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let buf = self.0.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            let (mut used, mut n) = (0, 0);
+            for &b in buf {
+                if n == out.len() {
+                    break;
+                }
+                used += 1;
+                if !b.is_ascii_whitespace() {
+                    out[n] = b;
+                    n += 1;
+                }
+            }
+            self.0.consume(used);
+            if n > 0 {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+/// Read at most `limit` bytes of `reader`, e.g. to preview the start of a large input.
+pub fn limit_reader(reader: Box<dyn BufRead>, limit: Option<u64>) -> Box<dyn BufRead> {
+    match limit {
+        Some(limit) => Box::new(reader.take(limit)),
+        None => reader,
+    }
+}
+
+pub fn get_text_reader(input: &str) -> Result<Box<dyn BufRead>> {
+    get_text_reader_for(Some(input), None)
+}
+
+/// Like `get_reader_for`, for text inputs: a leading UTF-8 byte order mark is discarded.
+pub fn get_text_reader_for(input: Option<&str>, text: Option<&str>) -> Result<Box<dyn BufRead>> {
+    let mut reader = get_reader_for(input, text)?;
+    skip_bom(&mut reader)?;
+    Ok(reader)
+}
+
+/// Discard a UTF-8 byte order mark (`EF BB BF`), as written by Excel, at the start of `reader`.
+pub fn skip_bom(reader: &mut dyn BufRead) -> Result<()> {
+    const BOM: &[u8] = b"\xef\xbb\xbf";
+    if reader.fill_buf()?.starts_with(BOM) {
+        reader.consume(BOM.len());
+    }
+    Ok(())
+}
+
+/// Read a signature as written by `text sign`, e.g. from `--sig-file -`; the `sig:` label is
+/// optional.
+pub fn read_sig(reader: &mut dyn Read) -> Result<String> {
+    let mut sig = String::new();
+    reader.read_to_string(&mut sig)?;
+    let sig = sig.trim();
+    Ok(sig.strip_prefix("sig:").unwrap_or(sig).to_string())
+}
+
+/// Build a batch output path from `template`, whose `{name}`, `{stem}`, `{ext}` and `{dir}`
+/// placeholders come from `input`: `{dir}/out/{stem}.json` maps `data/a.csv` to
+/// `data/out/a.json`.
+pub fn expand_output_template(template: &str, input: &Path) -> Result<PathBuf> {
+    let lossy = |s: Option<&std::ffi::OsStr>| {
+        s.map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let dir = match input.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_string_lossy(),
+        _ => ".".into(),
+    };
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("unclosed {{ in output template"))?
+            + start;
+        match &rest[start + 1..end] {
+            "name" => out.push_str(&lossy(input.file_name())),
+            "stem" => out.push_str(&lossy(input.file_stem())),
+            "ext" => out.push_str(&lossy(input.extension())),
+            "dir" => out.push_str(&dir),
+            other => anyhow::bail!(
+                "unknown placeholder {{{}}} in output template, expected name, stem, ext or dir",
+                other
+            ),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+/// Whether `input` should be expanded as a glob: it has glob metacharacters and isn't a real file.
+pub fn is_glob(input: &str) -> bool {
+    input.contains(['*', '?', '[']) && !Path::new(input).exists()
+}
+
+/// Expand a glob `input` into the files it matches, sorted; anything else is returned as is.
+pub fn expand_input(input: &str) -> Result<Vec<String>> {
+    if !is_glob(input) {
+        return Ok(vec![input.to_string()]);
+    }
+    let mut files = Vec::new();
+    for path in glob::glob(input)? {
+        let path = path?;
+        if path.is_file() {
+            files.push(path.display().to_string());
+        }
+    }
+    if files.is_empty() {
+        return Err(AppError::Input(format!("no files match {}", input)).into());
+    }
+    files.sort();
+    Ok(files)
+}
+
 pub fn get_content(input: &str) -> Result<Vec<u8>> {
     let mut reader = get_reader(input)?;
     let mut content = Vec::new();
     reader.read_to_end(&mut content)?;
     Ok(content)
 }
+
+/// Write `content` to `path` atomically: the bytes go to a temp file in the same
+/// directory which is then renamed into place, so readers never see a truncated file.
+/// On Unix an existing target keeps its permissions.
+pub fn write_atomic(path: impl AsRef<Path>, content: &[u8]) -> Result<()> {
+    atomic_write(path.as_ref(), None, |file| Ok(file.write_all(content)?))
+}
+
+/// Like `write_atomic`, but the content is streamed by `write`, whose result is returned; if it
+/// fails the temp file is removed and `path` is left as it was.
+pub fn write_atomic_with<T>(
+    path: impl AsRef<Path>,
+    write: impl FnOnce(&mut dyn Write) -> Result<T>,
+) -> Result<T> {
+    atomic_write(path.as_ref(), None, |file| {
+        let mut writer = io::BufWriter::new(file);
+        let ret = write(&mut writer)?;
+        writer.flush()?;
+        Ok(ret)
+    })
+}
+
+/// Like `write_atomic`, but the file is readable and writable only by the owner on Unix.
+pub fn write_private(path: impl AsRef<Path>, content: &[u8]) -> Result<()> {
+    atomic_write(path.as_ref(), Some(0o600), |file| {
+        Ok(file.write_all(content)?)
+    })
+}
+
+fn atomic_write<T>(
+    path: &Path,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut File) -> Result<T>,
+) -> Result<T> {
+    let tmp = temp_path(path)?;
+    let ret = write_temp(&tmp, path, mode, write).and_then(|ret| {
+        fs::rename(&tmp, path)?;
+        Ok(ret)
+    });
+    if ret.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    ret
+}
+
+fn temp_path(path: &Path) -> Result<PathBuf> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("invalid output path: {}", path.display()))?;
+    let tmp = format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    );
+    Ok(path.with_file_name(tmp))
+}
+
+fn write_temp<T>(
+    tmp: &Path,
+    path: &Path,
+    mode: Option<u32>,
+    write: impl FnOnce(&mut File) -> Result<T>,
+) -> Result<T> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode.unwrap_or(0o666));
+    }
+    let mut file = options.open(tmp)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = mode.or_else(|| fs::metadata(path).ok().map(|m| m.permissions().mode()));
+        if let Some(mode) = mode {
+            file.set_permissions(fs::Permissions::from_mode(mode))?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    let ret = write(&mut file)?;
+    file.sync_all()?;
+    Ok(ret)
+}
+
+pub const KEY_ENV: &str = "RCLI_KEY";
+
+/// Check that `dir` is a directory to write into, creating it first if `create` is set.
+pub fn prepare_output_dir(dir: &Path, create: bool) -> Result<()> {
+    if create {
+        fs::create_dir_all(dir)?;
+    } else if !dir.is_dir() {
+        return Err(AppError::Input(format!(
+            "{} does not exist or is not a directory (pass --create-dirs to create it)",
+            dir.display()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Resolve the bytes of a `--key` argument.
+///
+/// Precedence is flag > env: `env:VARNAME` reads `$VARNAME`, `keyring:service/account` reads
+/// the OS keyring (with the `keyring` feature), `@-` prompts for the key on a TTY (or reads the
+/// first line of piped stdin), any other value is handed to `load` (a key file for sign/verify,
+/// the literal key for encrypt/decrypt), and an omitted `--key` falls back to `$RCLI_KEY`.
+pub fn resolve_key(
+    key: Option<&str>,
+    load: impl FnOnce(&str) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    match key {
+        Some("@-") => read_key_stdin(),
+        Some(key) => {
+            if let Some(var) = key.strip_prefix("env:") {
+                read_key_env(var)
+            } else if let Some(spec) = key.strip_prefix("keyring:") {
+                let (service, account) = spec.split_once('/').ok_or_else(|| {
+                    anyhow::anyhow!("keyring keys look like keyring:service/account")
+                })?;
+                read_key_keyring(service, account)
+            } else {
+                load(key)
+            }
+        }
+        None => read_key_env(KEY_ENV),
+    }
+}
+
+/// Resolve `--key-file`/`--key-literal` (or the deprecated `--key`, read as a literal) to key bytes.
+/// `quiet` silences the deprecation warning.
+pub fn resolve_key_args(args: &KeyArgs, quiet: bool) -> Result<Vec<u8>> {
+    let literal = |key: &str| Ok(key.as_bytes().to_vec());
+    match (args.key_file.as_slice(), &args.key_literal, &args.key) {
+        ([file], _, _) => resolve_key(Some(file), get_content),
+        ([_, _, ..], _, _) => anyhow::bail!("only verify accepts more than one --key-file"),
+        ([], Some(key), _) => resolve_key(Some(key), literal),
+        ([], None, Some(key)) => {
+            if !quiet {
+                eprintln!("warning: --key is deprecated, use --key-literal or --key-file");
+            }
+            resolve_key(Some(key), literal)
+        }
+        ([], None, None) => resolve_key(None, literal),
+    }
+}
+
+/// Like `resolve_key_args`, for chacha: a key file may hold the base64 `text generate` writes,
+/// while a literal key is used byte for byte.
+pub fn resolve_chacha_key_args(args: &KeyArgs, quiet: bool) -> Result<Vec<u8>> {
+    match args.key_file.as_slice() {
+        [file] => resolve_key(Some(file), get_chacha_key),
+        _ => resolve_key_args(args, quiet),
+    }
+}
+
+/// Read a chacha key file, raw or base64.
+pub fn get_chacha_key(path: &str) -> Result<Vec<u8>> {
+    Ok(read_chacha_key(&get_content(path)?))
+}
+
+/// Like `resolve_key_args`, but resolves every repeated `--key-file`, each with its name.
+pub fn resolve_key_args_all(args: &KeyArgs, quiet: bool) -> Result<Vec<(String, Vec<u8>)>> {
+    if args.key_file.len() < 2 {
+        let name = args.key_file.first().map_or("key", String::as_str);
+        return Ok(vec![(name.to_string(), resolve_key_args(args, quiet)?)]);
+    }
+    args.key_file
+        .iter()
+        .map(|file| Ok((file.clone(), resolve_key(Some(file), get_content)?)))
+        .collect()
+}
+
+fn read_key_env(var: &str) -> Result<Vec<u8>> {
+    let key = std::env::var(var)
+        .map_err(|_| anyhow::anyhow!("no --key given and ${} is not set", var))?;
+    Ok(key.into_bytes())
+}
+
+fn read_key_stdin() -> Result<Vec<u8>> {
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        let key = rpassword::prompt_password("key: ")?;
+        Ok(key.into_bytes())
+    } else {
+        read_key_line(&mut stdin.lock())
+    }
+}
+
+#[cfg(feature = "keyring")]
+pub fn read_key_keyring(service: &str, account: &str) -> Result<Vec<u8>> {
+    keyring_secret(&keyring::Entry::new(service, account)?)
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_secret(entry: &keyring::Entry) -> Result<Vec<u8>> {
+    match entry.get_secret() {
+        Ok(secret) => Ok(secret),
+        Err(keyring::Error::NoEntry) => Err(anyhow::anyhow!("no key stored in the keyring")),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(feature = "keyring")]
+pub fn store_key_keyring(service: &str, account: &str, key: &[u8]) -> Result<()> {
+    keyring::Entry::new(service, account)?.set_secret(key)?;
+    Ok(())
+}
+
+#[cfg(feature = "keyring")]
+pub fn delete_key_keyring(service: &str, account: &str) -> Result<()> {
+    keyring::Entry::new(service, account)?.delete_credential()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn read_key_keyring(_service: &str, _account: &str) -> Result<Vec<u8>> {
+    anyhow::bail!("keyring support is not enabled; rebuild with --features keyring")
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store_key_keyring(_service: &str, _account: &str, _key: &[u8]) -> Result<()> {
+    anyhow::bail!("keyring support is not enabled; rebuild with --features keyring")
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn delete_key_keyring(_service: &str, _account: &str) -> Result<()> {
+    anyhow::bail!("keyring support is not enabled; rebuild with --features keyring")
+}
+
+/// Put `text` on the system clipboard (requires the `clipboard` feature).
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut clipboard =
+        arboard::Clipboard::new().map_err(|e| anyhow::anyhow!("clipboard unavailable: {}", e))?;
+    clipboard
+        .set_text(text)
+        .map_err(|e| anyhow::anyhow!("failed to copy to clipboard: {}", e))?;
+    Ok(())
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_text: &str) -> Result<()> {
+    anyhow::bail!("clipboard support is not enabled; rebuild with --features clipboard")
+}
+
+/// Render `text` as a QR code made of ASCII characters (requires the `qr` feature).
+#[cfg(feature = "qr")]
+pub fn render_qr(text: &str) -> Result<String> {
+    let code = qr_code(text)?;
+    Ok(code
+        .render::<char>()
+        .dark_color('#')
+        .light_color(' ')
+        .module_dimensions(2, 1)
+        .build())
+}
+
+/// Write `text` as a QR code PNG image to `path` (requires the `qr` feature).
+#[cfg(feature = "qr")]
+pub fn write_qr_png(text: &str, path: &Path) -> Result<()> {
+    let image = qr_code(text)?.render::<image::Luma<u8>>().build();
+    image
+        .save(path)
+        .map_err(|e| anyhow::anyhow!("failed to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Built at error correction level L, which leaves the most room for data.
+#[cfg(feature = "qr")]
+fn qr_code(text: &str) -> Result<qrcode::QrCode> {
+    let level = qrcode::EcLevel::L;
+    qrcode::QrCode::with_error_correction_level(text.as_bytes(), level).map_err(|e| match e {
+        qrcode::types::QrError::DataTooLong => anyhow::anyhow!(
+            "{} bytes is too long for a QR code; the largest version holds 2953 bytes",
+            text.len()
+        ),
+        e => anyhow::anyhow!("failed to build QR code: {}", e),
+    })
+}
+
+#[cfg(not(feature = "qr"))]
+pub fn render_qr(_text: &str) -> Result<String> {
+    anyhow::bail!("QR support is not enabled; rebuild with --features qr")
+}
+
+#[cfg(not(feature = "qr"))]
+pub fn write_qr_png(_text: &str, _path: &Path) -> Result<()> {
+    anyhow::bail!("QR support is not enabled; rebuild with --features qr")
+}
+
+/// The `crc32:<hex>` line `--with-checksum` appends after the encoded output `data`.
+pub fn checksum_line(data: &[u8]) -> String {
+    format!("crc32:{:08x}", crc32fast::hash(data))
+}
+
+/// Split a trailing `crc32:<hex>` line off `content` and check it against the text before it,
+/// without trailing whitespace or the `label` (`sig:`, `encode:`) that stdout output carries
+/// but the checksum doesn't cover. Content without such a line is returned unchanged.
+pub fn strip_checksum<'a>(content: &'a str, label: &str) -> Result<&'a str> {
+    let trimmed = content.trim_end();
+    let Some((body, expected)) = trimmed
+        .rsplit_once('\n')
+        .and_then(|(body, last)| Some((body.trim(), last.trim().strip_prefix("crc32:")?)))
+    else {
+        return Ok(content);
+    };
+    let body = body.strip_prefix(label).unwrap_or(body);
+    let actual = checksum_line(body.as_bytes());
+    if !actual[6..].eq_ignore_ascii_case(expected) {
+        return Err(AppError::Verification(format!(
+            "checksum mismatch: expected crc32:{}, got {}",
+            expected, actual
+        ))
+        .into());
+    }
+    Ok(body)
+}
+
+/// Passes writes through to `inner` while computing their CRC32, for streamed output.
+pub struct Crc32Writer<W> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+}
+
+impl<W: Write> Crc32Writer<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// The `crc32:<hex>` line for everything written so far.
+    pub fn finish(self) -> String {
+        format!("crc32:{:08x}", self.hasher.finalize())
+    }
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub fn read_key_line(reader: &mut dyn BufRead) -> Result<Vec<u8>> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let key = line.trim_end_matches(['\r', '\n']);
+    if key.is_empty() {
+        return Err(anyhow::anyhow!("no key found on stdin"));
+    }
+    Ok(key.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_input() -> Result<()> {
+        assert_eq!(
+            expand_input("fixtures/b*.txt")?,
+            ["fixtures/b64.txt", "fixtures/blake3.txt"]
+        );
+        assert_eq!(expand_input("-")?, ["-"]);
+        assert!(expand_input("fixtures/*.missing").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_sig() -> Result<()> {
+        let mut stdin = std::io::Cursor::new("aGVsbG8\n");
+        assert_eq!(read_sig(&mut stdin)?, "aGVsbG8");
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reader_for_text() -> Result<()> {
+        let mut content = String::new();
+        get_reader_for(Some("fixtures/blake3.txt"), Some("hello"))?.read_to_string(&mut content)?;
+        assert_eq!(content, "hello");
+
+        let mut content = Vec::new();
+        get_reader_for(Some("fixtures/blake3.txt"), None)?.read_to_end(&mut content)?;
+        assert_eq!(content, fs::read("fixtures/blake3.txt")?);
+        Ok(())
+    }
+
+    struct SlowReader;
+
+    impl Read for SlowReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            thread::sleep(Duration::from_secs(5));
+            Ok(0)
+        }
+    }
+
+    #[test]
+    fn test_wait_for_input() -> Result<()> {
+        let mut reader = wait_for_input(&b"piped"[..], Duration::from_secs(1))?;
+        assert_eq!(reader.fill_buf()?, b"piped");
+        let empty = wait_for_input(&b""[..], Duration::from_secs(1))?;
+        assert!(empty.is_empty());
+
+        let slow = wait_for_input(BufReader::new(SlowReader), Duration::from_millis(10));
+        assert_eq!(
+            slow.err().map(|e| e.to_string()),
+            Some("no input on stdin".into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reader_is_buffered() -> Result<()> {
+        let mut reader = get_reader("Cargo.toml")?;
+        assert!(reader.fill_buf()?.starts_with(b"[package]"));
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        assert_eq!(line, "[package]\n");
+        Ok(())
+    }
+
+    /// Sets an environment variable for one test and puts the old value back on drop.
+    struct EnvGuard {
+        var: &'static str,
+        old: Option<String>,
+    }
+
+    impl EnvGuard {
+        fn set(var: &'static str, value: &str) -> Self {
+            let old = std::env::var(var).ok();
+            std::env::set_var(var, value);
+            Self { var, old }
+        }
+    }
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            match &self.old {
+                Some(value) => std::env::set_var(self.var, value),
+                None => std::env::remove_var(self.var),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_key_from_env() -> Result<()> {
+        let _guard = EnvGuard::set("RCLI_TEST_KEY", "key-from-env");
+        let key = resolve_key(Some("env:RCLI_TEST_KEY"), get_content)?;
+        assert_eq!(key, b"key-from-env");
+
+        let _guard = EnvGuard::set(KEY_ENV, "default-key");
+        let key = resolve_key(None, get_content)?;
+        assert_eq!(key, b"default-key");
+
+        let key = resolve_key(Some("fixtures/blake3.txt"), get_content)?;
+        assert_eq!(key, get_content("fixtures/blake3.txt")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_key_line() -> Result<()> {
+        let mut reader = std::io::Cursor::new("secret-key\nhello world");
+        assert_eq!(read_key_line(&mut reader)?, b"secret-key");
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest)?;
+        assert_eq!(rest, "hello world");
+
+        let mut reader = std::io::Cursor::new("");
+        assert!(read_key_line(&mut reader).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_reader_fifo_without_writer() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("rcli_fifo_{}", std::process::id()));
+        let status = std::process::Command::new("mkfifo").arg(&path).status()?;
+        assert!(status.success());
+        let path_str = path.to_str().expect("temp path is utf8");
+        let ret = get_reader_with_timeout(path_str, Some(Duration::from_millis(100)));
+        fs::remove_file(&path)?;
+        let err = ret.err().expect("a FIFO with no writer times out");
+        assert!(err.to_string().contains("no writer"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee_writer() -> Result<()> {
+        let mut tee = TeeWriter::new(Vec::new(), Vec::new());
+        write!(tee, "sig:")?;
+        tee.write_all(&[0, 159, 146, 150])?;
+        tee.flush()?;
+        assert_eq!(tee.primary, b"sig:\x00\x9f\x92\x96");
+        assert_eq!(tee.primary, tee.copy);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_output_template() -> Result<()> {
+        let input = Path::new("data/reports/q1.csv");
+        let expand = |template| expand_output_template(template, input);
+        assert_eq!(expand("{stem}.{ext}")?, PathBuf::from("q1.csv"));
+        assert_eq!(
+            expand("{dir}/out/{name}")?,
+            PathBuf::from("data/reports/out/q1.csv")
+        );
+        assert_eq!(expand("{stem}.json")?, PathBuf::from("q1.json"));
+        assert_eq!(
+            expand_output_template("{dir}/{stem}.b64", Path::new("key"))?,
+            PathBuf::from("./key.b64")
+        );
+        assert!(expand("{base}.json").is_err());
+        assert!(expand("{stem.json").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_reader() -> Result<()> {
+        use base64::{engine::general_purpose::STANDARD, Engine};
+
+        let content = fs::read("Cargo.toml")?;
+        let mut reader = limit_reader(get_reader("Cargo.toml")?, Some(48));
+        let encoded = crate::process_encode(&mut reader, crate::Base64Format::Standard)?;
+        assert_eq!(encoded, STANDARD.encode(&content[..48]));
+
+        let mut reader = limit_reader(get_reader("Cargo.toml")?, None);
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all)?;
+        assert_eq!(all, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_stdin_piped() {
+        let err = check_stdin_piped("-", None, true).unwrap_err();
+        assert_eq!(AppError::from(err).exit_code(), 2);
+        assert!(check_stdin_piped("-", None, false).is_ok());
+        assert!(check_stdin_piped("-", Some("hi"), true).is_ok());
+        assert!(check_stdin_piped("Cargo.toml", None, true).is_ok());
+    }
+
+    #[test]
+    fn test_write_atomic() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("rcli_write_atomic_{}.txt", std::process::id()));
+        write_atomic(&path, b"first")?;
+        write_atomic(&path, b"second")?;
+        assert_eq!(fs::read(&path)?, b"second");
+        assert!(!temp_path(&path)?.exists());
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic_cleans_up_on_error() -> Result<()> {
+        // renaming a file over a directory fails after the temp file was written
+        let path =
+            std::env::temp_dir().join(format!("rcli_write_atomic_dir_{}", std::process::id()));
+        fs::create_dir_all(&path)?;
+        assert!(write_atomic(&path, b"content").is_err());
+        assert!(!temp_path(&path)?.exists());
+        fs::remove_dir(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic_with_keeps_target_on_error() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("rcli_write_atomic_with_{}.txt", std::process::id()));
+        write_atomic(&path, b"original")?;
+        let ret: Result<()> = write_atomic_with(&path, |writer| {
+            writer.write_all(b"partial")?;
+            anyhow::bail!("stream failed")
+        });
+        assert!(ret.is_err());
+        assert_eq!(fs::read(&path)?, b"original");
+        assert!(!temp_path(&path)?.exists());
+
+        write_atomic_with(&path, |writer| Ok(writer.write_all(b"streamed")?))?;
+        assert_eq!(fs::read(&path)?, b"streamed");
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path =
+            std::env::temp_dir().join(format!("rcli_write_private_{}.txt", std::process::id()));
+        write_private(&path, b"secret")?;
+        let mode = std::fs::metadata(&path)?.permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read(&path)?, b"secret");
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_key_args_file_and_literal() -> Result<()> {
+        let key = "iCfTwZ7jtMV*@FXZzEE&KCB#SXn7eGCE";
+        let path =
+            std::env::temp_dir().join(format!("rcli_resolve_key_args_{}.txt", std::process::id()));
+        fs::write(&path, key)?;
+        let from_file = resolve_key_args(
+            &KeyArgs {
+                key_file: vec![path.display().to_string()],
+                key_literal: None,
+                key: None,
+            },
+            false,
+        )?;
+        let from_literal = resolve_key_args(
+            &KeyArgs {
+                key_file: vec![],
+                key_literal: Some(key.to_string()),
+                key: None,
+            },
+            false,
+        )?;
+        let from_alias = resolve_key_args(
+            &KeyArgs {
+                key_file: vec![],
+                key_literal: None,
+                key: Some(key.to_string()),
+            },
+            true,
+        )?;
+        fs::remove_file(&path)?;
+        assert_eq!(from_file, key.as_bytes());
+        assert_eq!(from_file, from_literal);
+        assert_eq!(from_literal, from_alias);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_key_missing_env() {
+        assert!(resolve_key(Some("env:RCLI_TEST_MISSING_KEY"), get_content).is_err());
+    }
+
+    #[cfg(feature = "keyring")]
+    #[test]
+    fn test_keyring_secret_with_mock() -> Result<()> {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        let entry = keyring::Entry::new("rcli-test", "alice")?;
+        let err = keyring_secret(&entry).unwrap_err();
+        assert_eq!(err.to_string(), "no key stored in the keyring");
+
+        entry.set_secret(b"0123456789abcdef0123456789abcdef")?;
+        assert_eq!(keyring_secret(&entry)?, b"0123456789abcdef0123456789abcdef");
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_checksum() -> Result<()> {
+        let blob = "aGVsbG8gd29ybGQ=";
+        let signed = format!("{}\n{}\n", blob, checksum_line(blob.as_bytes()));
+        assert_eq!(strip_checksum(&signed, "encode:")?, blob);
+        assert_eq!(
+            strip_checksum(&format!("encode:{}", signed), "encode:")?,
+            blob
+        );
+        assert_eq!(strip_checksum(blob, "encode:")?, blob);
+
+        let tampered = signed.replacen("d29y", "d29z", 1);
+        let err = strip_checksum(&tampered, "encode:").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref(),
+            Some(AppError::Verification(_))
+        ));
+
+        let mut writer = Crc32Writer::new(Vec::new());
+        writer.write_all(blob.as_bytes())?;
+        assert_eq!(writer.finish(), checksum_line(blob.as_bytes()));
+        Ok(())
+    }
+
+    #[cfg(feature = "qr")]
+    #[test]
+    fn test_render_qr() -> Result<()> {
+        let qr = render_qr("yBAiZ0ZLnWjL2bKqJXgfEfYqSkKfQ4DnQw")?;
+        assert!(!qr.is_empty());
+        assert!(qr.contains('#'));
+
+        let too_long = "a".repeat(8000);
+        assert!(render_qr(&too_long).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_key_bad_keyring_spec() {
+        let err = resolve_key(Some("keyring:no-account"), get_content).unwrap_err();
+        assert!(err.to_string().contains("keyring:service/account"));
+    }
+}
@@ -0,0 +1,748 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Command, Stdio},
+};
+
+/// The built binary with `--no-config`, so a local config file can't change the results.
+fn rcli_command() -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_rcli"));
+    cmd.arg("--no-config");
+    cmd
+}
+
+fn rcli(args: &[&str]) -> Vec<u8> {
+    let output = rcli_command()
+        .args(args)
+        .output()
+        .expect("failed to run rcli");
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+fn test_no_newline() {
+    let args = ["base64", "encode", "-i", "fixtures/blake3.txt"];
+    let with_newline = rcli(&args);
+    let without_newline = rcli(&[&args[..], &["--no-newline"]].concat());
+    assert_eq!(with_newline.last(), Some(&b'\n'));
+    assert_eq!(with_newline.len(), without_newline.len() + 1);
+    assert_eq!(&with_newline[..without_newline.len()], &without_newline[..]);
+}
+
+#[test]
+fn test_sign_text_literal() {
+    let args = ["text", "sign", "--key-file", "fixtures/blake3.key"];
+    let literal = rcli(&[&args[..], &["--text", "hello world"]].concat());
+
+    let mut child = rcli_command()
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run rcli");
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin
+        .write_all(b"hello world")
+        .expect("failed to write stdin");
+    drop(stdin);
+    let piped = child.wait_with_output().expect("failed to run rcli");
+    assert!(piped.status.success());
+    assert_eq!(literal, piped.stdout);
+
+    let sig = String::from_utf8(literal).expect("sig is utf8");
+    let sig = sig.trim().trim_start_matches("sig:");
+    let verify = [
+        "text",
+        "verify",
+        "--key-file",
+        "fixtures/blake3.key",
+        "--text",
+        "hello world",
+        "--sig",
+        sig,
+    ];
+    assert_eq!(rcli(&verify), b"verified\n");
+
+    let conflict = rcli_command()
+        .args([&args[..], &["--text", "hi", "-i", "fixtures/b64.txt"]].concat())
+        .output()
+        .expect("failed to run rcli");
+    assert!(!conflict.status.success());
+}
+
+#[test]
+fn test_hash_glob() {
+    let output = String::from_utf8(rcli(&["hash", "-i", "fixtures/b*.txt"])).expect("utf8");
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("fixtures/b64.txt: "));
+    assert!(lines[1].starts_with("fixtures/blake3.txt: "));
+
+    let single = String::from_utf8(rcli(&["hash", "-i", "fixtures/b64.txt"])).expect("utf8");
+    assert_eq!(lines[0], format!("fixtures/b64.txt: {}", single.trim()));
+}
+
+#[test]
+fn test_compare_exit_code() {
+    let compare = |b: &str| {
+        rcli_command()
+            .args(["compare", "--a", "fixtures/blake3.txt", "--b", b])
+            .output()
+            .expect("failed to run rcli")
+    };
+    let same = compare("fixtures/blake3.txt");
+    assert!(same.status.success());
+    assert!(same.stdout.is_empty());
+
+    let different = compare("fixtures/b64.txt");
+    assert_eq!(different.status.code(), Some(1));
+    assert!(!different.stderr.is_empty());
+}
+
+#[test]
+fn test_quiet_genpass() {
+    let output = rcli_command()
+        .args(["genpass", "-u", "-n", "--quiet"])
+        .output()
+        .expect("failed to run rcli");
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert_eq!(output.stdout.len(), 17);
+}
+
+#[test]
+fn test_quiet_csv_skipped_rows() -> anyhow::Result<()> {
+    let output = std::env::temp_dir().join(format!("rcli_quiet_{}.json", std::process::id()));
+    let result = rcli_command()
+        .args([
+            "csv",
+            "-i",
+            "fixtures/ragged.csv",
+            "--on-error",
+            "skip",
+            "--quiet",
+            "-o",
+        ])
+        .arg(&output)
+        .output()?;
+    std::fs::remove_file(&output)?;
+    assert!(result.status.success());
+    assert!(result.stderr.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_csv_stats() {
+    let output = std::env::temp_dir().join(format!("rcli_stats_{}.csv", std::process::id()));
+    let result = rcli_command()
+        .args(["csv", "-i", "fixtures/status.csv", "--stats", "-o"])
+        .arg(&output)
+        .output()
+        .expect("failed to run rcli");
+    assert!(result.status.success());
+    let bytes_out = std::fs::metadata(&output).unwrap().len();
+    std::fs::remove_file(&output).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&result.stderr),
+        format!("rows: 6, columns: 2, bytes_out: {}\n", bytes_out)
+    );
+}
+
+#[test]
+fn test_error_format_json() -> anyhow::Result<()> {
+    let output = rcli_command()
+        .args(["--error-format", "json", "csv", "-i", "fixtures/status.csv"])
+        .args(["-o", "no-such-dir/out.json"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+    let err: serde_json::Value = serde_json::from_slice(&output.stderr)?;
+    assert_eq!(err["kind"], "input");
+    assert!(err["error"].is_string());
+    Ok(())
+}
+
+#[test]
+fn test_generate_create_dirs() -> anyhow::Result<()> {
+    let root = std::env::temp_dir().join(format!("rcli_create_dirs_{}", std::process::id()));
+    let nested = root.join("keys/ci");
+    let generate = |extra: &[&str]| {
+        rcli_command()
+            .args(["text", "generate", "--format", "blake3", "-o"])
+            .arg(&nested)
+            .args(extra)
+            .output()
+            .expect("failed to run rcli")
+    };
+    assert_eq!(generate(&[]).status.code(), Some(2));
+    assert!(!nested.exists());
+
+    assert!(generate(&["--create-dirs"]).status.success());
+    assert!(nested.join("blake3.txt").exists());
+    // without --output-template a rerun overwrites the previous keys
+    assert!(generate(&[]).status.success());
+    std::fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn test_stdin_timeout_empty_pipe() -> anyhow::Result<()> {
+    let output = rcli_command()
+        .args(["--stdin-timeout", "1", "base64", "encode"])
+        .stdin(Stdio::null())
+        .output()?;
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"encode:\n");
+    Ok(())
+}
+
+#[test]
+fn test_verify_multiple_keys() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_verify_keys_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let (old, new) = (dir.join("old.key"), dir.join("new.key"));
+    std::fs::write(&old, [1u8; 32])?;
+    std::fs::write(&new, [2u8; 32])?;
+    let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+
+    let sig = rcli(&["text", "sign", "-i", "fixtures/b64.txt", "--key-file", new]);
+    let sig = String::from_utf8(sig)?;
+    let sig = sig.trim().trim_start_matches("sig:");
+    let verify = |keys: &[&str]| {
+        let mut cmd = rcli_command();
+        cmd.args(["text", "verify", "-i", "fixtures/b64.txt", "--sig", sig]);
+        for key in keys {
+            cmd.args(["--key-file", key]);
+        }
+        cmd.output().expect("failed to run rcli")
+    };
+
+    let both = verify(&[old, new]);
+    assert!(both.status.success());
+    assert!(String::from_utf8(both.stderr)?.contains("new.key"));
+    assert_eq!(verify(&[old]).status.code(), Some(1));
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_broken_pipe_exits_cleanly() -> anyhow::Result<()> {
+    let input = std::env::temp_dir().join(format!("rcli_broken_pipe_{}", std::process::id()));
+    std::fs::write(&input, vec![0x5a; 4 * 1024 * 1024])?;
+    let mut child = rcli_command()
+        .args(["base64", "split", "--chunk-bytes", "1024", "-i"])
+        .arg(&input)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut first = String::new();
+    stdout.read_line(&mut first)?;
+    drop(stdout);
+
+    let output = child.wait_with_output()?;
+    std::fs::remove_file(&input)?;
+    assert!(first.starts_with("000:"));
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_encrypt_raw_round_trip() -> anyhow::Result<()> {
+    let key = "iCfTwZ7jtMV*@FXZzEE&KCB#SXn7eGCE";
+    let output = std::env::temp_dir().join(format!("rcli_raw_{}.bin", std::process::id()));
+    let output_str = output.to_str().expect("temp path is utf8");
+    let encrypt = rcli(&[
+        "text",
+        "encrypt",
+        "--key-literal",
+        key,
+        "--text",
+        "hello world",
+        "--output",
+        output_str,
+        "--raw",
+    ]);
+    assert!(encrypt.is_empty());
+    // 11 bytes of plaintext plus the 16-byte tag, not base64
+    assert_eq!(std::fs::read(&output)?.len(), 27);
+
+    let decrypt = rcli(&[
+        "text",
+        "decrypt",
+        "--key-literal",
+        key,
+        "-i",
+        output_str,
+        "--raw",
+    ]);
+    std::fs::remove_file(&output)?;
+    assert_eq!(decrypt, b"decrypt:hello world\n");
+    Ok(())
+}
+
+#[test]
+fn test_generate_chacha_round_trip() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_chacha_{}", std::process::id()));
+    let dir_str = dir.to_str().expect("temp path is utf8");
+    rcli(&[
+        "text",
+        "generate",
+        "--format",
+        "chacha",
+        "--output-path",
+        dir_str,
+        "--create-dirs",
+    ]);
+    let key = dir.join("chacha.key");
+    let nonce = dir.join("chacha.nonce");
+    let output = dir.join("hello.bin");
+    let (key, nonce, output) = (
+        key.to_str().expect("temp path is utf8"),
+        nonce.to_str().expect("temp path is utf8"),
+        output.to_str().expect("temp path is utf8"),
+    );
+    rcli(&[
+        "text",
+        "encrypt",
+        "--key-file",
+        key,
+        "--nonce",
+        nonce,
+        "--text",
+        "hello world",
+        "--output",
+        output,
+        "--raw",
+    ]);
+    let decrypt = rcli(&[
+        "text",
+        "decrypt",
+        "--key-file",
+        key,
+        "--nonce",
+        nonce,
+        "-i",
+        output,
+        "--raw",
+    ]);
+    // the file's base64 typed as a literal is 44 bytes, not a key
+    let literal = std::fs::read_to_string(key)?;
+    let status = rcli_command()
+        .args(["text", "encrypt", "--key-literal", literal.trim()])
+        .args(["--nonce", nonce, "--text", "hello world"])
+        .output()?
+        .status;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(decrypt, b"decrypt:hello world\n");
+    assert!(!status.success());
+    Ok(())
+}
+
+#[test]
+fn test_rekey_generates_nonce() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_rekey_{}", std::process::id()));
+    let dir_str = dir.to_str().expect("temp path is utf8");
+    rcli(&[
+        "text",
+        "generate",
+        "--format",
+        "chacha",
+        "--output-path",
+        dir_str,
+        "--create-dirs",
+    ]);
+    let encrypt = rcli(&[
+        "text",
+        "encrypt",
+        "--key-file",
+        "fixtures/blake3.key",
+        "--text",
+        "hello world",
+    ]);
+    let input = dir.join("hello.enc");
+    std::fs::write(&input, &encrypt["encrypt:".len()..])?;
+    let key = dir.join("chacha.key");
+    let (input, key) = (
+        input.to_str().expect("temp path is utf8"),
+        key.to_str().expect("temp path is utf8"),
+    );
+    let rekey = rcli(&[
+        "text",
+        "rekey",
+        "-i",
+        input,
+        "--old-key",
+        "fixtures/blake3.key",
+        "--new-key",
+        key,
+    ]);
+    let nonce = format!("{}.nonce", input);
+    assert_ne!(
+        std::fs::read(&nonce)?,
+        std::fs::read("fixtures/chacha2.nonce")?
+    );
+    std::fs::write(input, &rekey["rekey:".len()..])?;
+    let decrypt = rcli(&[
+        "text",
+        "decrypt",
+        "--key-file",
+        key,
+        "--nonce",
+        &nonce,
+        "-i",
+        input,
+    ]);
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(decrypt, b"decrypt:hello world\n");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_generate_secret_key_mode() -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = std::env::temp_dir().join(format!("rcli_key_mode_{}", std::process::id()));
+    rcli(&[
+        "text",
+        "generate",
+        "--format",
+        "ed25519",
+        "--output-path",
+        dir.to_str().expect("temp path is utf8"),
+        "--create-dirs",
+    ]);
+    let mode = std::fs::metadata(dir.join("ed25519.sk"))?
+        .permissions()
+        .mode();
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(mode & 0o777, 0o600);
+    Ok(())
+}
+
+#[test]
+fn test_tee() -> anyhow::Result<()> {
+    let tee = std::env::temp_dir().join(format!("rcli_tee_{}.txt", std::process::id()));
+    let stdout = rcli(&[
+        "base64",
+        "encode",
+        "-i",
+        "fixtures/blake3.txt",
+        "--tee",
+        tee.to_str().expect("temp path is utf8"),
+    ]);
+    let copy = std::fs::read(&tee)?;
+    std::fs::remove_file(&tee)?;
+    assert!(stdout.starts_with(b"encode:"));
+    assert_eq!(copy, stdout);
+    Ok(())
+}
+
+#[test]
+fn test_decode_with_checksum() -> anyhow::Result<()> {
+    let path = std::env::temp_dir().join(format!("rcli_crc_{}.b64", std::process::id()));
+    let path_str = path.to_str().expect("temp path is utf8");
+    rcli(&[
+        "base64",
+        "encode",
+        "-i",
+        "fixtures/blake3.txt",
+        "-o",
+        path_str,
+        "--with-checksum",
+    ]);
+    let decoded = rcli(&["base64", "decode", "-i", path_str, "--with-checksum"]);
+    assert_eq!(decoded, std::fs::read("fixtures/blake3.txt")?);
+
+    let encoded = std::fs::read_to_string(&path)?;
+    let flipped = if encoded.starts_with('A') { "B" } else { "A" };
+    std::fs::write(&path, format!("{}{}", flipped, &encoded[1..]))?;
+    let status = rcli_command()
+        .args(["base64", "decode", "-i", path_str, "--with-checksum"])
+        .output()?
+        .status;
+    std::fs::remove_file(&path)?;
+    assert_eq!(status.code(), Some(1));
+    Ok(())
+}
+
+#[test]
+fn test_decode_output_keeps_target_on_error() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_decode_atomic_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let output = dir.join("out.bin");
+    std::fs::write(&output, "original")?;
+    let status = rcli_command()
+        .args(["base64", "decode", "--text", "aGVsbG8gd29ybGQ*", "-o"])
+        .arg(&output)
+        .output()?
+        .status;
+    let kept = std::fs::read_to_string(&output)?;
+    let entries = std::fs::read_dir(&dir)?.count();
+    std::fs::remove_dir_all(&dir)?;
+    assert!(!status.success());
+    assert_eq!(kept, "original");
+    assert_eq!(entries, 1);
+    Ok(())
+}
+
+#[test]
+fn test_csv_recursive_output_template() -> anyhow::Result<()> {
+    let out = std::env::temp_dir().join(format!("rcli_csv_template_{}", std::process::id()));
+    let out_str = out.to_str().expect("temp path is utf8");
+    rcli(&[
+        "csv",
+        "-i",
+        "fixtures/csvdir",
+        "--recursive",
+        "--output-dir",
+        out_str,
+        "--output-template",
+        "{dir}/{stem}.converted.json",
+    ]);
+    assert!(out.join("a.converted.json").is_file());
+    assert!(out.join("nested/b.converted.json").is_file());
+    std::fs::remove_dir_all(&out)?;
+    Ok(())
+}
+
+#[test]
+fn test_base64_batch_output_template() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_b64_template_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("a.txt"), "hello")?;
+    std::fs::write(dir.join("b.txt"), "world")?;
+    let glob = dir.join("*.txt");
+    rcli(&[
+        "base64",
+        "encode",
+        "-i",
+        glob.to_str().expect("temp path is utf8"),
+        "--output-template",
+        "{dir}/encoded/{stem}.b64",
+    ]);
+    assert_eq!(
+        std::fs::read_to_string(dir.join("encoded/a.b64"))?,
+        "aGVsbG8="
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("encoded/b.b64"))?,
+        "d29ybGQ="
+    );
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_generate_output_template() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_gen_template_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let dir_str = dir.to_str().expect("temp path is utf8");
+    let generate = |template: &str| {
+        rcli_command()
+            .args(["text", "generate", "--format", "ed25519", "-o", dir_str])
+            .args(["--output-template", template])
+            .output()
+            .expect("failed to run rcli")
+    };
+    let collide = generate("key.bin");
+    assert!(!collide.status.success());
+    assert!(String::from_utf8_lossy(&collide.stderr).contains("maps both"));
+    assert!(!dir.join("key.bin").exists());
+
+    assert!(generate("{stem}.prod.{ext}").status.success());
+    assert!(dir.join("ed25519.prod.sk").is_file());
+    assert!(dir.join("ed25519.prod.pk").is_file());
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_sign_verify_sig_file_stdin() -> anyhow::Result<()> {
+    let sig = rcli(&[
+        "text",
+        "sign",
+        "--key-file",
+        "fixtures/blake3.key",
+        "--text",
+        "hello world",
+    ]);
+    let verify = |extra: &[&str]| -> anyhow::Result<std::process::Output> {
+        let mut child = rcli_command()
+            .args(["text", "verify", "--key-file", "fixtures/blake3.key"])
+            .args(["--sig-file", "-"])
+            .args(extra)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(&sig)?;
+        Ok(child.wait_with_output()?)
+    };
+    let verified = verify(&["--text", "hello world"])?;
+    assert!(verified.status.success());
+    assert_eq!(verified.stdout, b"verified\n");
+
+    let both = verify(&[])?;
+    assert_eq!(both.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&both.stderr).contains("can't both read stdin"));
+    Ok(())
+}
+
+#[test]
+fn test_verify_dir_signed_sidecars() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_verify_dir_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    for name in ["a.txt", "b.txt"] {
+        let path = dir.join(name);
+        std::fs::write(&path, name)?;
+        let sig = rcli(&[
+            "text",
+            "sign",
+            "--key-file",
+            "fixtures/blake3.key",
+            "-i",
+            path.to_str().expect("temp path is utf8"),
+        ]);
+        std::fs::write(dir.join(format!("{}.sig", name)), sig)?;
+    }
+    let output = rcli_command()
+        .args([
+            "text",
+            "verify-dir",
+            "--key-file",
+            "fixtures/blake3.key",
+            "--dir",
+        ])
+        .arg(&dir)
+        .output()?;
+    std::fs::remove_dir_all(&dir)?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout, "a.txt: OK\nb.txt: OK\n");
+    Ok(())
+}
+
+#[test]
+fn test_with_checksum_stdout_round_trip() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_crc_stdout_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let sig_path = dir.join("hello.sig");
+    let sig = rcli(&[
+        "text",
+        "sign",
+        "--key-file",
+        "fixtures/blake3.key",
+        "--text",
+        "hello",
+        "--with-checksum",
+    ]);
+    assert!(String::from_utf8(sig.clone())?.contains("\ncrc32:"));
+    std::fs::write(&sig_path, sig)?;
+    let verified = rcli(&[
+        "text",
+        "verify",
+        "--key-file",
+        "fixtures/blake3.key",
+        "--text",
+        "hello",
+        "--sig-file",
+        sig_path.to_str().expect("temp path is utf8"),
+    ]);
+    assert_eq!(verified, b"verified\n");
+
+    let b64_path = dir.join("hello.b64");
+    let encoded = rcli(&["base64", "encode", "--text", "hello", "--with-checksum"]);
+    std::fs::write(&b64_path, &encoded)?;
+    let b64_str = b64_path.to_str().expect("temp path is utf8");
+    let decoded = rcli(&["base64", "decode", "-i", b64_str, "--with-checksum"]);
+    assert_eq!(decoded, b"hello");
+
+    let tampered = String::from_utf8(encoded)?.replacen("aGVs", "aGVt", 1);
+    std::fs::write(&b64_path, tampered)?;
+    let status = rcli_command()
+        .args(["base64", "decode", "-i", b64_str, "--with-checksum"])
+        .output()?
+        .status;
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(status.code(), Some(1));
+    Ok(())
+}
+
+/// The `Examples:` lines of `rcli <cmd> --help`.
+fn help_examples(cmd: &str) -> anyhow::Result<Vec<String>> {
+    let help = String::from_utf8(rcli(&[cmd, "--help"]))?;
+    Ok(help
+        .lines()
+        .skip_while(|line| *line != "Examples:")
+        .skip(1)
+        .take_while(|line| line.starts_with("  rcli "))
+        .map(|line| line.trim().to_string())
+        .collect())
+}
+
+/// Run a help example through `sh` with the built `rcli` first on `PATH`.
+fn run_example(example: &str, dir: &std::path::Path) -> anyhow::Result<std::process::Output> {
+    let bin = std::path::Path::new(env!("CARGO_BIN_EXE_rcli"));
+    let path = match std::env::var_os("PATH") {
+        Some(path) => std::env::join_paths(
+            std::iter::once(bin.parent().expect("binary has a dir").to_path_buf())
+                .chain(std::env::split_paths(&path)),
+        )?,
+        None => bin.parent().expect("binary has a dir").into(),
+    };
+    // the examples can't pass --no-config, so point the config directory somewhere empty
+    Ok(Command::new("sh")
+        .args(["-c", example])
+        .current_dir(dir)
+        .env("PATH", &path)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .output()?)
+}
+
+#[test]
+fn test_csv_help_examples_run() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_csv_examples_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::copy("assets/juventus.csv", dir.join("players.csv"))?;
+    let examples = help_examples("csv")?;
+    assert_eq!(examples.len(), 3);
+    for example in &examples {
+        let output = run_example(example, &dir)?;
+        assert!(output.status.success(), "{} failed", example);
+    }
+    let written = ["output.json", "players.yaml", "players.scsv"].map(|name| dir.join(name));
+    let all_written = written.iter().all(|path| path.is_file());
+    let semicolons = std::fs::read_to_string(&written[2])?
+        .lines()
+        .next()
+        .map(|h| h.contains(';'));
+    std::fs::remove_dir_all(&dir)?;
+    assert!(all_written);
+    assert_eq!(semicolons, Some(true));
+    Ok(())
+}
+
+#[test]
+fn test_genpass_help_examples_run() -> anyhow::Result<()> {
+    let dir = std::env::temp_dir().join(format!("rcli_genpass_examples_{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let examples = help_examples("genpass")?;
+    let outputs = examples
+        .iter()
+        .map(|example| run_example(example, &dir))
+        .collect::<anyhow::Result<Vec<_>>>();
+    std::fs::remove_dir_all(&dir)?;
+    assert_eq!(examples.len(), 2);
+    for (example, output) in examples.iter().zip(outputs?) {
+        assert!(output.status.success(), "{} failed", example);
+        assert!(!output.stdout.is_empty());
+    }
+    Ok(())
+}
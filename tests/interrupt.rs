@@ -0,0 +1,57 @@
+// The interrupt flag is process-wide, so this lives in its own test binary.
+use std::io::{self, Read};
+
+use clap::Parser;
+use rcli::{
+    check_interrupted, process_csv_reader, set_interrupted, AppError, BatchErrors, CsvOpts,
+    OutputFormat,
+};
+
+/// Hands out one line per `read`, raising the interrupt flag once `after` lines are out, as a
+/// Ctrl-C arriving partway through the input would.
+struct InterruptingReader {
+    lines: Vec<String>,
+    next: usize,
+    after: usize,
+}
+
+impl Read for InterruptingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.next == self.after {
+            set_interrupted(true);
+        }
+        let Some(line) = self.lines.get(self.next) else {
+            return Ok(0);
+        };
+        buf[..line.len()].copy_from_slice(line.as_bytes());
+        self.next += 1;
+        Ok(line.len())
+    }
+}
+
+#[test]
+fn test_interrupted_csv_stops_mid_loop() -> anyhow::Result<()> {
+    let mut lines = vec!["id,name\n".to_string()];
+    lines.extend((0..100).map(|i| format!("{},player{}\n", i, i)));
+    let mut reader = InterruptingReader {
+        lines,
+        next: 0,
+        after: 10,
+    };
+    let opts = CsvOpts::try_parse_from(["csv", "-i", "-"])?;
+
+    let ret = process_csv_reader(&mut reader, OutputFormat::Json, &opts);
+    let err = ret.unwrap_err();
+    assert_eq!(err.to_string(), "interrupted");
+    assert_eq!(AppError::from(&err).exit_code(), 130);
+    // the loop noticed the flag on the next record instead of draining the input
+    assert!(reader.next < reader.lines.len());
+
+    // --keep-going collects per-input failures, but an interrupt still stops the batch
+    let mut errors = BatchErrors::new(true);
+    let ret = errors.run("input.csv", check_interrupted);
+    set_interrupted(false);
+    assert_eq!(AppError::from(ret.unwrap_err()), AppError::Interrupted);
+    assert!(errors.is_empty());
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use clap::Parser;
+use rcli::{
+    run, AppError, ErrorFormat, GenPassFormat, GenPassOpts, Opts, SubCommand, DEFAULT_BUFFER_SIZE,
+};
+
+#[test]
+fn test_run_genpass() -> anyhow::Result<()> {
+    let output = std::env::temp_dir().join(format!("rcli_run_genpass_{}", std::process::id()));
+    let opts = Opts {
+        cmd: SubCommand::GenPass(GenPassOpts {
+            cmd: None,
+            length: 24,
+            has_uppercase: true,
+            has_lowercase: true,
+            has_number: true,
+            has_symbol: false,
+            output: Some(output.clone()),
+            clipboard: false,
+            output_format: GenPassFormat::Plain,
+            name: "PASSWORD".into(),
+            show_bits: false,
+        }),
+        no_newline: false,
+        quiet: false,
+        no_config: true,
+        error_format: ErrorFormat::Text,
+        stdin_timeout: None,
+        audit_log: None,
+        tee: None,
+        keep_going: false,
+        buffer_size: DEFAULT_BUFFER_SIZE,
+    };
+    run(opts)?;
+
+    let password = std::fs::read_to_string(&output)?;
+    std::fs::remove_file(&output)?;
+    assert_eq!(password.len(), 24);
+    assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+    Ok(())
+}
+
+#[test]
+fn test_run_audit_log() -> anyhow::Result<()> {
+    let log = std::env::temp_dir().join(format!("rcli_audit_{}.jsonl", std::process::id()));
+    let log_str = log.to_str().expect("temp path is utf8");
+    let args = ["rcli", "--audit-log", log_str, "--no-config"];
+    run(Opts::try_parse_from(
+        [&args[..], &["count", "-i", "Cargo.toml"]].concat(),
+    )?)?;
+    let failed = ["compare", "--a", "Cargo.toml", "--b", "README.md"];
+    assert!(run(Opts::try_parse_from([&args[..], &failed].concat())?).is_err());
+
+    let audit = std::fs::read_to_string(&log)?;
+    std::fs::remove_file(&log)?;
+    let lines = audit
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["command"], "count");
+    assert_eq!(lines[0]["input"], "Cargo.toml");
+    assert_eq!(lines[0]["success"], true);
+    assert_eq!(lines[1]["command"], "compare");
+    assert_eq!(lines[1]["success"], false);
+    assert!(lines.iter().all(|line| line["timestamp"].is_u64()));
+    Ok(())
+}
+
+#[test]
+fn test_run_audit_log_failure_keeps_result() -> anyhow::Result<()> {
+    let log = std::env::temp_dir()
+        .join(format!("rcli_audit_missing_{}", std::process::id()))
+        .join("audit.jsonl");
+    let log_str = log.to_str().expect("temp path is utf8");
+    let args = ["rcli", "--audit-log", log_str, "--no-config"];
+    run(Opts::try_parse_from(
+        [&args[..], &["count", "-i", "Cargo.toml"]].concat(),
+    )?)?;
+
+    let failed = ["compare", "--a", "Cargo.toml", "--b", "README.md"];
+    let err = run(Opts::try_parse_from([&args[..], &failed].concat())?).unwrap_err();
+    assert_eq!(AppError::from(err).exit_code(), 1);
+    assert!(!log.exists());
+    Ok(())
+}